@@ -185,6 +185,8 @@ fn bench_patterns<T: Ord + std::fmt::Debug>(
 
     // Custom patterns designed to find worst case performance.
     let mut extra_pattern_providers: Vec<(&'static str, fn(usize) -> Vec<i32>)> = vec![
+        ("all_equal", patterns::all_equal),
+        ("random_d4", |len| patterns::random_uniform(len, 0..4)),
         ("random_d20_start_block", |len| {
             let mut v = patterns::random_uniform(len, 0..20);
             let loop_end = std::cmp::min(len, 100);
@@ -334,6 +336,22 @@ fn bench_patterns<T: Ord + std::fmt::Debug>(
         ("random_s70", |len| patterns::random_sorted(len, 70.0)),
         ("random_s90", |len| patterns::random_sorted(len, 90.0)),
         ("random_s99", |len| patterns::random_sorted(len, 99.0)),
+        ("descending_s90", |len| {
+            let mut v = patterns::random_sorted(len, 90.0);
+            let sorted_len = ((len as f64) * 0.9).round() as usize;
+            v[..sorted_len].reverse();
+
+            v
+        }),
+        ("ascending_moved_block", |len| {
+            // Sorted, except for a block of 10% of the elements that was moved from 1/4 to 3/4 of
+            // the slice.
+            let mut v = patterns::ascending(len);
+            let (block_start, block_len) = (len / 4, len / 10);
+            v[block_start..((len * 3) / 4)].rotate_left(block_len);
+
+            v
+        }),
     ];
 
     if env::var("EXTRA_PATTERNS").is_ok() {
@@ -367,6 +385,16 @@ fn bench_patterns<T: Ord + std::fmt::Debug>(
             stable::rust_std::SortImpl,
         );
 
+        bench_impl(
+            c,
+            test_size,
+            transform_name,
+            &transform,
+            pattern_name,
+            pattern_provider,
+            stable::rust_powersort::SortImpl,
+        );
+
         #[cfg(feature = "cpp_std_sys")]
         bench_impl(
             c,
@@ -622,6 +650,17 @@ fn bench_patterns<T: Ord + std::fmt::Debug>(
             sort_comp::other::rust_radsort::SortImpl,
         );
 
+        #[cfg(feature = "rust_lsd_radix")]
+        bench_impl(
+            c,
+            test_size,
+            transform_name,
+            &transform,
+            pattern_name,
+            pattern_provider,
+            sort_comp::other::rust_lsd_radix::SortImpl,
+        );
+
         #[cfg(feature = "cpp_simdsort")]
         bench_impl(
             c,
@@ -644,6 +683,17 @@ fn bench_patterns<T: Ord + std::fmt::Debug>(
             sort_comp::other::cpp_vqsort::SortImpl,
         );
 
+        #[cfg(feature = "cpp_boost_spreadsort")]
+        bench_impl(
+            c,
+            test_size,
+            transform_name,
+            &transform,
+            pattern_name,
+            pattern_provider,
+            sort_comp::other::cpp_boost_spreadsort::SortImpl,
+        );
+
         #[cfg(feature = "cpp_intel_avx512")]
         bench_impl(
             c,
@@ -797,6 +847,17 @@ fn bench_patterns<T: Ord + std::fmt::Debug>(
             pattern_provider,
             sort_comp::other::small_sort::sort10_unstable_ptr_select::SortImpl,
         );
+
+        #[cfg(feature = "ipnsort_variants")]
+        bench_impl(
+            c,
+            test_size,
+            transform_name,
+            &transform,
+            pattern_name,
+            pattern_provider,
+            sort_comp::other::ipnsort_variants::guaranteed::SortImpl,
+        );
     }
 }
 
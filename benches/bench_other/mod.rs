@@ -6,6 +6,78 @@ pub mod partition_point;
 #[cfg(feature = "partition")]
 pub mod partition;
 
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_array;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_pairs;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_strided;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_fallback;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_suffix;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_by_natural;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_heap;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_collect;
+
+#[cfg(feature = "pod_bytes")]
+pub mod sort_pod_bytes;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_each;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod small_sort_strategy;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_same_paths;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_exact_network;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_large_elements;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_coupled;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_by_bool_key;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod partition_lomuto;
+
+#[cfg(all(feature = "ipnsort_variants", feature = "rust_lsd_radix"))]
+pub mod sort_radix;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_segments;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_mixed_direction;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_by_priority;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_small_word;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod sort_floats;
+
+#[cfg(feature = "ipnsort_variants")]
+pub mod rotation_merge;
+
 #[allow(unused)]
 pub fn bench_other<T: Ord + std::fmt::Debug>(
     c: &mut Criterion,
@@ -37,6 +109,270 @@ pub fn bench_other<T: Ord + std::fmt::Debug>(
         pattern_name,
         pattern_provider,
     );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_array::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_pairs::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_strided::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_fallback::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_suffix::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_by_natural::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_heap::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_collect::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "pod_bytes")]
+    sort_pod_bytes::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_each::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    small_sort_strategy::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_same_paths::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_exact_network::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_large_elements::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_coupled::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_by_bool_key::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    partition_lomuto::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(all(feature = "ipnsort_variants", feature = "rust_lsd_radix"))]
+    sort_radix::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_segments::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_mixed_direction::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_by_priority::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_small_word::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    sort_floats::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
+
+    #[cfg(feature = "ipnsort_variants")]
+    rotation_merge::bench(
+        c,
+        filter_arg,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+    );
 }
 
 pub mod util;
@@ -0,0 +1,63 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Partitions around the first element with the Lomuto, block and fulcrum partition of ipnsort, to
+// find the crossover used for `LOMUTO_MAX_BYTES`.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if test_size < 2 {
+        return;
+    }
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_partition_lomuto",
+        |v: &mut [T]| {
+            let (pivot, rest) = v.split_first_mut().unwrap();
+            rust_ipnsort::partition_lomuto_by(rest, pivot, &mut |a, b| a < b);
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_partition_blocks",
+        |v: &mut [T]| {
+            let (pivot, rest) = v.split_first_mut().unwrap();
+            rust_ipnsort::partition_blocks_by(rest, pivot, &mut |a, b| a < b);
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_partition_fulcrum",
+        |v: &mut [T]| {
+            let (pivot, rest) = v.split_first_mut().unwrap();
+            rust_ipnsort::partition_fulcrum_by(rest, pivot, &mut |a, b| a < b);
+        },
+    );
+}
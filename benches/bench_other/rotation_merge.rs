@@ -0,0 +1,59 @@
+use criterion::Criterion;
+
+use sort_comp::stable::rust_std;
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Merges two sorted halves of `String`, where moves are cheap and comparisons are not. The
+// vendored stable sort finds the two runs and does a single buffered merge of them.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "string" {
+        return;
+    }
+
+    let transform_fn = &(sorted_halves as fn(Vec<i32>) -> Vec<String>);
+
+    bench_fn(
+        c,
+        test_size,
+        "string",
+        transform_fn,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_rotation_merge",
+        |v: &mut [String]| rust_ipnsort::rotation_merge(v, v.len() / 2, &mut |a, b| a < b),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "string",
+        transform_fn,
+        pattern_name,
+        pattern_provider,
+        "rust_std_stable_buffered_merge",
+        |v: &mut [String]| rust_std::sort(v),
+    );
+}
+
+fn sorted_halves(values: Vec<i32>) -> Vec<String> {
+    let mut v = values
+        .iter()
+        .map(|val| format!("{:010}", val.saturating_abs()))
+        .collect::<Vec<_>>();
+
+    let mid = v.len() / 2;
+    v[..mid].sort();
+    v[mid..].sort();
+
+    v
+}
@@ -0,0 +1,37 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort::{self, SmallSort};
+
+use crate::bench_other::util::bench_fn;
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if !(transform_name == "i32" || transform_name == "string") {
+        return;
+    }
+
+    for (strategy_name, strategy) in [
+        ("insertion", SmallSort::Insertion),
+        ("network", SmallSort::Network),
+        ("indirect_merge", SmallSort::IndirectMerge),
+        ("auto", SmallSort::Auto),
+    ] {
+        bench_fn(
+            c,
+            test_size,
+            transform_name,
+            transform,
+            pattern_name,
+            pattern_provider,
+            &format!("rust_ipnsort_small_sort_{strategy_name}"),
+            |v: &mut [T]| rust_ipnsort::sort_by_with_small_sort(v, |a, b| a.cmp(b), strategy),
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+const ARRAY_LEN: usize = 8;
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" || test_size != ARRAY_LEN {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "std_sort_unstable_array",
+        |v: &mut [i32]| {
+            let mut arr: [i32; ARRAY_LEN] = v.try_into().unwrap();
+            arr.sort_unstable();
+            v.copy_from_slice(&arr);
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_array",
+        |v: &mut [i32]| {
+            let arr: [i32; ARRAY_LEN] = v.try_into().unwrap();
+            v.copy_from_slice(&rust_ipnsort::sort_array(arr));
+        },
+    );
+}
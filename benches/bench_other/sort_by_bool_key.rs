@@ -0,0 +1,58 @@
+use criterion::Criterion;
+
+use sort_comp::stable::rust_std;
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Sorts by whether the value is even, with the stable partition and with stable and unstable
+// comparison sorts on the same key.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+    let key = |val: &i32| val % 2 == 0;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by_bool_key",
+        |v: &mut [i32]| rust_ipnsort::sort_by_bool_key(v, key),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_std_stable_sort_by_bool",
+        |v: &mut [i32]| rust_std::sort_by(v, |a, b| key(a).cmp(&key(b))),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by_bool",
+        |v: &mut [i32]| rust_ipnsort::sort_by(v, |a, b| key(a).cmp(&key(b))),
+    );
+}
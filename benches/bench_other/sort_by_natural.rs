@@ -0,0 +1,53 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Quantifies what `sort_by(v, Ord::cmp)` costs compared to `sort(v)`, which compares with `lt`.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "u64" {
+        return;
+    }
+
+    // Same mapping as the u64 transform in bench.rs.
+    let transform: fn(Vec<i32>) -> Vec<u64> = |values| {
+        values
+            .iter()
+            .map(|val| {
+                let x = ((*val as i64) + (i32::MAX as i64) + 1) as u64;
+                x.checked_mul(i32::MAX as u64).unwrap()
+            })
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort",
+        |v: &mut [u64]| rust_ipnsort::sort(v),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by_ord_cmp",
+        |v: &mut [u64]| rust_ipnsort::sort_by(v, Ord::cmp),
+    );
+}
@@ -0,0 +1,179 @@
+use std::cmp::Ordering;
+
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// 64 bytes per task, with 56 byte payloads for the heavy variants. The payloads are never read,
+// they only give the elements their size.
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+enum Task {
+    Urgent { deadline: u64, data: [u64; 6] },
+    High { deadline: u64, name: String },
+    Normal { id: u32, data: [u64; 6] },
+    Idle(u64),
+}
+
+fn priority(task: &Task) -> u8 {
+    match task {
+        Task::Urgent { .. } => 0,
+        Task::High { .. } => 1,
+        Task::Normal { .. } => 2,
+        Task::Idle(_) => 3,
+    }
+}
+
+fn compare_payload(a: &Task, b: &Task) -> Ordering {
+    match (a, b) {
+        (Task::Urgent { deadline: x, .. }, Task::Urgent { deadline: y, .. }) => x.cmp(y),
+        (
+            Task::High {
+                deadline: x,
+                name: name_x,
+            },
+            Task::High {
+                deadline: y,
+                name: name_y,
+            },
+        ) => x.cmp(y).then_with(|| name_x.cmp(name_y)),
+        (Task::Normal { id: x, .. }, Task::Normal { id: y, .. }) => x.cmp(y),
+        (Task::Idle(x), Task::Idle(y)) => x.cmp(y),
+        _ => Ordering::Equal,
+    }
+}
+
+// Only needed by `bench_fn`, the benchmarks pass their comparisons explicitly.
+impl PartialEq for Task {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Task {}
+
+impl PartialOrd for Task {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Task {
+    fn cmp(&self, other: &Self) -> Ordering {
+        priority(self)
+            .cmp(&priority(other))
+            .then_with(|| compare_payload(self, other))
+    }
+}
+
+// Groups the tasks by priority in-place with a counting sort over the precomputed priorities,
+// then sorts each group by payload.
+fn sort_grouped(v: &mut [Task]) {
+    let mut priorities = v.iter().map(priority).collect::<Vec<_>>();
+
+    let mut counts = [0usize; 256];
+    for &p in &priorities {
+        counts[p as usize] += 1;
+    }
+
+    let mut starts = [0usize; 257];
+    for p in 0..256 {
+        starts[p + 1] = starts[p] + counts[p];
+    }
+
+    let mut next = starts;
+    for p in 0..256 {
+        while next[p] < starts[p + 1] {
+            let other = priorities[next[p]] as usize;
+            if other == p {
+                next[p] += 1;
+            } else {
+                v.swap(next[p], next[other]);
+                priorities.swap(next[p], next[other]);
+                next[other] += 1;
+            }
+        }
+    }
+
+    for p in 0..256 {
+        rust_ipnsort::sort_by(&mut v[starts[p]..starts[p + 1]], compare_payload);
+    }
+}
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    let transform_task: fn(Vec<i32>) -> Vec<Task> = |v| {
+        v.into_iter()
+            .map(|val| {
+                let val = val as u32;
+                match val % 4 {
+                    0 => Task::Urgent {
+                        deadline: (val >> 8) as u64,
+                        data: [val as u64; 6],
+                    },
+                    1 => Task::High {
+                        deadline: (val >> 28) as u64,
+                        name: val.to_string(),
+                    },
+                    2 => Task::Normal {
+                        id: val,
+                        data: [val as u64; 6],
+                    },
+                    _ => Task::Idle(val as u64),
+                }
+            })
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        "task",
+        &transform_task,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by",
+        |v: &mut [Task]| {
+            rust_ipnsort::sort_by(v, |a, b| {
+                priority(a)
+                    .cmp(&priority(b))
+                    .then_with(|| compare_payload(a, b))
+            })
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "task",
+        &transform_task,
+        pattern_name,
+        pattern_provider,
+        "grouped_sort_by",
+        sort_grouped,
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "task",
+        &transform_task,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by_priority",
+        |v: &mut [Task]| rust_ipnsort::sort_by_priority(v, priority, compare_payload),
+    );
+}
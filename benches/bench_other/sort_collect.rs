@@ -0,0 +1,52 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Collects the even values of the input, an iterator without an exact size hint, sorts them and
+// writes them back to the front of the input.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_collect_then_sort",
+        |v: &mut [i32]| {
+            let mut sorted: Vec<i32> = v.iter().copied().filter(|x| x % 2 == 0).collect();
+            rust_ipnsort::sort(&mut sorted);
+            v[..sorted.len()].copy_from_slice(&sorted);
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_collect",
+        |v: &mut [i32]| {
+            let sorted = rust_ipnsort::sort_collect(v.iter().copied().filter(|x| x % 2 == 0));
+            v[..sorted.len()].copy_from_slice(&sorted);
+        },
+    );
+}
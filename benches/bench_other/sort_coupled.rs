@@ -0,0 +1,74 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Sorts `u32` keys with `[f32; 3]` values in a separate slice, once with `sort_coupled` and once by
+// copying both into tuples, sorting those and writing them back. Both allocate the values for every
+// run.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<u32> = |values| {
+        values
+            .iter()
+            .map(|val| ((*val as i64) + (i32::MAX as i64) + 1) as u32)
+            .collect()
+    };
+
+    fn values_of(keys: &[u32]) -> Vec<[f32; 3]> {
+        keys.iter().map(|&key| [key as f32; 3]).collect()
+    }
+
+    bench_fn(
+        c,
+        test_size,
+        "u32",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_coupled",
+        |keys: &mut [u32]| {
+            let mut values = values_of(keys);
+            rust_ipnsort::sort_coupled(keys, &mut values);
+            std::hint::black_box(values);
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "u32",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_zipped",
+        |keys: &mut [u32]| {
+            let mut values = values_of(keys);
+            let mut zipped = keys
+                .iter()
+                .copied()
+                .zip(values.iter().copied())
+                .collect::<Vec<_>>();
+            rust_ipnsort::sort_by(&mut zipped, |a, b| a.0.cmp(&b.0));
+            for ((key, value), (sorted_key, sorted_value)) in
+                keys.iter_mut().zip(values.iter_mut()).zip(zipped)
+            {
+                *key = sorted_key;
+                *value = sorted_value;
+            }
+            std::hint::black_box(values);
+        },
+    );
+}
@@ -0,0 +1,54 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+const GROUP_LEN: usize = 16;
+
+// Sorts the input as independent groups of `GROUP_LEN` elements, e.g. 100k groups for a test size
+// of 1.6M.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" || test_size < GROUP_LEN {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "std_sort_unstable_each",
+        |v: &mut [i32]| {
+            for group in v.chunks_mut(GROUP_LEN) {
+                group.sort_unstable();
+            }
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_each",
+        |v: &mut [i32]| {
+            let mut groups = v.chunks_mut(GROUP_LEN).collect::<Vec<_>>();
+            rust_ipnsort::sort_each(&mut groups);
+        },
+    );
+}
@@ -0,0 +1,53 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Sorts the input as independent windows of a fixed length, once with a length that matches a
+// sorting network exactly and once with one that doesn't.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" || test_size < 12 {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_windows_8",
+        |v: &mut [i32]| {
+            for window in v.chunks_exact_mut(8) {
+                rust_ipnsort::sort(window);
+            }
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_windows_12",
+        |v: &mut [i32]| {
+            for window in v.chunks_exact_mut(12) {
+                rust_ipnsort::sort(window);
+            }
+        },
+    );
+}
@@ -0,0 +1,86 @@
+use std::cell::{Cell, RefCell};
+
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort::{self, Heapsort, MedianOfMedians};
+
+use crate::bench_other::util::bench_fn;
+
+/// Runs McIlroy's "A Killer Adversary for Quicksort" against `rust_ipnsort::sort_by` and returns
+/// the values it settled on. ipnsort is deterministic, so sorting these values again takes the
+/// same path and exhausts the imbalance limit, which makes it hit the fallback.
+fn antiqsort_killer(len: usize) -> Vec<i32> {
+    let gas = len as i32;
+    let mut values = vec![gas; len];
+    // Fix the first elements as three short descending runs. Otherwise the lazily decided order
+    // turns into long presorted runs, which the run detection in front of the quicksort catches.
+    let prefix = [1, 0, 3, 2, 5, 4];
+    let prefix_len = prefix.len().min(len);
+    values[..prefix_len].copy_from_slice(&prefix[..prefix_len]);
+    let values = RefCell::new(values);
+    let next_solid = Cell::new(prefix.len() as i32);
+    let candidate = Cell::new(0);
+
+    let mut indices = (0..len).collect::<Vec<usize>>();
+    rust_ipnsort::sort_by(&mut indices, |&a, &b| {
+        let mut values = values.borrow_mut();
+
+        if values[a] == gas && values[b] == gas {
+            let frozen = if a == candidate.get() { a } else { b };
+            values[frozen] = next_solid.get();
+            next_solid.set(next_solid.get() + 1);
+        }
+
+        if values[a] == gas {
+            candidate.set(a);
+        } else if values[b] == gas {
+            candidate.set(b);
+        }
+
+        values[a].cmp(&values[b])
+    });
+
+    values.into_inner()
+}
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    _pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    // The killer input replaces the pattern, only run once per size.
+    if transform_name != "i32" || pattern_name != "random" {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+    let killer = antiqsort_killer(test_size);
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        "antiqsort_killer",
+        |_| killer.clone(),
+        "rust_ipnsort_fallback_heapsort",
+        |v: &mut [i32]| rust_ipnsort::sort_by_with_fallback::<_, _, Heapsort>(v, |a, b| a.cmp(b)),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        "antiqsort_killer",
+        |_| killer.clone(),
+        "rust_ipnsort_fallback_median_of_medians",
+        |v: &mut [i32]| {
+            rust_ipnsort::sort_by_with_fallback::<_, _, MedianOfMedians>(v, |a, b| a.cmp(b))
+        },
+    );
+}
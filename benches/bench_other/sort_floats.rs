@@ -0,0 +1,63 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort::{self, NanPolicy};
+
+use crate::bench_other::util::bench_fn;
+
+// `f64` isn't `Ord`, so the values are passed around as their bits and converted in place before
+// sorting. About 1% of the values are NaN.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "u64" {
+        return;
+    }
+
+    let transform_f64_bits: fn(Vec<i32>) -> Vec<u64> = |v| {
+        v.into_iter()
+            .map(|val| {
+                if val % 100 == 0 {
+                    f64::NAN.to_bits()
+                } else {
+                    (val as f64 / 7.0).to_bits()
+                }
+            })
+            .collect()
+    };
+
+    fn as_f64(v: &mut [u64]) -> &mut [f64] {
+        // SAFETY: `u64` and `f64` have the same size and alignment, and every bit pattern is valid
+        // for both.
+        unsafe { std::slice::from_raw_parts_mut(v.as_mut_ptr().cast::<f64>(), v.len()) }
+    }
+
+    bench_fn(
+        c,
+        test_size,
+        "f64",
+        &transform_f64_bits,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by_total_cmp",
+        |v: &mut [u64]| rust_ipnsort::sort_by(as_f64(v), f64::total_cmp),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "f64",
+        &transform_f64_bits,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_floats_with_last",
+        |v: &mut [u64]| {
+            rust_ipnsort::sort_floats_with(as_f64(v), NanPolicy::Last).unwrap();
+        },
+    );
+}
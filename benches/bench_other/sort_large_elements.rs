@@ -0,0 +1,43 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Sorts 256 byte elements, where every move of an element is a large memcpy. The key is stored big
+// endian in the first bytes, so the byte-wise `Ord` of the array matches the order of the `i32`.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" || test_size > 100_000 {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<[u8; 256]> = |values| {
+        values
+            .iter()
+            .map(|&val| {
+                let mut elem = [0; 256];
+                elem[..4].copy_from_slice(&((val as u32) ^ (1 << 31)).to_be_bytes());
+                elem
+            })
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        "u8_256",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_unstable",
+        |v: &mut [[u8; 256]]| rust_ipnsort::sort(v),
+    );
+}
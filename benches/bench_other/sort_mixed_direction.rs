@@ -0,0 +1,56 @@
+use std::cmp::Reverse;
+
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Descending primary and ascending secondary key. The primary key has 16 distinct values, so
+// most comparisons see equal primary keys, the secondary key is the input value.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "u64" {
+        return;
+    }
+
+    let transform_mixed: fn(Vec<i32>) -> Vec<(Reverse<u32>, u32)> = |v| {
+        v.into_iter()
+            .map(|val| {
+                (
+                    Reverse((val as u32).wrapping_mul(0x9E37_79B9) >> 28),
+                    val as u32,
+                )
+            })
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        "reverse_u32_u32",
+        &transform_mixed,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort",
+        |v: &mut [(Reverse<u32>, u32)]| rust_ipnsort::sort(v),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "reverse_u32_u32",
+        &transform_mixed,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_mixed_direction",
+        |v: &mut [(Reverse<u32>, u32)]| rust_ipnsort::sort_mixed_direction(v),
+    );
+}
@@ -0,0 +1,50 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+type Pair = (u32, [u8; 28]);
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        // The pairs are built from the pattern directly, only run this once per pattern.
+        return;
+    }
+
+    let pair_transform: fn(Vec<i32>) -> Vec<Pair> = |v| {
+        v.into_iter()
+            .map(|val| (val as u32, [val as u8; 28]))
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        "pair_u32_28b",
+        &pair_transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_by_key_field",
+        |v: &mut [Pair]| rust_ipnsort::sort_by(v, |a, b| a.0.cmp(&b.0)),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "pair_u32_28b",
+        &pair_transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_pairs_by_key",
+        rust_ipnsort::sort_pairs_by_key,
+    );
+}
@@ -0,0 +1,53 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    // 16 byte keys that share a common prefix, like IDs, with the value big-endian encoded at the
+    // end so that the byte order matches the logical order.
+    let transform: fn(Vec<i32>) -> Vec<[u8; 16]> = |v| {
+        v.into_iter()
+            .map(|val| {
+                let mut key = [0xAB; 16];
+                key[12..].copy_from_slice(&(val as u32).to_be_bytes());
+                key
+            })
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        "u8_16",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort",
+        |v: &mut [[u8; 16]]| rust_ipnsort::sort(v),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "u8_16",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_pod_bytes",
+        |v: &mut [[u8; 16]]| rust_ipnsort::sort_pod_bytes(v),
+    );
+}
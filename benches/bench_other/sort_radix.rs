@@ -0,0 +1,51 @@
+use criterion::Criterion;
+
+use sort_comp::other::rust_lsd_radix;
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Sorts `u32`s with the in-crate LSD radix sort and with ipnsort.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    // Flipping the sign bit keeps the order of the values.
+    let transform: fn(Vec<i32>) -> Vec<u32> = |values| {
+        values
+            .into_iter()
+            .map(|val| (val as u32) ^ (1 << 31))
+            .collect()
+    };
+
+    bench_fn(
+        c,
+        test_size,
+        "u32",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_lsd_radix",
+        |v: &mut [u32]| rust_lsd_radix::sort_radix(v),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        "u32",
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_unstable",
+        |v: &mut [u32]| rust_ipnsort::sort(v),
+    );
+}
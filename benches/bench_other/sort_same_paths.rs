@@ -0,0 +1,124 @@
+use std::num::{NonZeroU64, Wrapping};
+use std::time::Duration;
+
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort::{self, SmallSort};
+
+use crate::bench_other::util::bench_fn;
+
+// Types that should take the same type-based paths as a plain type, sorted next to it with the same
+// values. `char` like `u32` and `NonZeroU64` like `u64`, their niche doesn't affect the dispatch.
+// `Wrapping<u64>` like `u64`, it is `repr(transparent)` and `Freeze`. `Duration` like the
+// `(u64, u32)` of its fields, both are 16 byte two-field types.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    bench_type::<u32>(c, test_size, "u32", pattern_name, pattern_provider);
+    bench_type::<char>(c, test_size, "char", pattern_name, pattern_provider);
+
+    bench_type::<u64>(c, test_size, "u64", pattern_name, pattern_provider);
+    bench_type::<NonZeroU64>(c, test_size, "non_zero_u64", pattern_name, pattern_provider);
+    bench_type::<Wrapping<u64>>(c, test_size, "wrapping_u64", pattern_name, pattern_provider);
+
+    bench_type::<(u64, u32)>(c, test_size, "u64_u32", pattern_name, pattern_provider);
+    bench_type::<Duration>(c, test_size, "duration", pattern_name, pattern_provider);
+
+    bench_fn(
+        c,
+        test_size,
+        "duration",
+        &(transform::<Duration> as fn(Vec<i32>) -> Vec<Duration>),
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_network",
+        |v: &mut [Duration]| {
+            rust_ipnsort::sort_by_with_small_sort(v, |a, b| a.cmp(b), SmallSort::Network)
+        },
+    );
+}
+
+fn bench_type<T: FromValue + Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    test_size: usize,
+    type_name: &str,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    bench_fn(
+        c,
+        test_size,
+        type_name,
+        &(transform::<T> as fn(Vec<i32>) -> Vec<T>),
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort",
+        |v: &mut [T]| rust_ipnsort::sort(v),
+    );
+}
+
+fn transform<T: FromValue>(values: Vec<i32>) -> Vec<T> {
+    values.into_iter().map(T::from_value).collect()
+}
+
+// Maps the pattern values the same way for a type and the plain type it is compared with.
+trait FromValue {
+    fn from_value(val: i32) -> Self;
+}
+
+impl FromValue for u32 {
+    // Scalar values below the surrogate range, so every value is a valid `char`.
+    fn from_value(val: i32) -> Self {
+        val.rem_euclid(0xD800) as u32
+    }
+}
+
+impl FromValue for char {
+    fn from_value(val: i32) -> Self {
+        char::from_u32(u32::from_value(val)).unwrap()
+    }
+}
+
+impl FromValue for u64 {
+    // Shifted so that zero can't occur.
+    fn from_value(val: i32) -> Self {
+        (val as i64 - i32::MIN as i64 + 1) as u64
+    }
+}
+
+impl FromValue for NonZeroU64 {
+    fn from_value(val: i32) -> Self {
+        NonZeroU64::new(u64::from_value(val)).unwrap()
+    }
+}
+
+impl FromValue for Wrapping<u64> {
+    fn from_value(val: i32) -> Self {
+        Wrapping(u64::from_value(val))
+    }
+}
+
+impl FromValue for (u64, u32) {
+    // Spread over the first 1000 seconds, with random sub-second parts.
+    fn from_value(val: i32) -> Self {
+        let val = val as u32;
+        ((val % 1000) as u64, (val / 1000) % 1_000_000_000)
+    }
+}
+
+impl FromValue for Duration {
+    fn from_value(val: i32) -> Self {
+        let (secs, nanos) = <(u64, u32)>::from_value(val);
+        Duration::new(secs, nanos)
+    }
+}
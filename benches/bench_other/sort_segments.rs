@@ -0,0 +1,106 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Splits the input into segments of 0 to 1023 elements, with the lengths spread by a fixed
+// stride, so that every run sees the same layout.
+fn split_segments(v: &mut [i32]) -> Vec<&mut [i32]> {
+    let mut segments = Vec::new();
+    let mut rest = v;
+    let mut i = 0;
+    while !rest.is_empty() {
+        let len = ((i * 7919) % 1024).min(rest.len());
+        let (segment, tail) = rest.split_at_mut(len);
+        segments.push(segment);
+        rest = tail;
+        i += 1;
+    }
+
+    segments
+}
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "flatten_sort_rescatter",
+        |v: &mut [i32]| {
+            let mut segments = split_segments(v);
+            let mut flat = segments.concat();
+            rust_ipnsort::sort(&mut flat);
+
+            let mut start = 0;
+            for segment in segments.iter_mut() {
+                let end = start + segment.len();
+                segment.copy_from_slice(&flat[start..end]);
+                start = end;
+            }
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "index_sort_scatter",
+        |v: &mut [i32]| {
+            let mut segments = split_segments(v);
+            let mut index = segments
+                .iter()
+                .enumerate()
+                .flat_map(|(s, segment)| (0..segment.len()).map(move |o| (s, o)))
+                .collect::<Vec<_>>();
+            rust_ipnsort::sort_by(&mut index, |&(sa, oa), &(sb, ob)| {
+                segments[sa][oa].cmp(&segments[sb][ob])
+            });
+
+            let sorted = index
+                .iter()
+                .map(|&(s, o)| segments[s][o])
+                .collect::<Vec<_>>();
+            let mut start = 0;
+            for segment in segments.iter_mut() {
+                let end = start + segment.len();
+                segment.copy_from_slice(&sorted[start..end]);
+                start = end;
+            }
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_segments",
+        |v: &mut [i32]| {
+            let mut segments = split_segments(v);
+            rust_ipnsort::sort_segments(&mut segments);
+        },
+    );
+}
@@ -0,0 +1,68 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Same size and no interior mutability as `u64`, but not `Copy`, so the insertion sort takes the
+// generic `insert_tail` instead of the word-sized one.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NotCopy(u64);
+
+// Sorts the input in chunks of up to 20 `u64`, which are sorted with insertion sort alone.
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "u64" {
+        return;
+    }
+
+    let transform_u64: fn(Vec<i32>) -> Vec<u64> = |v| {
+        v.into_iter()
+            .map(|val| (val as i64 - i32::MIN as i64) as u64)
+            .collect()
+    };
+    let transform_not_copy: fn(Vec<i32>) -> Vec<NotCopy> = |v| {
+        v.into_iter()
+            .map(|val| NotCopy((val as i64 - i32::MIN as i64) as u64))
+            .collect()
+    };
+
+    for chunk_len in [2, 4, 8, 12, 16, 20] {
+        bench_fn(
+            c,
+            test_size,
+            "u64",
+            &transform_u64,
+            pattern_name,
+            pattern_provider,
+            &format!("rust_ipnsort_unstable_chunks_{chunk_len}"),
+            |v: &mut [u64]| {
+                for chunk in v.chunks_mut(chunk_len) {
+                    rust_ipnsort::sort(chunk);
+                }
+            },
+        );
+
+        bench_fn(
+            c,
+            test_size,
+            "u64_not_copy",
+            &transform_not_copy,
+            pattern_name,
+            pattern_provider,
+            &format!("rust_ipnsort_unstable_chunks_{chunk_len}"),
+            |v: &mut [NotCopy]| {
+                for chunk in v.chunks_mut(chunk_len) {
+                    rust_ipnsort::sort(chunk);
+                }
+            },
+        );
+    }
+}
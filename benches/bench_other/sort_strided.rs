@@ -0,0 +1,62 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::rust_ipnsort;
+
+use crate::bench_other::util::bench_fn;
+
+// Number of columns of the row-major matrix, each column is sorted separately.
+const COLUMNS: usize = 16;
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" || test_size < COLUMNS * 2 || test_size % COLUMNS != 0 {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+    let rows = test_size / COLUMNS;
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "copy_sort_copyback_columns",
+        |v: &mut [i32]| {
+            let mut column = Vec::with_capacity(rows);
+            for col in 0..COLUMNS {
+                column.clear();
+                column.extend(v[col..].iter().step_by(COLUMNS));
+                rust_ipnsort::sort(&mut column);
+
+                for (dest, val) in v[col..].iter_mut().step_by(COLUMNS).zip(&column) {
+                    *dest = *val;
+                }
+            }
+        },
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        pattern_name,
+        pattern_provider,
+        "rust_ipnsort_sort_strided_columns",
+        |v: &mut [i32]| {
+            for col in 0..COLUMNS {
+                rust_ipnsort::sort_strided(v, col, rows, COLUMNS);
+            }
+        },
+    );
+}
@@ -0,0 +1,64 @@
+use criterion::Criterion;
+
+use sort_comp::unstable::{rust_ipnsort, rust_std};
+
+use crate::bench_other::util::bench_fn;
+
+// Percentage of the input that is a sorted prefix, the rest is appended unsorted.
+const SORTED_PREFIX_PERCENT: usize = 90;
+
+pub fn bench<T: Ord + std::fmt::Debug>(
+    c: &mut Criterion,
+    _filter_arg: &str,
+    test_size: usize,
+    transform_name: &str,
+    _transform: &fn(Vec<i32>) -> Vec<T>,
+    pattern_name: &str,
+    pattern_provider: &fn(usize) -> Vec<i32>,
+) {
+    if transform_name != "i32" || pattern_name != "random" {
+        return;
+    }
+
+    let transform: fn(Vec<i32>) -> Vec<i32> = |v| v;
+    let sorted_prefix_len = (test_size * SORTED_PREFIX_PERCENT) / 100;
+    let appended_log = |len| {
+        let mut v = pattern_provider(len);
+        v[..sorted_prefix_len].sort();
+        v
+    };
+    let pattern_name = format!("sorted_prefix_{SORTED_PREFIX_PERCENT}_random_suffix");
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        &pattern_name,
+        appended_log,
+        "rust_ipnsort_sort_suffix",
+        |v: &mut [i32]| rust_ipnsort::sort_suffix(v, sorted_prefix_len),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        &pattern_name,
+        appended_log,
+        "rust_ipnsort_find_streak",
+        |v: &mut [i32]| rust_ipnsort::sort(v),
+    );
+
+    bench_fn(
+        c,
+        test_size,
+        transform_name,
+        &transform,
+        &pattern_name,
+        appended_log,
+        "rust_std_full_sort",
+        |v: &mut [i32]| rust_std::sort(v),
+    );
+}
@@ -114,6 +114,26 @@ fn build_and_link_cpp_vqsort() {
 #[cfg(not(feature = "cpp_vqsort"))]
 fn build_and_link_cpp_vqsort() {}
 
+#[cfg(feature = "cpp_boost_spreadsort")]
+fn build_and_link_cpp_boost_spreadsort() {
+    println!("cargo:rerun-if-env-changed=BOOST_INCLUDE_DIR");
+
+    build_and_link_cpp_sort(
+        "cpp_boost_spreadsort",
+        Some(|builder: &mut cc::Build| {
+            // Boost is too large to vendor it in thirdparty, spreadsort only needs the headers.
+            if let Ok(boost_include_dir) = env::var("BOOST_INCLUDE_DIR") {
+                builder.include(boost_include_dir);
+            }
+
+            None
+        }),
+    );
+}
+
+#[cfg(not(feature = "cpp_boost_spreadsort"))]
+fn build_and_link_cpp_boost_spreadsort() {}
+
 #[cfg(feature = "cpp_intel_avx512")]
 fn build_and_link_cpp_intel_avx512() {
     build_and_link_cpp_sort(
@@ -270,6 +290,7 @@ fn main() {
     build_and_link_cpp_powersort();
     build_and_link_cpp_simdsort();
     build_and_link_cpp_vqsort();
+    build_and_link_cpp_boost_spreadsort();
     build_and_link_cpp_intel_avx512();
     build_and_link_singelisort();
     build_and_link_cpp_ips4o();
@@ -0,0 +1,82 @@
+//! Models for expensive comparisons.
+//!
+//! Sort implementations trade comparisons against data movement. With cheap integer comparisons
+//! the data movement dominates, but for example string collation or comparing via a database
+//! lookup shift the balance towards doing as few comparisons as possible. [`CostlyCmp`] simulates
+//! such regimes with a configurable amount of busy work per comparison.
+
+use std::cmp::Ordering;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::Sort;
+
+/// Wraps a value together with a comparison cost. Every comparison spins for the cost of the left
+/// hand side, measured in loop iterations of roughly one cycle each. Equality and ordering only
+/// depend on the wrapped value.
+#[derive(Clone, Copy, Debug)]
+pub struct CostlyCmp<T>(pub T, pub u64);
+
+#[inline(never)]
+fn spin(iterations: u64) {
+    for i in 0..iterations {
+        black_box(i);
+    }
+}
+
+impl<T: PartialEq> PartialEq for CostlyCmp<T> {
+    fn eq(&self, other: &Self) -> bool {
+        spin(self.1);
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for CostlyCmp<T> {}
+
+impl<T: PartialOrd> PartialOrd for CostlyCmp<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        spin(self.1);
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<T: Ord> Ord for CostlyCmp<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        spin(self.1);
+        self.0.cmp(&other.0)
+    }
+}
+
+/// Result of [`measure_sort`].
+#[derive(Clone, Copy, Debug)]
+pub struct SortCost {
+    pub elapsed: Duration,
+    pub comparisons: u64,
+}
+
+/// Sorts a copy of `v` with `S`, where every comparison costs `cmp_cost` spin iterations. Returns
+/// the sorted values, the wall-clock time of the sort and the number of comparisons.
+pub fn measure_sort<S: Sort, T: Ord + Clone>(v: &[T], cmp_cost: u64) -> (Vec<T>, SortCost) {
+    let mut wrapped = v
+        .iter()
+        .map(|val| CostlyCmp(val.clone(), cmp_cost))
+        .collect::<Vec<_>>();
+
+    let mut comparisons = 0;
+    let start = Instant::now();
+    S::sort_by(&mut wrapped, |a, b| {
+        comparisons += 1;
+        a.cmp(b)
+    });
+    let elapsed = start.elapsed();
+
+    let sorted = wrapped.into_iter().map(|elem| elem.0).collect();
+
+    (
+        sorted,
+        SortCost {
+            elapsed,
+            comparisons,
+        },
+    )
+}
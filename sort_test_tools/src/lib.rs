@@ -12,6 +12,7 @@ pub trait Sort {
         F: FnMut(&T, &T) -> std::cmp::Ordering;
 }
 
+pub mod cost_model;
 pub mod ffi_types;
 pub mod patterns;
 pub mod tests;
@@ -0,0 +1,31 @@
+#include <boost/sort/spreadsort/spreadsort.hpp>
+
+#include <stdint.h>
+
+#include "shared.h"
+
+extern "C" {
+// --- i32 ---
+
+void boost_spreadsort_i32(int32_t* data, size_t len) {
+  boost::sort::spreadsort::integer_sort(data, data + len);
+}
+
+// --- u64 ---
+
+void boost_spreadsort_u64(uint64_t* data, size_t len) {
+  boost::sort::spreadsort::integer_sort(data, data + len);
+}
+
+// --- f32 ---
+
+void boost_spreadsort_f32(float* data, size_t len) {
+  boost::sort::spreadsort::float_sort(data, data + len);
+}
+
+// --- f64 ---
+
+void boost_spreadsort_f64(double* data, size_t len) {
+  boost::sort::spreadsort::float_sort(data, data + len);
+}
+}  // extern "C"
@@ -110,11 +110,19 @@ macro_rules! ffi_sort_impl {
 
             impl<T> CppSort for T {
                 default fn sort(_data: &mut [T]) {
-                    panic!("Type not supported");
+                    panic!(
+                        "Type not supported: {} only sorts i32, u64, FFIString, F128 and FFIOneKiloByte, not {}",
+                        $name,
+                        std::any::type_name::<T>()
+                    );
                 }
 
                 default fn sort_by<F: FnMut(&T, &T) -> Ordering>(_data: &mut [T], _compare: F) {
-                    panic!("Type not supported");
+                    panic!(
+                        "Type not supported: {} only sorts i32, u64, FFIString, F128 and FFIOneKiloByte, not {}",
+                        $name,
+                        std::any::type_name::<T>()
+                    );
                 }
             }
 
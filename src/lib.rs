@@ -18,6 +18,14 @@
     auto_traits
 )]
 
+use std::cmp::Ordering;
+
+/// Implements [`sort_test_tools::Sort`] as `SortImpl` for the `sort` and `sort_by` functions in
+/// scope.
+///
+/// With the `assert_sorted` feature every call checks that the output is sorted, see
+/// [`assert_sorted_by`].
+#[macro_export]
 macro_rules! sort_impl {
     ($name:expr) => {
         pub struct SortImpl;
@@ -33,6 +41,9 @@ macro_rules! sort_impl {
                 T: Ord,
             {
                 sort(arr);
+
+                #[cfg(feature = "assert_sorted")]
+                $crate::assert_sorted_by(arr, T::cmp, $name);
             }
 
             #[inline]
@@ -40,12 +51,41 @@ macro_rules! sort_impl {
             where
                 F: FnMut(&T, &T) -> Ordering,
             {
+                #[cfg(not(feature = "assert_sorted"))]
                 sort_by(arr, compare);
+
+                #[cfg(feature = "assert_sorted")]
+                {
+                    let mut compare = compare;
+                    sort_by(arr, &mut compare);
+                    $crate::assert_sorted_by(arr, compare, $name);
+                }
             }
         }
     };
 }
 
+/// Panics if `arr` is not sorted according to `compare`.
+///
+/// Injected by [`sort_impl`] after every `sort` and `sort_by` call with the `assert_sorted`
+/// feature, to catch implementations, and especially FFI backends, that silently produce wrong
+/// results. It's opt-in because the check calls `compare` `arr.len() - 1` more times, which skews
+/// comparison counts, and comparison functions that violate `Ord` can legitimately produce
+/// unsorted output. The resulting panic happens after the sort completed and leaves `arr` intact.
+#[doc(hidden)]
+pub fn assert_sorted_by<T, F>(arr: &[T], mut compare: F, name: &str)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let unsorted_at = arr
+        .windows(2)
+        .position(|w| compare(&w[0], &w[1]) == Ordering::Greater);
+
+    if let Some(i) = unsorted_at {
+        panic!("{name} produced unsorted output at index {i}");
+    }
+}
+
 #[macro_use]
 pub mod ffi_util;
 
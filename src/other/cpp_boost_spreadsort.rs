@@ -0,0 +1,65 @@
+use std::cmp::Ordering;
+
+sort_impl!("cpp_boost_spreadsort_radix");
+
+extern "C" {
+    fn boost_spreadsort_i32(data: *mut i32, len: usize);
+    fn boost_spreadsort_u64(data: *mut u64, len: usize);
+    fn boost_spreadsort_f32(data: *mut f32, len: usize);
+    fn boost_spreadsort_f64(data: *mut f64, len: usize);
+}
+
+trait BoostSpreadsort: Sized {
+    fn sort(data: &mut [Self]);
+}
+
+impl<T> BoostSpreadsort for T {
+    default fn sort(_data: &mut [Self]) {
+        panic!("Type not supported by boost spreadsort, only i32, u64, f32 and f64 are");
+    }
+}
+
+impl BoostSpreadsort for i32 {
+    fn sort(data: &mut [Self]) {
+        unsafe {
+            boost_spreadsort_i32(data.as_mut_ptr(), data.len());
+        }
+    }
+}
+
+impl BoostSpreadsort for u64 {
+    fn sort(data: &mut [Self]) {
+        unsafe {
+            boost_spreadsort_u64(data.as_mut_ptr(), data.len());
+        }
+    }
+}
+
+impl BoostSpreadsort for f32 {
+    fn sort(data: &mut [Self]) {
+        unsafe {
+            boost_spreadsort_f32(data.as_mut_ptr(), data.len());
+        }
+    }
+}
+
+impl BoostSpreadsort for f64 {
+    fn sort(data: &mut [Self]) {
+        unsafe {
+            boost_spreadsort_f64(data.as_mut_ptr(), data.len());
+        }
+    }
+}
+
+pub fn sort<T: Ord>(data: &mut [T]) {
+    BoostSpreadsort::sort(data);
+}
+
+/// Sorts floats with `float_sort`. The order of NaNs is unspecified.
+pub fn sort_float<T: PartialOrd>(data: &mut [T]) {
+    BoostSpreadsort::sort(data);
+}
+
+pub fn sort_by<T, F: FnMut(&T, &T) -> Ordering>(_data: &mut [T], _compare: F) {
+    panic!("sort_by not supported by boost spreadsort");
+}
@@ -0,0 +1,16 @@
+use core::cmp::Ordering;
+
+use crate::unstable::rust_ipnsort;
+
+sort_impl!("rust_ipnsort_guaranteed_unstable");
+
+pub fn sort<T: Ord>(v: &mut [T]) {
+    rust_ipnsort::sort_by_guaranteed(v, T::cmp);
+}
+
+pub fn sort_by<T, F>(v: &mut [T], compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    rust_ipnsort::sort_by_guaranteed(v, compare);
+}
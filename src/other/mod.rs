@@ -1,10 +1,17 @@
 #[cfg(feature = "rust_radsort")]
 pub mod rust_radsort;
 
+#[cfg(feature = "rust_lsd_radix")]
+pub mod rust_lsd_radix;
+
 // Call simdsort sort via FFI.
 #[cfg(feature = "cpp_simdsort")]
 pub mod cpp_simdsort;
 
+// Call boost spreadsort via FFI.
+#[cfg(feature = "cpp_boost_spreadsort")]
+pub mod cpp_boost_spreadsort;
+
 // Call vqsort sort via FFI.
 #[cfg(feature = "cpp_vqsort")]
 pub mod cpp_vqsort;
@@ -23,6 +30,9 @@ pub mod sort_evolution;
 #[cfg(feature = "small_sort")]
 pub mod small_sort;
 
+#[cfg(feature = "ipnsort_variants")]
+pub mod ipnsort_variants;
+
 #[cfg(feature = "partition_point")]
 pub mod partition_point;
 
@@ -0,0 +1,193 @@
+//! LSD radix sort for primitive integer and float types, without external dependencies.
+//!
+//! Every element is mapped to an unsigned key with the same order, see [`Radixable`]. The keys are
+//! then sorted one byte at a time, starting with the least significant byte. Each pass is a stable
+//! counting sort that scatters the elements into their bucket ranges of a heap allocated buffer of
+//! the same length as the input. The counts of all passes are gathered in a single scan up-front,
+//! which also allows skipping the passes where every key has the same byte.
+
+use std::cmp::Ordering;
+
+sort_impl!("rust_lsd_radix");
+
+/// Types that can be sorted by [`sort_radix`].
+pub trait Radixable: Copy {
+    /// Number of significant bytes of [`Radixable::radix_key`], at most 8.
+    const KEY_BYTES: usize;
+
+    /// Returns an unsigned key that orders the same way as `self`. The key is read as a
+    /// `KEY_BYTES` wide big-endian number, the most significant byte is the most significant digit,
+    /// all bytes above `KEY_BYTES` are zero.
+    fn radix_key(self) -> u64;
+}
+
+macro_rules! radixable_unsigned_impl {
+    ($($t:ty),*) => {
+        $(
+            impl Radixable for $t {
+                const KEY_BYTES: usize = std::mem::size_of::<$t>();
+
+                #[inline(always)]
+                fn radix_key(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+radixable_unsigned_impl!(u8, u16, u32, u64, usize);
+
+// Flipping the sign bit moves the negative values below the positive ones, and keeps the order
+// within both, as two's complement orders them like unsigned values.
+macro_rules! radixable_signed_impl {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl Radixable for $t {
+                const KEY_BYTES: usize = std::mem::size_of::<$t>();
+
+                #[inline(always)]
+                fn radix_key(self) -> u64 {
+                    ((self as $u) ^ (1 << (<$u>::BITS - 1))) as u64
+                }
+            }
+        )*
+    };
+}
+
+radixable_signed_impl!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+
+// The IEEE 754 total order, the same as `total_cmp`. Positive values only need the sign bit set to
+// be above all negative values. Negative values order inversely to their bits, inverting all bits
+// fixes that and clears the sign bit.
+macro_rules! radixable_float_impl {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl Radixable for $t {
+                const KEY_BYTES: usize = std::mem::size_of::<$t>();
+
+                #[inline(always)]
+                fn radix_key(self) -> u64 {
+                    let bits = self.to_bits();
+                    let sign_bit = 1 << (<$u>::BITS - 1);
+                    let key = if bits & sign_bit != 0 {
+                        !bits
+                    } else {
+                        bits | sign_bit
+                    };
+
+                    key as u64
+                }
+            }
+        )*
+    };
+}
+
+radixable_float_impl!(f32 => u32, f64 => u64);
+
+/// Sorts `v` in ascending order of [`Radixable::radix_key`]. Elements with equal keys keep their
+/// relative order.
+///
+/// Allocates a buffer of `v.len()` elements for inputs longer than 32 elements.
+pub fn sort_radix<T: Radixable>(v: &mut [T]) {
+    // Below this, the passes over the 256 buckets cost more than sorting the elements directly.
+    const MAX_LEN_INSERTION_SORT: usize = 32;
+
+    let len = v.len();
+    if len < 2 {
+        return;
+    }
+
+    if len <= MAX_LEN_INSERTION_SORT {
+        insertion_sort_by_key(v);
+        return;
+    }
+
+    let mut counts = vec![[0usize; 256]; T::KEY_BYTES];
+    for &val in v.iter() {
+        let key = val.radix_key();
+        for (digit, digit_counts) in counts.iter_mut().enumerate() {
+            digit_counts[byte_at(key, digit)] += 1;
+        }
+    }
+
+    let mut scratch = v.to_vec();
+    let mut sorted_in_scratch = false;
+
+    for (digit, digit_counts) in counts.iter().enumerate() {
+        // Every key has the same byte here, the pass would not move anything.
+        if digit_counts.contains(&len) {
+            continue;
+        }
+
+        let mut offsets = [0usize; 256];
+        let mut sum = 0;
+        for (offset, &count) in offsets.iter_mut().zip(digit_counts.iter()) {
+            *offset = sum;
+            sum += count;
+        }
+
+        if sorted_in_scratch {
+            scatter(&scratch, v, digit, &mut offsets);
+        } else {
+            scatter(v, &mut scratch, digit, &mut offsets);
+        }
+        sorted_in_scratch = !sorted_in_scratch;
+    }
+
+    if sorted_in_scratch {
+        v.copy_from_slice(&scratch);
+    }
+}
+
+#[inline(always)]
+fn byte_at(key: u64, digit: usize) -> usize {
+    ((key >> (digit * 8)) & 0xFF) as usize
+}
+
+/// Moves every element of `src` to the next free position of its bucket in `dst`.
+fn scatter<T: Radixable>(src: &[T], dst: &mut [T], digit: usize, offsets: &mut [usize; 256]) {
+    for &val in src {
+        let offset = &mut offsets[byte_at(val.radix_key(), digit)];
+        dst[*offset] = val;
+        *offset += 1;
+    }
+}
+
+fn insertion_sort_by_key<T: Radixable>(v: &mut [T]) {
+    for i in 1..v.len() {
+        let val = v[i];
+        let key = val.radix_key();
+
+        let mut j = i;
+        while j > 0 && key < v[j - 1].radix_key() {
+            v[j] = v[j - 1];
+            j -= 1;
+        }
+        v[j] = val;
+    }
+}
+
+trait RadixSort: Sized {
+    fn sort(v: &mut [Self]);
+}
+
+impl<T> RadixSort for T {
+    default fn sort(_v: &mut [Self]) {
+        panic!("Type not supported by rust_lsd_radix");
+    }
+}
+
+impl<T: Radixable> RadixSort for T {
+    fn sort(v: &mut [Self]) {
+        sort_radix(v);
+    }
+}
+
+pub fn sort<T: Ord>(v: &mut [T]) {
+    RadixSort::sort(v);
+}
+
+pub fn sort_by<T, F: FnMut(&T, &T) -> Ordering>(_v: &mut [T], _compare: F) {
+    panic!("sort_by not supported by rust_lsd_radix");
+}
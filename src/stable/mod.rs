@@ -1,3 +1,4 @@
+pub mod rust_powersort;
 pub mod rust_std;
 
 #[cfg(feature = "rust_wpwoodjr")]
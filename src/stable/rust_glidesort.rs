@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::mem;
 
 use glidesort;
 
@@ -11,3 +12,56 @@ pub fn sort<T: Ord>(data: &mut [T]) {
 pub fn sort_by<T, F: FnMut(&T, &T) -> Ordering>(data: &mut [T], compare: F) {
     glidesort::sort_by(data, compare);
 }
+
+/// Size of the heap buffer glidesort gets to work with, see [`sort_with_policy`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GlideBufferPolicy {
+    /// Buffer with space for exactly this many elements, independent of the input length.
+    FixedElems(usize),
+    /// Buffer with space for this fraction of the input length, clamped to `0.0..=1.0`.
+    FractionOfLen(f32),
+    /// Buffer with space for half the input length, but at most `AUTO_MAX_BUFFER_BYTES`.
+    Auto,
+}
+
+impl Default for GlideBufferPolicy {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// Limits the memory used by [`GlideBufferPolicy::Auto`] for large inputs. Glidesort is designed
+/// to work well with buffers much smaller than the input.
+const AUTO_MAX_BUFFER_BYTES: usize = 16 * 1024 * 1024;
+
+impl GlideBufferPolicy {
+    /// Returns the number of elements of type `T` the buffer for an input of length `len` can
+    /// hold.
+    pub fn buffer_len<T>(self, len: usize) -> usize {
+        match self {
+            Self::FixedElems(buffer_len) => buffer_len,
+            Self::FractionOfLen(fraction) => {
+                (len as f64 * fraction.clamp(0.0, 1.0) as f64).round() as usize
+            }
+            Self::Auto => (len / 2).min(AUTO_MAX_BUFFER_BYTES / mem::size_of::<T>().max(1)),
+        }
+    }
+}
+
+/// Sorts `data` with glidesort, using a heap buffer sized by `policy`.
+///
+/// This allows benchmarking glidesort's memory versus speed trade-off, which [`sort_by`] leaves to
+/// the defaults of the glidesort crate.
+pub fn sort_with_policy<T, F>(data: &mut [T], compare: F, policy: GlideBufferPolicy)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let buffer_len = policy.buffer_len::<T>(data.len());
+    let mut buffer = Vec::<T>::with_capacity(buffer_len);
+
+    glidesort::sort_with_buffer_by(
+        data,
+        &mut buffer.spare_capacity_mut()[..buffer_len],
+        compare,
+    );
+}
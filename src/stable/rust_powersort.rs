@@ -0,0 +1,189 @@
+//! Stable merge sort with the powersort merge policy.
+//!
+//! Run detection, insertion sort and the merge are shared with `rust_std`, only the decision which
+//! runs to merge when is different. Powersort assigns every boundary between two adjacent runs a
+//! power, the depth of the boundary in a nearly-optimal binary merge tree over the run midpoints.
+//! Merging is done bottom-up along that tree, which keeps the total merge cost within
+//! `n * (H + 2)`, where `H` is the entropy of the run-length distribution. See "Nearly-Optimal
+//! Mergesorts" by Munro and Wild (2018), https://arxiv.org/abs/1805.04154.
+
+use std::cmp::Ordering;
+use std::mem::size_of;
+
+use crate::stable::rust_std::{insert_head, merge};
+
+sort_impl!("rust_powersort_stable");
+
+#[inline]
+pub fn sort<T>(arr: &mut [T])
+where
+    T: Ord,
+{
+    powersort(arr, &mut |a, b| a.lt(b));
+}
+
+#[inline]
+pub fn sort_by<T, F>(arr: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    powersort(arr, &mut |a, b| compare(a, b) == Ordering::Less);
+}
+
+/// Same as [`sort_by`], but returns the merge cost, the sum of the lengths of all merged slices.
+pub fn sort_by_merge_cost<T, F>(arr: &mut [T], mut compare: F) -> u64
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    powersort(arr, &mut |a, b| compare(a, b) == Ordering::Less)
+}
+
+/// Sorts `v` and returns the merge cost.
+fn powersort<T, F>(v: &mut [T], is_less: &mut F) -> u64
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Slices of up to this length get sorted using insertion sort.
+    const MAX_INSERTION: usize = 20;
+    // Very short runs are extended using insertion sort to span at least this many elements.
+    const MIN_RUN: usize = 10;
+
+    // Sorting has no meaningful behavior on zero-sized types.
+    if size_of::<T>() == 0 {
+        return 0;
+    }
+
+    let len = v.len();
+
+    if len <= MAX_INSERTION {
+        if len >= 2 {
+            for i in (0..len - 1).rev() {
+                insert_head(&mut v[i..], is_less);
+            }
+        }
+        return 0;
+    }
+
+    // The merge buffer holds a copy of the shorter run, see `rust_std::merge_sort`.
+    let mut buf = Vec::<T>::with_capacity(len / 2);
+    let mut merge_cost = 0;
+
+    // Like `rust_std`, runs are found back to front. `current` is the run found last, `runs` holds
+    // the runs to the right of it that are still waiting to be merged, each together with the power
+    // of the boundary to its left. The powers on the stack are strictly increasing from bottom to
+    // top, so the stack never holds more than 64 runs.
+    let mut runs: Vec<(Run, u32)> = Vec::new();
+    let mut current = find_run(v, len, MIN_RUN, is_less);
+
+    while current.start > 0 {
+        let next = find_run(v, current.start, MIN_RUN, is_less);
+        let power = node_power(len, next, current);
+
+        while let Some(&(right, right_power)) = runs.last() {
+            if right_power <= power {
+                break;
+            }
+
+            runs.pop();
+            current = merge_runs(v, current, right, &mut buf, is_less);
+            merge_cost += current.len as u64;
+        }
+
+        runs.push((current, power));
+        current = next;
+    }
+
+    while let Some((right, _)) = runs.pop() {
+        current = merge_runs(v, current, right, &mut buf, is_less);
+        merge_cost += current.len as u64;
+    }
+
+    debug_assert!(current.start == 0 && current.len == len);
+
+    merge_cost
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    start: usize,
+    len: usize,
+}
+
+/// Finds the run that ends at `end`, reverses it if it's strictly descending and extends it with
+/// insertion sort to at least `min_run` elements, or up to the start of `v`.
+fn find_run<T, F>(v: &mut [T], end: usize, min_run: usize, is_less: &mut F) -> Run
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let mut start = end - 1;
+    if start > 0 {
+        start -= 1;
+        if is_less(&v[start + 1], &v[start]) {
+            while start > 0 && is_less(&v[start], &v[start - 1]) {
+                start -= 1;
+            }
+            v[start..end].reverse();
+        } else {
+            while start > 0 && !is_less(&v[start], &v[start - 1]) {
+                start -= 1;
+            }
+        }
+    }
+
+    while start > 0 && end - start < min_run {
+        start -= 1;
+        insert_head(&mut v[start..end], is_less);
+    }
+
+    Run {
+        start,
+        len: end - start,
+    }
+}
+
+/// Merges the adjacent runs `left` and `right` of `v` and returns the merged run.
+fn merge_runs<T, F>(v: &mut [T], left: Run, right: Run, buf: &mut Vec<T>, is_less: &mut F) -> Run
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    debug_assert_eq!(left.start + left.len, right.start);
+
+    let merged_len = left.len + right.len;
+    debug_assert!(buf.capacity() >= left.len.min(right.len));
+
+    // SAFETY: Both runs are non-empty and adjacent, the shorter one fits into `buf` which has a
+    // capacity of half the slice length, and `T` is not a zero-sized type.
+    unsafe {
+        merge(
+            &mut v[left.start..(left.start + merged_len)],
+            left.len,
+            buf.as_mut_ptr(),
+            is_less,
+        );
+    }
+
+    Run {
+        start: left.start,
+        len: merged_len,
+    }
+}
+
+/// Returns the power of the boundary between the adjacent runs `left` and `right` of a slice of
+/// length `len`.
+///
+/// With the run midpoints scaled to `[0, 1)`, that is the first fractional bit in which the two
+/// midpoints differ. Boundaries with a lower power get merged later, they are closer to the root
+/// of the merge tree.
+fn node_power(len: usize, left: Run, right: Run) -> u32 {
+    debug_assert_eq!(left.start + left.len, right.start);
+
+    // Twice the midpoints to stay in integers, as 64-bit fixed point fractions of `2 * len`.
+    let len_2 = 2 * len as u128;
+    let mid_left_2 = (2 * left.start + left.len) as u128;
+    let mid_right_2 = (2 * right.start + right.len) as u128;
+
+    let a = ((mid_left_2 << 64) / len_2) as u64;
+    let b = ((mid_right_2 << 64) / len_2) as u64;
+
+    (a ^ b).leading_zeros() + 1
+}
@@ -1,6 +1,7 @@
 #![allow(unused_unsafe)]
 
 use std::cmp::Ordering;
+use std::collections::TryReserveError;
 use std::mem::{self, size_of};
 use std::ptr;
 
@@ -93,6 +94,29 @@ where
     merge_sort(arr, |a, b| compare(a, b) == Ordering::Less);
 }
 
+/// Same as [`sort`], but returns an error instead of aborting if the scratch memory can't be
+/// allocated.
+///
+/// All memory is allocated before the first element is moved, so `arr` is left untouched if this
+/// returns an error. Slices short enough for insertion sort never allocate, and never fail.
+#[inline]
+pub fn try_sort<T>(arr: &mut [T]) -> Result<(), TryReserveError>
+where
+    T: Ord,
+{
+    merge_sort_impl::<T, _, true>(arr, |a, b| a.lt(b))
+}
+
+/// Same as [`sort_by`], but returns an error instead of aborting if the scratch memory can't be
+/// allocated. See [`try_sort`].
+#[inline]
+pub fn try_sort_by<T, F>(arr: &mut [T], mut compare: F) -> Result<(), TryReserveError>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    merge_sort_impl::<T, _, true>(arr, |a, b| compare(a, b) == Ordering::Less)
+}
+
 /// This merge sort borrows some (but not all) ideas from TimSort, which is described in detail
 /// [here](https://github.com/python/cpython/blob/main/Objects/listsort.txt).
 ///
@@ -106,7 +130,21 @@ where
 ///
 /// The invariants ensure that the total running time is *O*(*n* \* log(*n*)) worst-case.
 #[cfg(not(no_global_oom_handling))]
-fn merge_sort<T, F>(v: &mut [T], mut is_less: F)
+fn merge_sort<T, F>(v: &mut [T], is_less: F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Without `FALLIBLE` allocation failures abort, there is no error to handle.
+    let _ = merge_sort_impl::<T, F, false>(v, is_less);
+}
+
+/// Implementation of [`merge_sort`]. If `FALLIBLE` is true, all memory is allocated up front with
+/// `try_reserve`, and allocation failures are returned before `v` is touched.
+#[inline(always)]
+fn merge_sort_impl<T, F, const FALLIBLE: bool>(
+    v: &mut [T],
+    mut is_less: F,
+) -> Result<(), TryReserveError>
 where
     F: FnMut(&T, &T) -> bool,
 {
@@ -117,7 +155,7 @@ where
 
     // Sorting has no meaningful behavior on zero-sized types.
     if size_of::<T>() == 0 {
-        return;
+        return Ok(());
     }
 
     let len = v.len();
@@ -129,20 +167,31 @@ where
                 insert_head(&mut v[i..], &mut is_less);
             }
         }
-        return;
+        return Ok(());
     }
 
     // Allocate a buffer to use as scratch memory. We keep the length 0 so we can keep in it
     // shallow copies of the contents of `v` without risking the dtors running on copies if
     // `is_less` panics. When merging two sorted runs, this buffer holds a copy of the shorter run,
     // which will always have length at most `len / 2`.
-    let mut buf = Vec::with_capacity(len / 2);
+    let mut buf = Vec::new();
+    // Because of the invariants the run lengths grow at least like the Fibonacci numbers, so there
+    // are never more than this many runs on the stack. Reserving them up front means no push can
+    // fail once sorting has started.
+    let max_runs = 2 * usize::BITS as usize;
+    let mut runs = Vec::new();
+    if FALLIBLE {
+        buf.try_reserve_exact(len / 2)?;
+        runs.try_reserve_exact(max_runs)?;
+    } else {
+        buf.reserve_exact(len / 2);
+    }
+    let runs_capacity = runs.capacity();
 
     // In order to identify natural runs in `v`, we traverse it backwards. That might seem like a
     // strange decision, but consider the fact that merges more often go in the opposite direction
     // (forwards). According to benchmarks, merging forwards is slightly faster than merging
     // backwards. To conclude, identifying runs by traversing backwards improves performance.
-    let mut runs = vec![];
     let mut end = len;
     while end > 0 {
         // Find the next natural run, and reverse it if it's strictly descending.
@@ -200,6 +249,9 @@ where
 
     // Finally, exactly one run must remain in the stack.
     debug_assert!(runs.len() == 1 && runs[0].start == 0 && runs[0].len == len);
+    debug_assert!(!FALLIBLE || runs.capacity() == runs_capacity);
+
+    return Ok(());
 
     // Examines the stack of runs and identifies the next pair of runs to merge. More specifically,
     // if `Some(r)` is returned, that means `runs[r]` and `runs[r + 1]` must be merged next. If the
@@ -245,7 +297,7 @@ where
 ///
 /// This is the integral subroutine of insertion sort.
 #[cfg(not(no_global_oom_handling))]
-fn insert_head<T, F>(v: &mut [T], is_less: &mut F)
+pub(crate) fn insert_head<T, F>(v: &mut [T], is_less: &mut F)
 where
     F: FnMut(&T, &T) -> bool,
 {
@@ -320,7 +372,7 @@ where
 /// The two slices must be non-empty and `mid` must be in bounds. Buffer `buf` must be long enough
 /// to hold a copy of the shorter slice. Also, `T` must not be a zero-sized type.
 #[cfg(not(no_global_oom_handling))]
-unsafe fn merge<T, F>(v: &mut [T], mid: usize, buf: *mut T, is_less: &mut F)
+pub(crate) unsafe fn merge<T, F>(v: &mut [T], mid: usize, buf: *mut T, is_less: &mut F)
 where
     F: FnMut(&T, &T) -> bool,
 {
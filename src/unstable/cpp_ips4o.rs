@@ -1 +1,7 @@
+//! ips4o via FFI, sequential version.
+//!
+//! Like every `ffi_sort_impl!` sort only `i32`, `u64`, `FFIString`, `F128` and `FFIOneKiloByte`
+//! are supported. `sort` and `sort_by` panic for any other element type, they never leave the
+//! slice unsorted.
+
 ffi_sort_impl!("cpp_ips4o_unstable", ips4o_unstable);
@@ -1,3 +1,10 @@
+//! Drop-merge sort from the dmsort crate.
+//!
+//! dmsort is tuned for nearly sorted inputs. It drops out-of-order elements in one pass, sorts
+//! those and merges them back in. The crate accepts every type and comparison function, so unlike
+//! `rust_crumsort_rs` there is no fallback. If too many elements get dropped, the crate itself
+//! switches to `slice::sort_unstable`.
+
 use std::cmp::Ordering;
 
 use dmsort;
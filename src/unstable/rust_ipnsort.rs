@@ -3,9 +3,20 @@
 //! Instruction-Parallel-Network Unstable Sort, ipnsort by Lukas Bergdoll
 
 use core::cmp::{self, Ordering};
+use core::convert::Infallible;
+use core::future::Future;
+use core::hash::{Hash, Hasher};
 use core::intrinsics;
 use core::mem::{self, MaybeUninit};
+use core::ops::Range;
+use core::pin::Pin;
 use core::ptr;
+use core::task::{Context, Poll};
+
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{TryReserveError, VecDeque};
+use std::sync::atomic::{self, AtomicBool};
 
 sort_impl!("rust_ipnsort_unstable");
 
@@ -88,6 +99,14 @@ where
 /// assert!(v == [5, 4, 3, 2, 1]);
 /// ```
 ///
+/// # Performance
+///
+/// For the natural order use [`sort`] instead of `sort_by(v, Ord::cmp)`. `sort` compares with
+/// `lt` directly, while `sort_by` has to turn every [`Ordering`] into a bool. The type of the
+/// comparison function can't be used to detect `Ord::cmp`, so the two can't be unified with
+/// specialization. For random `u64` inputs of length 1M `sort_by(v, Ord::cmp)` measured ~8%
+/// slower, see the `sort_by_natural` benchmark.
+///
 /// [pdqsort]: https://github.com/orlp/pdqsort
 #[inline(always)]
 pub fn sort_by<T, F>(arr: &mut [T], mut compare: F)
@@ -97,189 +116,3165 @@ where
     quicksort(arr, |a, b| compare(a, b) == Ordering::Less);
 }
 
-// --- IMPL ---
+/// Sorts the slice of [`PartialOrd`] elements, but might not preserve the order of equal elements.
+///
+/// Same as `sort_by(v, |a, b| a.partial_cmp(b).unwrap())`, but the panic names the actual problem.
+///
+/// # Panics
+///
+/// Panics with "incomparable elements" if `partial_cmp` returns `None` for any pair of elements
+/// the sort compares. A `NaN` float in a slice of at least two elements is always found, every
+/// element is compared at least once. For other partial orders it depends on which pairs end up
+/// being compared. If it panics, `v` is left in an unspecified order, with all its elements.
+///
+/// # Examples
+///
+/// ```
+/// let mut floats = [5f64, 4.0, 1.0, 3.0, 2.0];
+/// sort_comp::unstable::rust_ipnsort::sort_partial(&mut floats);
+/// assert_eq!(floats, [1.0, 2.0, 3.0, 4.0, 5.0]);
+/// ```
+pub fn sort_partial<T>(v: &mut [T])
+where
+    T: PartialOrd,
+{
+    quicksort(v, |a, b| {
+        a.partial_cmp(b).expect("incomparable elements") == Ordering::Less
+    });
+}
 
-/// Sorts `v` using pattern-defeating quicksort, which is *O*(*n* \* log(*n*)) worst-case.
-#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
-pub fn quicksort<T, F>(v: &mut [T], mut is_less: F)
+/// Where [`sort_floats_with`] puts `NaN` values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// All `NaN` values before all numbers.
+    First,
+    /// All `NaN` values after all numbers.
+    Last,
+    /// Return [`NanFound`] without sorting if there is any `NaN`.
+    Error,
+}
+
+/// Returned by [`sort_floats_with`] for [`NanPolicy::Error`] if the slice contains a `NaN`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NanFound;
+
+/// Sorts the slice of floats, with the `NaN` values grouped according to `policy`, but might not
+/// preserve the order of equal elements.
+///
+/// Numbers are sorted like with `total_cmp`, so `-0.0` comes before `0.0`. `NaN` values of either
+/// sign, quiet or signaling, are moved to the front or back. This is done with the sign bit: for
+/// [`NanPolicy::First`] it is set on every `NaN` before it is turned into the integer key of the
+/// total order, which puts them below `-inf`, for [`NanPolicy::Last`] it is cleared, which puts
+/// them above `inf`. The `NaN` values keep their bits, only the key is changed. For
+/// [`NanPolicy::Error`] the slice is scanned for `NaN` first and left untouched if it has one,
+/// otherwise sorted like for [`NanPolicy::Last`].
+///
+/// # Examples
+///
+/// ```
+/// use sort_comp::unstable::rust_ipnsort::{sort_floats_with, NanFound, NanPolicy};
+///
+/// let mut v = [2.0, f64::NAN, -0.0, 1.0, 0.0];
+/// assert_eq!(sort_floats_with(&mut v, NanPolicy::First), Ok(()));
+/// assert!(v[0].is_nan());
+///
+/// // `-0.0 == 0.0`, so compare the bits to see the order of the zeros.
+/// let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+/// assert_eq!(bits(&v[1..]), bits(&[-0.0, 0.0, 1.0, 2.0]));
+///
+/// assert_eq!(sort_floats_with(&mut v, NanPolicy::Error), Err(NanFound));
+/// ```
+pub fn sort_floats_with<T>(v: &mut [T], policy: NanPolicy) -> Result<(), NanFound>
 where
-    F: FnMut(&T, &T) -> bool,
+    T: SortableFloat,
 {
-    // Sorting has no meaningful behavior on zero-sized types.
-    if const { mem::size_of::<T>() == 0 } {
-        return;
+    let nans_first = match policy {
+        NanPolicy::First => true,
+        NanPolicy::Last => false,
+        NanPolicy::Error => {
+            if v.iter().any(|val| val.is_nan()) {
+                return Err(NanFound);
+            }
+            false
+        }
+    };
+
+    if nans_first {
+        quicksort(v, |a, b| a.sort_key(true) < b.sort_key(true));
+    } else {
+        quicksort(v, |a, b| a.sort_key(false) < b.sort_key(false));
     }
 
-    let len = v.len();
+    Ok(())
+}
 
-    // This path is critical for very small inputs. Always pick insertion sort for these inputs,
-    // without any other analysis. This is perf critical for small inputs, in cold code.
-    const MAX_LEN_ALWAYS_INSERTION_SORT: usize = 20;
+/// Float types that can be sorted by [`sort_floats_with`].
+pub trait SortableFloat: Copy {
+    /// Returns `true` if `self` is `NaN`.
+    fn is_nan(self) -> bool;
 
-    // Instrumenting the standard library showed that 90+% of the calls to sort by rustc are either
-    // of size 0 or 1. Make this path extra fast by assuming the branch is likely.
-    if intrinsics::likely(len < 2) {
-        return;
+    /// Returns an integer key that orders the same way as `total_cmp`, except that all `NaN`
+    /// values are below all numbers if `nans_first` is `true`, and above them otherwise.
+    fn sort_key(self, nans_first: bool) -> i64;
+}
+
+macro_rules! sortable_float_impl {
+    ($($t:ty => $i:ty, $u:ty);*) => {
+        $(
+            impl SortableFloat for $t {
+                #[inline(always)]
+                fn is_nan(self) -> bool {
+                    <$t>::is_nan(self)
+                }
+
+                #[inline(always)]
+                fn sort_key(self, nans_first: bool) -> i64 {
+                    const SIGN_BIT: $i = <$i>::MIN;
+
+                    let mut bits = self.to_bits() as $i;
+                    if <$t>::is_nan(self) {
+                        bits = if nans_first {
+                            bits | SIGN_BIT
+                        } else {
+                            bits & !SIGN_BIT
+                        };
+                    }
+
+                    // Same as `total_cmp`, negative values order inversely to their bits, so all
+                    // bits but the sign bit are flipped for them.
+                    bits ^= (((bits >> (<$i>::BITS - 1)) as $u) >> 1) as $i;
+                    bits as i64
+                }
+            }
+        )*
+    };
+}
+
+sortable_float_impl!(f32 => i32, u32; f64 => i64, u64);
+
+/// Same as [`sort_by`], but ignores the research overrides like [`set_small_sort_threshold`] and
+/// [`set_fulcrum_enabled`].
+///
+/// The sequence of calls to `compare`, which elements in which argument order, only depends on the
+/// input and the results returned by `compare`. Not on the thread, earlier sorts, or the addresses
+/// of the elements. For a fixed input and a deterministic `compare`, the call sequence is always the
+/// same, so `compare` may record or memoize into external state and behave predictably. The same
+/// holds for [`sort_by`] as long as the overrides are left at their defaults. The call sequence is
+/// not stable across versions of this crate.
+pub fn sort_deterministic<T, F>(v: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
+
+    let config = RuntimeConfig::COMPILED;
+
+    if let Some(limit) = quicksort_prelude(v, &mut is_less, Some(config)) {
+        recurse(v, &mut is_less, None, limit, config);
     }
+}
 
-    // It's important to differentiate between small-sort performance for small slices and
-    // small-sort performance sorting small sub-slices as part of the main quicksort loop. For the
-    // former, testing showed that the representative benchmarks for real-world performance are cold
-    // CPU state and not single-size hot benchmarks. For the latter the CPU will call them many
-    // times, so hot benchmarks are fine and more realistic. And it's worth it to optimize sorting
-    // small sub-slices with more sophisticated solutions than insertion sort.
+/// Index of an element that is expected to be a good pivot, e.g. a known approximate median.
+///
+/// See [`sort_by_with_hint`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PivotHint(pub usize);
 
-    if intrinsics::likely(len <= MAX_LEN_ALWAYS_INSERTION_SORT) {
-        // More specialized and faster options, extending the range of allocation free sorting
-        // are possible but come at a great cost of additional code, which is problematic for
-        // compile-times.
-        insertion_sort_shift_left(v, 1, &mut is_less);
+/// Sorts the slice with a comparator function, using `hint` as the first pivot instead of sampling
+/// one with `choose_pivot`.
+///
+/// This is useful when sorting many slices drawn from the same known distribution, where the
+/// caller can cheaply provide a good approximation of the median. Only the first partition uses
+/// the hint, all further pivots are chosen as usual. A hint that is out of bounds for `arr` is
+/// ignored, and the result is the same as calling [`sort_by`].
+#[inline(always)]
+pub fn sort_by_with_hint<T, F>(arr: &mut [T], mut compare: F, hint: PivotHint)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    quicksort_with_hint(arr, |a, b| compare(a, b) == Ordering::Less, hint);
+}
 
-        return;
+/// Sorts the slice with a comparator function, but might not preserve the order of equal
+/// elements, choosing every pivot with the median-of-medians algorithm.
+///
+/// Unlike [`sort_by`], which relies on a heapsort fallback to bound the worst-case, every partition
+/// step is guaranteed to be balanced. Elements smaller and greater than each pivot are at most ~70%
+/// of the sub-slice, and elements equal to it are excluded from further recursion, so the
+/// recursion depth is *O*(log(*n*)) for every input. The heapsort fallback is only kept for
+/// comparison functions that don't implement a total order. This costs significantly more
+/// comparisons for the average input.
+#[inline(always)]
+pub fn sort_by_guaranteed<T, F>(arr: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    quicksort_guaranteed(arr, |a, b| compare(a, b) == Ordering::Less, &mut ());
+}
+
+/// Same as [`sort_by_guaranteed`], but calls `on_fallback` every time a sub-slice exceeds the
+/// recursion limit and is sorted with heapsort instead.
+///
+/// Research and test only. With median-of-medians pivots the limit is never exceeded for a
+/// `compare` that implements a total order, no matter the input, so any call points to an invalid
+/// order.
+pub fn sort_by_guaranteed_on_fallback<T, F, C>(v: &mut [T], mut compare: F, on_fallback: C)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    C: FnMut(),
+{
+    quicksort_guaranteed(
+        v,
+        |a, b| compare(a, b) == Ordering::Less,
+        &mut FallbackObserver(on_fallback),
+    );
+}
+
+/// Sorts the slice with [`heapsort`] only, but might not preserve the order of equal elements.
+///
+/// Meant for callers that need a tight bound on the latency of every call, not the best average.
+/// There is no pattern detection, no small-sort and no recursion. Every input takes at most
+/// 2 \* *n* \* log2(*n*) comparisons, and the run time depends far less on the input pattern than
+/// for [`sort`]. On average this is several times slower than [`sort`] though, because the heap
+/// accesses are cache unfriendly and hard to predict.
+#[inline(always)]
+pub fn sort_heap<T>(arr: &mut [T])
+where
+    T: Ord,
+{
+    heapsort(arr, &mut |a: &T, b: &T| a.lt(b));
+}
+
+/// Same as [`sort_heap`], with a comparator function.
+#[inline(always)]
+pub fn sort_heap_by<T, F>(arr: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    heapsort(arr, &mut |a: &T, b: &T| compare(a, b) == Ordering::Less);
+}
+
+/// Same as [`sort_by`], but switches to the [`Fallback`] `FB` instead of heapsort once too many
+/// imbalanced partitions were made.
+///
+/// Research only, allows comparing fallback strategies on adversarial inputs. With [`Heapsort`]
+/// this behaves exactly like [`sort_by`].
+pub fn sort_by_with_fallback<T, F, FB>(arr: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    FB: Fallback,
+{
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
+
+    if let Some(limit) = quicksort_prelude(arr, &mut is_less, None) {
+        recurse_with_fallback::<T, _, FB, _>(
+            arr,
+            &mut is_less,
+            None,
+            limit,
+            RuntimeConfig::load(),
+            &mut (),
+        );
     }
+}
 
-    let (streak_end, was_reversed) = find_streak(v, &mut is_less);
-    if streak_end == len {
-        if was_reversed {
-            v.reverse();
-        }
+/// Statistics about one [`sort_by_stats`] call.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SortStats {
+    /// Maximum number of nested partitions, i.e. the depth of the quicksort recursion tree
+    /// including the iterations that continue in a loop instead of recursing.
+    pub max_recursion_depth: u32,
+    /// Number of sub-slices that exceeded the limit of imbalanced partitions and were sorted with
+    /// heapsort instead.
+    pub heapsort_fallbacks: usize,
+    /// Number of partitions, including the ones that split off elements equal to an ancestor
+    /// pivot.
+    pub total_partitions: usize,
+    pub comparisons: u64,
+}
+
+/// Same as [`sort_by`], but returns statistics about the main quicksort loop.
+///
+/// Research only, for characterizing how balanced partitions are and how often the heapsort
+/// fallback is hit for different input distributions. Inputs that are handled before the main
+/// loop, e.g. small or presorted ones, report zero partitions. The work done by the presorted
+/// path, that sorts only the unsorted rest of a mostly sorted input, is not included either, but
+/// its comparisons are.
+pub fn sort_by_stats<T, F>(v: &mut [T], mut compare: F) -> SortStats
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    struct StatsObserver {
+        root_limit: u32,
+        stats: SortStats,
+    }
 
-        // TODO if streak_end >= len / 2 | quicksort the rest and merge via rotation merge.
+    impl<U> RecurseObserver<U> for StatsObserver {
+        fn on_partition(&mut self, limit: u32) {
+            let depth = self.root_limit - limit;
+            self.stats.max_recursion_depth = cmp::max(self.stats.max_recursion_depth, depth);
+            self.stats.total_partitions += 1;
+        }
 
-        return;
+        fn on_fallback(&mut self) {
+            self.stats.heapsort_fallbacks += 1;
+        }
     }
 
-    // Limit the number of imbalanced partitions to `2 * floor(log2(len))`.
-    // The binary OR by one is used to eliminate the zero-check in the logarithm.
-    let limit = 2 * (len | 1).ilog2();
+    let mut comparisons = 0;
+    let mut is_less = |a: &T, b: &T| {
+        comparisons += 1;
+        compare(a, b) == Ordering::Less
+    };
+
+    let mut observer = StatsObserver {
+        root_limit: 0,
+        stats: SortStats::default(),
+    };
 
-    recurse(v, &mut is_less, None, limit);
+    if let Some(limit) = quicksort_prelude(v, &mut is_less, None) {
+        observer.root_limit = limit;
+        recurse_with_fallback::<T, _, DefaultFallback, _>(
+            v,
+            &mut is_less,
+            None,
+            limit,
+            RuntimeConfig::load(),
+            &mut observer,
+        );
+    }
+
+    SortStats {
+        comparisons,
+        ..observer.stats
+    }
 }
 
-/// Finds a streak of presorted elements starting at the beginning of the slice. Returns the first
-/// value that is not part of said streak, and a bool denoting wether the streak was reversed.
-/// Streaks can be increasing or decreasing.
-fn find_streak<T, F>(v: &[T], is_less: &mut F) -> (usize, bool)
+/// Same as [`sort_by`], but calls `on_fallback` every time a sub-slice exceeds the limit of
+/// imbalanced partitions and is sorted with heapsort instead.
+///
+/// Random inputs practically never hit the fallback, so frequent calls point to inputs crafted
+/// against the pivot selection, or a `compare` that doesn't implement a total order. Services can
+/// use this to detect algorithmic complexity attacks on their sort workloads. The sort itself is
+/// the same as [`sort_by`], `on_fallback` is called before each fallback, and [`sort_by`] is not
+/// affected by the hook.
+pub fn sort_by_on_fallback<T, F, C>(v: &mut [T], mut compare: F, on_fallback: C)
 where
-    F: FnMut(&T, &T) -> bool,
+    F: FnMut(&T, &T) -> Ordering,
+    C: FnMut(),
 {
-    let len = v.len();
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
 
-    if len < 2 {
-        return (len, false);
+    if <T as UnstableSortTypeImpl>::sort_exact_network(v, &mut is_less) {
+        return;
     }
 
-    let mut end = 2;
+    if let Some(limit) = quicksort_prelude(v, &mut is_less, None) {
+        recurse_with_fallback::<T, _, DefaultFallback, _>(
+            v,
+            &mut is_less,
+            None,
+            limit,
+            RuntimeConfig::load(),
+            &mut FallbackObserver(on_fallback),
+        );
+    }
+}
 
-    // SAFETY: See below specific.
-    unsafe {
-        // SAFETY: We checked that len >= 2, so 0 and 1 are valid indices.
-        let assume_reverse = is_less(v.get_unchecked(1), v.get_unchecked(0));
+/// Calls the wrapped closure on every fallback, see [`sort_by_on_fallback`].
+struct FallbackObserver<C>(C);
 
-        // SAFETY: We know end >= 2 and check end < len.
-        // From that follows that accessing v at end and end - 1 is safe.
-        if assume_reverse {
-            while end < len && is_less(v.get_unchecked(end), v.get_unchecked(end - 1)) {
-                end += 1;
-            }
+impl<T, C: FnMut()> RecurseObserver<T> for FallbackObserver<C> {
+    #[inline(always)]
+    fn on_partition(&mut self, _limit: u32) {}
 
-            (end, true)
-        } else {
-            while end < len && !is_less(v.get_unchecked(end), v.get_unchecked(end - 1)) {
-                end += 1;
-            }
-            (end, false)
-        }
+    fn on_fallback(&mut self) {
+        (self.0)();
     }
 }
 
-/// Sorts `v` using heapsort, which guarantees *O*(*n* \* log(*n*)) worst-case.
+/// Recursion tree of the main quicksort loop of one [`sort_by_traced`] call.
 ///
-/// Never inline this, it sits the main hot-loop in `recurse` and is meant as unlikely algorithmic
-/// fallback.
-#[inline(never)]
-pub fn heapsort<T, F>(v: &mut [T], is_less: &mut F)
+/// `nodes[0]` is the root and covers the whole slice. Every other node is a part of the sub-slice
+/// of its parent. The leaves tile the slice, they are the sub-slices that were sorted without
+/// another partition, e.g. by the small-sort or the heapsort fallback, or that only contain
+/// elements equal to a pivot.
+#[cfg(feature = "pivot_trace")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PivotTree<T> {
+    pub nodes: Vec<PivotNode<T>>,
+}
+
+/// A sub-slice in a [`PivotTree`].
+#[cfg(feature = "pivot_trace")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PivotNode<T> {
+    /// Bounds of the sub-slice in the sorted slice.
+    pub range: Range<usize>,
+    /// Clone of the pivot the sub-slice was partitioned around, `None` for leaves.
+    pub pivot: Option<T>,
+    /// Fraction of the sub-slice that ended up in front of the pivot, in `[0, 1]`. `None` for
+    /// leaves.
+    pub split_ratio: Option<f64>,
+    /// Indices of the nodes for the non-empty parts of the sub-slice, in order: elements less
+    /// than the pivot, elements equal to it, and the rest. Empty for leaves.
+    pub children: Vec<usize>,
+}
+
+/// Same as [`sort_by`], but returns the recursion tree of the main quicksort loop, with a clone
+/// of every pivot.
+///
+/// Research only, for rendering the recursion and studying the balance of pivots for different
+/// input types. Like for [`sort_by_stats`], inputs that are handled before the main loop, e.g.
+/// small or presorted ones, yield a tree with the root as only node.
+#[cfg(feature = "pivot_trace")]
+pub fn sort_by_traced<T, F>(v: &mut [T], mut compare: F) -> PivotTree<T>
 where
-    F: FnMut(&T, &T) -> bool,
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
 {
-    // This binary heap respects the invariant `parent >= child`.
-    let mut sift_down = |v: &mut [T], mut node| {
-        loop {
-            // Children of `node`.
-            let mut child = 2 * node + 1;
-            if child >= v.len() {
-                break;
-            }
+    struct PivotTracer<T> {
+        v_ptr: *const T,
+        nodes: Vec<PivotNode<T>>,
+        /// Node index of every leaf by its range, a leaf becomes an inner node when its sub-slice
+        /// is split.
+        leaves: std::collections::HashMap<(usize, usize), usize>,
+    }
 
-            // Choose the greater child.
-            if child + 1 < v.len() {
-                // We need a branch to be sure not to out-of-bounds index,
-                // but it's highly predictable.  The comparison, however,
-                // is better done branchless, especially for primitives.
-                child += is_less(&v[child], &v[child + 1]) as usize;
-            }
+    impl<T> PivotTracer<T> {
+        fn index(&self, elem_ptr: *const T) -> usize {
+            // SAFETY: Every sub-slice passed to the observer is part of the traced slice.
+            unsafe { elem_ptr.offset_from(self.v_ptr) as usize }
+        }
 
-            // Stop if the invariant holds at `node`.
-            if !is_less(&v[node], &v[child]) {
-                break;
+        fn push_leaf(&mut self, range: Range<usize>) -> usize {
+            let node = self.nodes.len();
+            self.leaves.insert((range.start, range.end), node);
+            self.nodes.push(PivotNode {
+                range,
+                pivot: None,
+                split_ratio: None,
+                children: Vec::new(),
+            });
+
+            node
+        }
+    }
+
+    impl<T: Clone> RecurseObserver<T> for PivotTracer<T> {
+        fn on_partition(&mut self, _limit: u32) {}
+
+        fn on_fallback(&mut self) {}
+
+        fn on_split(&mut self, left: &[T], equal: &[T], right: &[T]) {
+            let start = self.index(left.as_ptr());
+            let mid = start + left.len();
+            let equal_end = mid + equal.len();
+            let end = equal_end + right.len();
+
+            let node = self
+                .leaves
+                .remove(&(start, end))
+                .expect("split sub-slice is not a leaf");
+
+            let mut children = Vec::new();
+            for range in [start..mid, mid..equal_end, equal_end..end] {
+                if !range.is_empty() {
+                    children.push(self.push_leaf(range));
+                }
             }
 
-            // Swap `node` with the greater child, move one step down, and continue sifting.
-            v.swap(node, child);
-            node = child;
+            let node = &mut self.nodes[node];
+            node.pivot = Some(equal[0].clone());
+            node.split_ratio = Some(left.len() as f64 / (end - start) as f64);
+            node.children = children;
         }
-    };
+    }
 
-    // Build the heap in linear time.
-    for i in (0..v.len() / 2).rev() {
-        sift_down(v, i);
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
+
+    let mut tracer = PivotTracer {
+        v_ptr: v.as_ptr(),
+        nodes: Vec::new(),
+        leaves: std::collections::HashMap::new(),
+    };
+    tracer.push_leaf(0..v.len());
+
+    if let Some(limit) = quicksort_prelude(v, &mut is_less, None) {
+        recurse_with_fallback::<T, _, DefaultFallback, _>(
+            v,
+            &mut is_less,
+            None,
+            limit,
+            RuntimeConfig::load(),
+            &mut tracer,
+        );
     }
 
-    // Pop maximal elements from the heap.
-    for i in (1..v.len()).rev() {
-        v.swap(0, i);
-        sift_down(&mut v[..i], 0);
+    PivotTree {
+        nodes: tracer.nodes,
     }
 }
 
-/// TODO explain
-#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
-#[inline(always)]
-unsafe fn swap_elements_between_blocks<T>(
-    l_ptr: *mut T,
-    r_ptr: *mut T,
-    mut l_offsets_ptr: *const u8,
-    mut r_offsets_ptr: *const u8,
-    count: usize,
-) -> (*const u8, *const u8) {
-    macro_rules! left {
-        () => {
-            l_ptr.add(*l_offsets_ptr as usize)
-        };
+/// Same as [`sort_by`], but returns the number of observed element moves.
+///
+/// Research only. Moves happen through `ptr::copy` and friends deep inside the partition and
+/// small-sort loops, which can't be hooked without slowing down the sort itself. Instead, every
+/// element is wrapped in a [`Tracked`] that carries a unique id, and the last address each id was
+/// seen at is kept in a side table. Whenever an element is passed to `compare`, and once more for
+/// all elements after the sort, its current address is compared to the last one seen, and a
+/// difference counts as one move. Copies to temporaries, like the pivot copy on the stack, are
+/// observed as well.
+///
+/// Several moves of the same element between two observations count only once, so the result is a
+/// lower bound on the number of element writes. The wrapper doesn't add interior mutability, so
+/// `Tracked<T>` takes the same type-based code paths as other types of its size, e.g.
+/// `Tracked<i32>` is 8 bytes and sorted like a `u64`. `v` is sorted by cloning the sorted
+/// wrapped elements back into it.
+pub fn sort_by_move_counted<T, F>(v: &mut [T], mut compare: F) -> u64
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(v.len() <= u32::MAX as usize);
+
+    let mut tracked = v
+        .iter()
+        .enumerate()
+        .map(|(id, value)| Tracked {
+            value: value.clone(),
+            id: id as u32,
+        })
+        .collect::<Vec<_>>();
+
+    let mut last_addr = tracked
+        .iter()
+        .map(|elem| elem as *const Tracked<T> as usize)
+        .collect::<Vec<_>>();
+    let mut moves = 0;
+
+    let mut observe = |elem: &Tracked<T>, last_addr: &mut [usize]| {
+        let addr = elem as *const Tracked<T> as usize;
+        let last = &mut last_addr[elem.id as usize];
+        if *last != addr {
+            *last = addr;
+            moves += 1;
+        }
+    };
+
+    sort_by(&mut tracked, |a, b| {
+        observe(a, &mut last_addr);
+        observe(b, &mut last_addr);
+        compare(&a.value, &b.value)
+    });
+
+    for elem in &tracked {
+        observe(elem, &mut last_addr);
     }
-    macro_rules! right {
-        () => {
-            r_ptr.sub(*r_offsets_ptr as usize + 1)
-        };
+
+    for (dst, elem) in v.iter_mut().zip(tracked) {
+        *dst = elem.value;
     }
 
-    if count <= 1 {
-        if count == 1 {
-            // SAFETY: TODO
-            unsafe {
-                ptr::swap_nonoverlapping(left!(), right!(), 1);
-                l_offsets_ptr = l_offsets_ptr.add(1);
-                r_offsets_ptr = r_offsets_ptr.add(1);
-            }
-        }
+    moves
+}
 
-        return (l_offsets_ptr, r_offsets_ptr);
+/// Element wrapper of [`sort_by_move_counted`].
+#[derive(Clone, Debug)]
+pub struct Tracked<T> {
+    pub value: T,
+    pub id: u32,
+}
+
+/// Sorts the slice and returns its number of inversions, the number of pairs `i < j` with
+/// `v[i] > v[j]`.
+///
+/// The inversion count measures how far `v` is from being sorted, e.g. for Kendall's tau.
+/// Quicksort can't count them cheaply, partitioning moves elements past others they are never
+/// compared with. So this is a top-down merge sort instead, that counts the inversions between both
+/// halves while merging them with `merge_up`, and uses insertion sort for short slices, where every
+/// shift is one inversion. Equal elements are no inversion and keep their relative order. Runs in
+/// O(n log n), with a buffer of `v.len()` elements.
+///
+/// The count saturates at `u64::MAX`, which can only happen for more than ~6 * 10^9 elements.
+/// Should `T::cmp` panic, `v` keeps all its elements in unspecified order.
+pub fn sort_and_count_inversions<T>(v: &mut [T]) -> u64
+where
+    T: Ord,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if const { mem::size_of::<T>() == 0 } {
+        return 0;
     }
 
-    // Instead of swapping one pair at the time, it is more efficient to perform a cyclic
-    // permutation. This is not strictly equivalent to swapping, but produces a similar
-    // result using fewer memory operations.
-    //
+    // The buffer stays empty, so it never drops any of the elements merged into it.
+    let mut buf = Vec::<T>::with_capacity(v.len());
+
+    // SAFETY: `buf` is valid for `v.len()` writes and doesn't alias `v`.
+    unsafe { merge_sort_count_inversions(v, buf.as_mut_ptr(), &mut |a: &T, b: &T| a.lt(b)) }
+}
+
+/// SAFETY: The caller must guarantee that `scratch_ptr` is valid for `v.len()` writes and doesn't
+/// alias `v`.
+unsafe fn merge_sort_count_inversions<T, F>(
+    v: &mut [T],
+    scratch_ptr: *mut T,
+    is_less: &mut F,
+) -> u64
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    const MAX_LEN_INSERTION_SORT: usize = 20;
+
+    let len = v.len();
+
+    if len <= MAX_LEN_INSERTION_SORT {
+        let mut inversions = 0;
+        if len >= 2 {
+            // Every comparison that returns true shifts the inserted element past a greater one.
+            insertion_sort_shift_left(v, 1, &mut |a: &T, b: &T| {
+                let less = is_less(a, b);
+                inversions += less as u64;
+                less
+            });
+        }
+        return inversions;
+    }
+
+    let mid = len / 2;
+
+    // SAFETY: See function safety comment, both halves are shorter than `v`.
+    let mut inversions = unsafe {
+        merge_sort_count_inversions(&mut v[..mid], scratch_ptr, is_less).saturating_add(
+            merge_sort_count_inversions(&mut v[mid..], scratch_ptr, is_less),
+        )
+    };
+
+    // SAFETY: All reads are in bounds of the two halves, as each is only read while it has
+    // elements left, and exactly `len` elements are written to `scratch_ptr`. The merge only reads
+    // from `v`, so should `is_less` panic, `v` still holds every element exactly once, and the
+    // bitwise copies in the scratch are never dropped.
+    unsafe {
+        let v_ptr = v.as_mut_ptr();
+        let left_end = v_ptr.add(mid) as *const T;
+        let right_end = v_ptr.add(len) as *const T;
+
+        let mut left = v_ptr as *const T;
+        let mut right = left_end;
+        let mut dest = scratch_ptr;
+
+        while left < left_end && right < right_end {
+            let right_before = right;
+            (left, right, dest) = merge_up(left, right, dest, is_less);
+            // Taking from the right half moves that element past all remaining ones of the left
+            // half.
+            let took_right = (right != right_before) as u64;
+            inversions = inversions.saturating_add(took_right * left_end.sub_ptr(left) as u64);
+        }
+
+        // At most one of the two halves has elements left, they are already in place relative to
+        // each other.
+        let left_rest = left_end.sub_ptr(left);
+        ptr::copy_nonoverlapping(left, dest, left_rest);
+        ptr::copy_nonoverlapping(right, dest.add(left_rest), right_end.sub_ptr(right));
+
+        ptr::copy_nonoverlapping(scratch_ptr, v_ptr, len);
+    }
+
+    inversions
+}
+
+/// Comparison logic that can be used as trait object, see [`sort_with_comparator`].
+pub trait Comparator<T> {
+    /// Same contract as the comparator function of [`sort_by`].
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+impl<T, F> Comparator<T> for F
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// Sorts the slice with a [`Comparator`] trait object, but might not preserve the order of equal
+/// elements.
+///
+/// Unlike [`sort_by`], the sort is only instantiated once per `T`, no matter how many different
+/// comparators are used. This is useful if the comparison logic is only known at runtime, e.g.
+/// when it's loaded from a plugin. Every comparison is a dynamic call, which is slower than the
+/// inlined comparisons of [`sort_by`].
+pub fn sort_with_comparator<T>(v: &mut [T], comparator: &dyn Comparator<T>) {
+    quicksort(v, |a, b| comparator.compare(a, b) == Ordering::Less);
+}
+
+/// Sorts the slice with a comparator function, but might not preserve the order of equal
+/// elements. Yields to the async runtime while sorting.
+///
+/// Large inputs are partitioned with an explicit work stack instead of recursion. After every
+/// ~64k elements of work, the sort yields once, so that other tasks on a cooperative runtime
+/// can make progress. Sub-slices that are small enough are sorted in one go. Unlike [`sort_by`]
+/// this allocates the work stack, which holds *O*(log(*n*)) entries.
+pub async fn sort_by_yielding<T, F>(v: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // Sub-slices up to this length are sorted without yielding.
+    const YIELD_INTERVAL_ELEMS: usize = 1 << 16;
+
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
+
+    let Some(limit) = quicksort_prelude(v, &mut is_less, None) else {
+        return;
+    };
+
+    let config = RuntimeConfig::load();
+
+    let mut work = vec![(0, v.len(), None, limit)];
+    let mut elems_since_yield = 0;
+
+    while let Some(item) = work.pop() {
+        let (start, end, ancestor_idx, limit) = item;
+
+        if elems_since_yield >= YIELD_INTERVAL_ELEMS {
+            YieldNow { yielded: false }.await;
+            elems_since_yield = 0;
+        }
+        elems_since_yield += end - start;
+
+        if end - start <= YIELD_INTERVAL_ELEMS || limit == 0 {
+            let (head, tail) = v.split_at_mut(start);
+            let ancestor_pivot = ancestor_idx.map(|i| &head[i]);
+            recurse(
+                &mut tail[..(end - start)],
+                &mut is_less,
+                ancestor_pivot,
+                limit,
+                config,
+            );
+            continue;
+        }
+
+        split_work_item(v, item, &mut work, &mut is_less, config);
+    }
+}
+
+/// Sorts the slice with a comparator function, but might not preserve the order of equal
+/// elements. Stops early if `cancel` is set.
+///
+/// Like [`sort_by_yielding`], large inputs are partitioned with an explicit work stack, and
+/// `cancel` is checked before each work item. Sub-slices of up to ~64k elements are sorted in one
+/// go, so the sort returns shortly after `cancel` is set, with `Err(Cancelled)`. `v` is then a
+/// permutation of the input, but not necessarily sorted. The analysis of presorted inputs before
+/// the first work item is not interruptible, it is *O*(*n*) except for mostly sorted inputs. If
+/// the sort finishes before `cancel` is observed, `Ok(())` is returned even if it's set.
+pub fn sort_by_cancellable<T, F>(
+    v: &mut [T],
+    mut compare: F,
+    cancel: &AtomicBool,
+) -> Result<(), Cancelled>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // Sub-slices up to this length are sorted without checking `cancel`.
+    const CHECK_INTERVAL_ELEMS: usize = 1 << 16;
+
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
+
+    let Some(limit) = quicksort_prelude(v, &mut is_less, None) else {
+        return Ok(());
+    };
+
+    let config = RuntimeConfig::load();
+
+    let mut work = vec![(0, v.len(), None, limit)];
+
+    while let Some(item) = work.pop() {
+        if cancel.load(atomic::Ordering::Relaxed) {
+            return Err(Cancelled);
+        }
+
+        let (start, end, ancestor_idx, limit) = item;
+
+        if end - start <= CHECK_INTERVAL_ELEMS || limit == 0 {
+            let (head, tail) = v.split_at_mut(start);
+            let ancestor_pivot = ancestor_idx.map(|i| &head[i]);
+            recurse(
+                &mut tail[..(end - start)],
+                &mut is_less,
+                ancestor_pivot,
+                limit,
+                config,
+            );
+            continue;
+        }
+
+        split_work_item(v, item, &mut work, &mut is_less, config);
+    }
+
+    Ok(())
+}
+
+/// Returned by [`sort_by_cancellable`] if the sort was cancelled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+/// Sorts the slice with a comparator function, but might not preserve the order of equal
+/// elements. Stops once `compare` was called `max_comparisons` times.
+///
+/// Meant as a tripwire against comparison functions or inputs that make the sort run for much
+/// longer than expected. The sort does *O*(*n* \* log(*n*)) comparisons, so a budget of a small
+/// multiple of `n * log2(n)` is only exceeded if something is wrong. If the sort needs more than
+/// `max_comparisons` comparisons, `Err(BudgetExceeded)` is returned. `v` is then a permutation of
+/// the input, but not necessarily sorted.
+///
+/// The sort runs as one [`SortSession::step`] with `max_comparisons` as budget, so the budget is
+/// checked between work items. It can be exceeded by about the length of the largest remaining
+/// sub-slice, and inputs that are handled before the first work item, e.g. fully presorted ones or
+/// ones of up to 20 elements, are always sorted.
+pub fn sort_by_budget<T, F>(
+    v: &mut [T],
+    compare: F,
+    max_comparisons: u64,
+) -> Result<(), BudgetExceeded>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let budget = usize::try_from(max_comparisons).unwrap_or(usize::MAX);
+
+    match SortSession::new(v, compare).step(budget) {
+        SortProgress::Done => Ok(()),
+        SortProgress::InProgress => Err(BudgetExceeded),
+    }
+}
+
+/// Returned by [`sort_by_budget`] if the sort needed more comparisons than allowed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded;
+
+/// Sort that runs in steps of bounded work, e.g. to sort a large table in an interactive
+/// application between frames.
+///
+/// Like [`sort_by_cancellable`], large inputs are partitioned with an explicit work stack. The
+/// stack is kept between calls to [`step`](SortSession::step), which does work items until the
+/// comparisons it made reach its budget. A work item is one partition of a sub-slice, or sorting
+/// a sub-slice of up to 256 elements in one go, so a step can exceed its budget by about the
+/// length of the largest remaining sub-slice. The first step also does the analysis of presorted
+/// inputs, which is *O*(*n*).
+///
+/// Between steps, `v` is a permutation of the input in which every element outside of
+/// [`unsorted_ranges`](SortSession::unsorted_ranges) is already at its final position.
+pub struct SortSession<'a, T, F> {
+    v: &'a mut [T],
+    compare: F,
+    config: RuntimeConfig,
+    work: Vec<WorkItem>,
+    started: bool,
+}
+
+/// Returned by [`SortSession::step`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortProgress {
+    InProgress,
+    Done,
+}
+
+impl<'a, T, F> SortSession<'a, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    /// Sub-slices up to this length are sorted in one work item.
+    const LEAF_ELEMS: usize = 256;
+
+    /// Creates a session that sorts `v` with `compare`, without doing any work yet.
+    pub fn new(v: &'a mut [T], compare: F) -> Self {
+        Self {
+            v,
+            compare,
+            config: RuntimeConfig::load(),
+            work: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Sorts until `budget` comparisons have been made, or `v` is sorted. Always does at least
+    /// one work item.
+    pub fn step(&mut self, budget: usize) -> SortProgress {
+        let compare = &mut self.compare;
+        let comparisons = Cell::new(0);
+        let mut is_less = |a: &T, b: &T| {
+            comparisons.set(comparisons.get() + 1);
+            compare(a, b) == Ordering::Less
+        };
+
+        if !self.started {
+            self.started = true;
+            if let Some(limit) = quicksort_prelude(self.v, &mut is_less, Some(self.config)) {
+                self.work.push((0, self.v.len(), None, limit));
+            }
+        } else if let Some(item) = self.work.pop() {
+            Self::do_work_item(self.v, item, &mut self.work, &mut is_less, self.config);
+        }
+
+        while comparisons.get() < budget {
+            let Some(item) = self.work.pop() else {
+                break;
+            };
+            Self::do_work_item(self.v, item, &mut self.work, &mut is_less, self.config);
+        }
+
+        if self.work.is_empty() {
+            SortProgress::Done
+        } else {
+            SortProgress::InProgress
+        }
+    }
+
+    /// The slice in its current order, e.g. to display the already sorted parts.
+    pub fn as_slice(&self) -> &[T] {
+        self.v
+    }
+
+    /// Ranges of `v` that still need sorting. Each of them holds exactly the elements that end
+    /// up there, just not yet in order.
+    pub fn unsorted_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let whole = (!self.started).then_some(0..self.v.len());
+        whole
+            .into_iter()
+            .chain(self.work.iter().map(|&(start, end, _, _)| start..end))
+    }
+
+    fn do_work_item(
+        v: &mut [T],
+        item: WorkItem,
+        work: &mut Vec<WorkItem>,
+        is_less: &mut impl FnMut(&T, &T) -> bool,
+        config: RuntimeConfig,
+    ) {
+        let (start, end, ancestor_idx, limit) = item;
+
+        if end - start <= Self::LEAF_ELEMS || limit == 0 {
+            let (head, tail) = v.split_at_mut(start);
+            let ancestor_pivot = ancestor_idx.map(|i| &head[i]);
+            recurse(
+                &mut tail[..(end - start)],
+                is_less,
+                ancestor_pivot,
+                limit,
+                config,
+            );
+        } else {
+            split_work_item(v, item, work, is_less, config);
+        }
+    }
+}
+
+/// Sub-slice `v[start..end]` that remains to be sorted by an explicit-stack quicksort, as
+/// `(start, end, ancestor_pivot, limit)`. `ancestor_pivot` is the index of the predecessor pivot,
+/// which is always left of `start`.
+type WorkItem = (usize, usize, Option<usize>, u32);
+
+/// One iteration of `recurse` on `item`, with the sides that still need sorting pushed to `work`
+/// instead of recursing. The shorter side is pushed last, which keeps `work` at *O*(log(*n*))
+/// entries. `item.3`, the limit, must not be zero.
+fn split_work_item<T, F>(
+    v: &mut [T],
+    item: WorkItem,
+    work: &mut Vec<WorkItem>,
+    is_less: &mut F,
+    config: RuntimeConfig,
+) where
+    F: FnMut(&T, &T) -> bool,
+{
+    let (start, end, ancestor_idx, limit) = item;
+
+    let (head, tail) = v.split_at_mut(start);
+    let sub = &mut tail[..(end - start)];
+    let ancestor_pivot = ancestor_idx.map(|i| &head[i]);
+
+    let pivot = choose_pivot(sub, is_less, config);
+
+    if let Some(p) = ancestor_pivot {
+        if !is_less(p, &sub[pivot]) {
+            let mid = partition_equal(sub, pivot, is_less);
+            work.push((start + mid + 1, end, None, limit - 1));
+            return;
+        }
+    }
+
+    if is_all_equal(sub, pivot, is_less) {
+        return;
+    }
+
+    let (mid, _) = partition_configured(sub, pivot, is_less, config.fulcrum_enabled);
+
+    let right = &mut sub[mid..];
+    let split_equal = has_many_equal_to_pivot(right, is_less);
+    let equal_len = if split_equal {
+        partition_equal(right, 0, is_less) + 1
+    } else {
+        1
+    };
+
+    let right_ancestor_idx = if split_equal { None } else { Some(start + mid) };
+    let left_item = (start, start + mid, ancestor_idx, limit - 1);
+    let right_item = (start + mid + equal_len, end, right_ancestor_idx, limit - 1);
+
+    if mid < end - start - mid - equal_len {
+        work.push(right_item);
+        work.push(left_item);
+    } else {
+        work.push(left_item);
+        work.push(right_item);
+    }
+}
+
+/// Future that returns `Pending` once, after waking its task, see [`sort_by_yielding`].
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+
+        Poll::Pending
+    }
+}
+
+/// Sorts a slice of boxes by comparing the pointees with `compare`, but might not preserve the
+/// order of equal elements.
+///
+/// This works for unsized pointees like `Box<[u8]>`, `Box<str>` or `Box<dyn Trait>`, and is
+/// equivalent to `sort_by(v, |a, b| compare(&**a, &**b))`. The boxes themselves are moved, the
+/// pointees never are. That makes `Box<T>` qualify for the `Freeze` optimizations regardless of
+/// `T`, any interior mutability of `T` lives behind the pointer and is observed by all copies of
+/// it.
+#[inline(always)]
+pub fn sort_boxed_by<T, F>(v: &mut [Box<T>], mut compare: F)
+where
+    T: ?Sized,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sort_by(v, |a, b| compare(&**a, &**b));
+}
+
+/// Sequence of comparisons performed by [`sort_by_recorded`], which can be replayed with
+/// [`replay`].
+#[cfg(feature = "comparison_trace")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComparisonTrace {
+    pub comparisons: Vec<RecordedComparison>,
+    /// The recorded sort panicked after the last comparison, e.g. because it detected an Ord
+    /// violation.
+    pub panicked: bool,
+}
+
+/// A single comparison `compare(a, b)` and its result.
+///
+/// The indices are positions in the sorted slice at the time of the comparison, not in the
+/// original input. They are `None` if the sort compared a temporary copy of an element, e.g. the
+/// pivot or an element in a scratch buffer.
+#[cfg(feature = "comparison_trace")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RecordedComparison {
+    pub a: Option<usize>,
+    pub b: Option<usize>,
+    pub result: Ordering,
+}
+
+/// Same as [`sort_by`], but records every comparison.
+///
+/// Replaying the returned trace with [`replay`] on the same input yields the same output, even if
+/// `compare` is non-deterministic or doesn't implement a total order. This makes sort bugs found
+/// with such comparison functions, e.g. while fuzzing, reproducible.
+///
+/// A panic of the sort is caught and marked in the trace, otherwise the trace of the failing sort
+/// would be lost. Replaying it panics in the same place.
+#[cfg(feature = "comparison_trace")]
+pub fn sort_by_recorded<T, F>(v: &mut [T], mut compare: F) -> ComparisonTrace
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let (v_ptr, len) = (v.as_ptr(), v.len());
+    let mut comparisons = Vec::new();
+
+    let sort_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        sort_by(v, |a, b| {
+            let result = compare(a, b);
+            comparisons.push(RecordedComparison {
+                a: trace_index(v_ptr, len, a),
+                b: trace_index(v_ptr, len, b),
+                result,
+            });
+
+            result
+        });
+    }));
+
+    ComparisonTrace {
+        comparisons,
+        panicked: sort_result.is_err(),
+    }
+}
+
+/// Sorts `v` with the comparison results recorded in `trace`, instead of calling a comparison
+/// function.
+///
+/// # Panics
+///
+/// Panics if the sort diverges from the recorded one, i.e. compares different positions or needs
+/// more comparisons than recorded. This happens if `v` is not the input `trace` was recorded with,
+/// or if the sort implementation changed.
+#[cfg(feature = "comparison_trace")]
+pub fn replay<T>(trace: &ComparisonTrace, v: &mut [T]) {
+    let (v_ptr, len) = (v.as_ptr(), v.len());
+    let mut comparisons = trace.comparisons.iter().enumerate();
+
+    sort_by(v, |a, b| {
+        let (i, recorded) = comparisons
+            .next()
+            .expect("Replay needs more comparisons than recorded");
+
+        assert_eq!(
+            (recorded.a, recorded.b),
+            (trace_index(v_ptr, len, a), trace_index(v_ptr, len, b)),
+            "Replay diverged at comparison {i}"
+        );
+
+        recorded.result
+    });
+
+    assert!(
+        comparisons.next().is_none(),
+        "Replay needs fewer comparisons than recorded"
+    );
+}
+
+/// Sorts the array and returns it, but might not preserve the order of equal elements.
+///
+/// Arrays that fit into the small-sort are sorted with it directly, skipping the analysis done by
+/// [`sort`] for inputs of unknown length. Because `N` is known at compile-time, all length checks
+/// of the small-sort are resolved at compile-time.
+#[inline(always)]
+pub fn sort_array<T, const N: usize>(mut arr: [T; N]) -> [T; N]
+where
+    T: Ord,
+{
+    let mut is_less = |a: &T, b: &T| a.lt(b);
+
+    if !<T as UnstableSortTypeImpl>::small_sort(&mut arr, &mut is_less) {
+        quicksort(&mut arr, is_less);
+    }
+
+    arr
+}
+
+/// Sorts the indices in `perm` by the relation `less_matrix`, where `a` is less than `b` if
+/// `less_matrix[a][b]` is `true`.
+///
+/// Research only, for studying how the small-sort networks and quicksort behave under a given
+/// relation, including ones that are not a total order, without constructing elements that compare
+/// that way. Takes the same paths as [`sort_array`], so arrays that fit into the small-sort never
+/// reach the quicksort. The relation doesn't need to be consistent. If it isn't, the resulting order
+/// is unspecified and the sort may panic with "Ord violation" when it detects that, but it is
+/// always memory safe and `perm` remains a permutation of its input, even after a panic. The
+/// indices in `perm` only select rows and columns of the matrix, nothing else moves.
+///
+/// # Panics
+///
+/// Panics if an index in `perm` is `N` or larger.
+pub fn sort_from_matrix<const N: usize>(perm: &mut [u32; N], less_matrix: &[[bool; N]; N]) {
+    let mut is_less = |a: &u32, b: &u32| less_matrix[*a as usize][*b as usize];
+
+    if !<u32 as UnstableSortTypeImpl>::small_sort(perm, &mut is_less) {
+        quicksort(perm, is_less);
+    }
+}
+
+/// Sorts each of the `groups`, but might not preserve the order of equal elements.
+///
+/// Equivalent to calling [`sort`] for every group, but the per-call setup is done only once, and
+/// groups that fit into the small-sort are passed to it directly, skipping the analysis done by
+/// [`sort`] for inputs of unknown length. Those small-sorts share one stack scratch buffer, as long
+/// as it fits the stack budget of the small-sorts. This is meant for sorting many small groups.
+#[inline(always)]
+pub fn sort_each<T>(groups: &mut [&mut [T]])
+where
+    T: Ord,
+{
+    sort_each_impl(groups, &mut |a: &T, b: &T| a.lt(b));
+}
+
+/// Sorts each of the `groups` with a comparator function, but might not preserve the order of
+/// equal elements.
+///
+/// See [`sort_each`].
+#[inline(always)]
+pub fn sort_each_by<T, F>(groups: &mut [&mut [T]], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sort_each_impl(groups, &mut |a: &T, b: &T| compare(a, b) == Ordering::Less);
+}
+
+fn sort_each_impl<T, F>(groups: &mut [&mut [T]], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if const { mem::size_of::<T>() == 0 } {
+        return;
+    }
+
+    let config = RuntimeConfig::load();
+
+    // The small-sorts of all groups share one scratch, instead of setting up their own on every
+    // call. Types too large for the stack budget keep the per-call scratch of the small-sorts.
+    if const { mem::size_of::<T>() * SMALL_SORT_SCRATCH_LEN <= MAX_STACK_SCRATCH_BYTES }
+        && config.small_sort == SmallSort::Auto
+    {
+        sort_each_shared_scratch(groups, is_less, config);
+    } else {
+        for group in groups.iter_mut() {
+            sort_group(group, is_less, config, |v, is_less| {
+                small_sort_configured(v, is_less, config.small_sort)
+            });
+        }
+    }
+}
+
+// Kept out of line, so that types above the stack budget never reserve the scratch.
+#[inline(never)]
+fn sort_each_shared_scratch<T, F>(groups: &mut [&mut [T]], is_less: &mut F, config: RuntimeConfig)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let mut scratch = MaybeUninit::<[T; SMALL_SORT_SCRATCH_LEN]>::uninit();
+
+    for group in groups.iter_mut() {
+        sort_group(group, is_less, config, |v, is_less| {
+            <T as UnstableSortTypeImpl>::small_sort_with_scratch(v, &mut scratch, is_less)
+        });
+    }
+}
+
+#[inline(always)]
+fn sort_group<T, F, S>(v: &mut [T], is_less: &mut F, config: RuntimeConfig, small_sort: S)
+where
+    F: FnMut(&T, &T) -> bool,
+    S: FnOnce(&mut [T], &mut F) -> bool,
+{
+    if intrinsics::likely(v.len() <= config.small_sort_threshold) && small_sort(v, is_less) {
+        return;
+    }
+
+    if let Some(limit) = quicksort_prelude(v, is_less, Some(config)) {
+        recurse(v, is_less, None, limit, config);
+    }
+}
+
+/// Collects `iter` into a `Vec` and sorts it, but might not preserve the order of equal elements.
+///
+/// Fuses `let mut v: Vec<_> = iter.collect(); v.sort_unstable();`. The `Vec` is allocated the same
+/// way as by `collect`, only the lower bound of the size hint is preallocated. The upper bound can
+/// be far too large, e.g. for a sparse filter over a huge range, like `(0..u64::MAX).filter(..)`.
+///
+/// # Examples
+///
+/// ```
+/// use sort_comp::unstable::rust_ipnsort::sort_collect;
+///
+/// let v = sort_collect([5, -3, 1, 4, -2].into_iter().filter(|x| *x != 4));
+/// assert_eq!(v, [-3, -2, 1, 5]);
+/// ```
+#[inline(always)]
+pub fn sort_collect<T, I>(iter: I) -> Vec<T>
+where
+    T: Ord,
+    I: IntoIterator<Item = T>,
+{
+    let mut v = iter.into_iter().collect::<Vec<_>>();
+    sort(&mut v);
+    v
+}
+
+/// Collects `iter` into a `Vec` and sorts it with a comparator function, but might not preserve
+/// the order of equal elements.
+///
+/// See [`sort_collect`] and [`sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// use sort_comp::unstable::rust_ipnsort::sort_collect_by;
+///
+/// let v = sort_collect_by(["bb", "a", "ccc"], |a, b| b.len().cmp(&a.len()));
+/// assert_eq!(v, ["ccc", "bb", "a"]);
+/// ```
+#[inline(always)]
+pub fn sort_collect_by<T, I, F>(iter: I, compare: F) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut v = iter.into_iter().collect::<Vec<_>>();
+    sort_by(&mut v, compare);
+    v
+}
+
+/// Sorts the deque, but might not preserve the order of equal elements.
+///
+/// Same as `sort(dq.make_contiguous())`. If the elements wrap around the end of the ring buffer,
+/// `make_contiguous` first rotates them into one contiguous slice inside the existing buffer. This
+/// moves elements, but doesn't allocate.
+#[inline(always)]
+pub fn sort_deque<T>(dq: &mut VecDeque<T>)
+where
+    T: Ord,
+{
+    sort(dq.make_contiguous());
+}
+
+/// Same as [`sort_deque`], with a comparator function.
+#[inline(always)]
+pub fn sort_deque_by<T, F>(dq: &mut VecDeque<T>, compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sort_by(dq.make_contiguous(), compare);
+}
+
+/// Sorts the slice by comparing a borrowed view of each element, but might not preserve the order
+/// of equal elements.
+///
+/// Same as `sort_by(v, |a, b| compare(project(a), project(b)))`. Unlike `sort_by_key`, the key is
+/// borrowed from the element instead of returned by value, so `U` can be unsized or expensive to
+/// clone, e.g. a `str` field of a record. `project` is called twice per comparison, so it should be
+/// cheap, like a field access.
+///
+/// # Examples
+///
+/// ```
+/// use sort_comp::unstable::rust_ipnsort::sort_by_projection;
+///
+/// struct User {
+///     name: String,
+///     id: u32,
+/// }
+///
+/// let mut users = ["carol", "alice", "bob"]
+///     .into_iter()
+///     .zip(0..)
+///     .map(|(name, id)| User { name: name.to_string(), id })
+///     .collect::<Vec<_>>();
+///
+/// sort_by_projection(&mut users, |user| user.name.as_str(), str::cmp);
+/// assert!(users.iter().map(|user| user.id).eq([1, 2, 0]));
+/// ```
+#[inline(always)]
+pub fn sort_by_projection<T, U, P, F>(v: &mut [T], project: P, mut compare: F)
+where
+    U: ?Sized,
+    P: Fn(&T) -> &U,
+    F: FnMut(&U, &U) -> Ordering,
+{
+    quicksort(v, |a, b| compare(project(a), project(b)) == Ordering::Less);
+}
+
+/// Sorts the slice with a key extraction function, but might not preserve the order of equal
+/// elements.
+///
+/// The key is computed once per element and cached, like `slice::sort_by_cached_key`. The cache
+/// stores `(key, index)` pairs, the width of the index is chosen at runtime by
+/// [`cached_key_index_width`].
+pub fn sort_by_cached_key<T, K, F>(v: &mut [T], mut f: F)
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    // Without `FALLIBLE` allocation failures abort, there is no error to handle.
+    let _ = sort_by_cached_key_impl::<T, K, Infallible, _, false>(v, |elem| Ok(f(elem)));
+}
+
+/// Same as [`sort_by_cached_key`], but returns an error instead of aborting if the key cache can't
+/// be allocated.
+///
+/// The cache is allocated before the first key is computed, so `v` is left untouched and `f` is not
+/// called if this returns an error.
+pub fn try_sort_by_cached_key<T, K, F>(v: &mut [T], mut f: F) -> Result<(), TryReserveError>
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    sort_by_cached_key_impl::<T, K, Infallible, _, true>(v, |elem| Ok(f(elem))).map(|_| ())
+}
+
+/// Sorts the slice stably, but returns an error instead of aborting if the scratch memory can't be
+/// allocated.
+///
+/// This is the stable merge sort of [`crate::stable::rust_std`], with all memory allocated up front.
+/// `v` is left untouched if this returns an error.
+pub fn try_sort_stable<T>(v: &mut [T]) -> Result<(), TryReserveError>
+where
+    T: Ord,
+{
+    crate::stable::rust_std::try_sort(v)
+}
+
+/// Implementation of [`sort_by_cached_key`] with a fallible key function. If `FALLIBLE` is true,
+/// the cache is allocated with `try_reserve`.
+///
+/// The outer error is the failed allocation, the inner one the first error returned by `f`. `f` is
+/// not called again after it returned an error, and `v` is only modified if all keys were computed.
+#[inline(always)]
+fn sort_by_cached_key_impl<T, K, E, F, const FALLIBLE: bool>(
+    v: &mut [T],
+    mut f: F,
+) -> Result<Result<(), E>, TryReserveError>
+where
+    F: FnMut(&T) -> Result<K, E>,
+    K: Ord,
+{
+    macro_rules! sort_by_key {
+        ($t:ty, $slice:ident, $f:ident) => {{
+            let mut indices = Vec::new();
+            if FALLIBLE {
+                indices.try_reserve_exact($slice.len())?;
+            } else {
+                indices.reserve_exact($slice.len());
+            }
+            for (i, elem) in $slice.iter().enumerate() {
+                match $f(elem) {
+                    Ok(key) => indices.push((key, i as $t)),
+                    Err(err) => return Ok(Err(err)),
+                }
+            }
+            // The elements of `indices` are unique, as they are indexed, so any sort will be
+            // stable with respect to the original slice.
+            sort(&mut indices);
+            for i in 0..$slice.len() {
+                let mut index = indices[i].1;
+                while (index as usize) < i {
+                    index = indices[index as usize].1;
+                }
+                indices[i].1 = index;
+                $slice.swap(i, index as usize);
+            }
+        }};
+    }
+
+    let len = v.len();
+    if len < 2 {
+        return Ok(Ok(()));
+    }
+
+    match cached_key_index_width::<K>(len) {
+        CachedKeyIndexWidth::U8 => sort_by_key!(u8, v, f),
+        CachedKeyIndexWidth::U16 => sort_by_key!(u16, v, f),
+        CachedKeyIndexWidth::U32 => sort_by_key!(u32, v, f),
+        CachedKeyIndexWidth::Usize => sort_by_key!(usize, v, f),
+    }
+
+    Ok(Ok(()))
+}
+
+/// Sorts the slice with a comparator function, and places equal elements in the reverse of their
+/// original order.
+///
+/// This is a testing tool, not a faster sort. It is the opposite of what a stable sort does, so
+/// code that accidentally relies on the order of equal elements after an unstable sort fails
+/// reliably, instead of only for some inputs. The original index is used as a reversed tiebreaker,
+/// which allocates `v.len()` indices.
+pub fn sort_anti_stable_by<T, F>(v: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let len = v.len();
+    if len < 2 {
+        return;
+    }
+
+    let mut indices = (0..len).collect::<Vec<_>>();
+    sort_by(&mut indices, |&a, &b| {
+        compare(&v[a], &v[b]).then_with(|| b.cmp(&a))
+    });
+
+    // Same permutation as in `sort_by_cached_key`, `indices[i]` holds the original position of the
+    // element that belongs to position `i`.
+    for i in 0..len {
+        let mut index = indices[i];
+        while index < i {
+            index = indices[index];
+        }
+        indices[i] = index;
+        v.swap(i, index);
+    }
+}
+
+/// Sorts `keys`, and moves the elements of `values` the same way, but might not preserve the order
+/// of equal keys.
+///
+/// Meant for struct-of-arrays layouts, where `keys[i]` and `values[i]` belong together. Every key
+/// and value is moved into a buffer of `(key, value)` pairs, which allocates `keys.len()` pairs.
+/// The pairs are sorted by key and moved back, neither `K` nor `V` have to be `Clone`. Sorting an
+/// index permutation instead and applying it to `values` afterwards was ~1.8x slower for 1M random
+/// `u32` keys with `[f32; 3]` values, the random accesses of applying the permutation cost more
+/// than moving the values along during the sort.
+///
+/// # Panics
+///
+/// Panics if `keys` and `values` have different lengths.
+pub fn sort_coupled<K, V>(keys: &mut [K], values: &mut [V])
+where
+    K: Ord,
+{
+    assert_eq!(keys.len(), values.len(), "keys and values differ in length");
+
+    let len = keys.len();
+    if len < 2 {
+        return;
+    }
+
+    // Moves the pairs back into `keys` and `values` in their current order, also if comparing two
+    // keys panics. Until then every key and value is owned by the buffer only.
+    struct WriteBack<K, V> {
+        pairs: *const (K, V),
+        keys: *mut K,
+        values: *mut V,
+        len: usize,
+    }
+
+    impl<K, V> Drop for WriteBack<K, V> {
+        fn drop(&mut self) {
+            for i in 0..self.len {
+                // SAFETY: The buffer holds exactly one bitwise copy of every key and value, and
+                // `keys` and `values` have the same length.
+                unsafe {
+                    let (key, value) = ptr::read(self.pairs.add(i));
+                    ptr::write(self.keys.add(i), key);
+                    ptr::write(self.values.add(i), value);
+                }
+            }
+        }
+    }
+
+    // The buffer never has a length, the pairs in it are owned by `write_back`.
+    let mut buf = Vec::<(K, V)>::with_capacity(len);
+    let pairs = buf.as_mut_ptr();
+
+    // SAFETY: The buffer has capacity for `len` pairs, and each key and value is moved into it
+    // exactly once. Nothing in between can panic, and from here on `write_back` moves them back.
+    let _write_back = unsafe {
+        for i in 0..len {
+            ptr::write(
+                pairs.add(i),
+                (
+                    ptr::read(keys.as_ptr().add(i)),
+                    ptr::read(values.as_ptr().add(i)),
+                ),
+            );
+        }
+
+        WriteBack {
+            pairs,
+            keys: keys.as_mut_ptr(),
+            values: values.as_mut_ptr(),
+            len,
+        }
+    };
+
+    // SAFETY: The first `len` pairs of the buffer are initialized, and the slice is no longer used
+    // once `_write_back` reads them.
+    let pairs = unsafe { core::slice::from_raw_parts_mut(pairs, len) };
+    quicksort(pairs, |a, b| a.0.lt(&b.0));
+}
+
+/// Sorts the slice by the `u32` rank of each element, e.g. from a lookup table for a custom total
+/// order, but might not preserve the order of equal elements.
+///
+/// The comparison is a plain integer comparison of the ranks, which `rank_of` is called for on
+/// every comparison. That keeps the fast paths for few distinct ranks, and beat packing rank and
+/// index into a `u64` and sorting that, for both few and many distinct ranks. If `rank_of` is
+/// expensive, use [`sort_by_cached_key`] instead.
+#[inline(always)]
+pub fn sort_by_rank<T, F>(v: &mut [T], mut rank_of: F)
+where
+    F: FnMut(&T) -> u32,
+{
+    quicksort(v, |a, b| rank_of(a) < rank_of(b));
+}
+
+/// Sorts the slice by a small integer priority first, e.g. the variant of an enum, and by
+/// `compare` among elements of equal priority, but might not preserve the order of equal elements.
+///
+/// Same as `sort_by(v, |a, b| priority(a).cmp(&priority(b)).then_with(|| compare(a, b)))`, so
+/// `compare` is only called for elements of equal priority and only has to order those. A
+/// `sort_by_key` with a `(priority, payload)` tuple key does the same, but has to clone the payload
+/// for every comparison. `priority` is called twice per comparison, so it should be cheap, like a
+/// `match` on the variant.
+///
+/// Computing the priorities up-front into a `Vec<u8>`, grouping the elements by priority with a
+/// counting sort, and sorting each group by `compare` was 10-30% slower than this for 1M elements
+/// of an enum with 4 variants and 56 byte payloads, both with an in-place and a buffered grouping.
+/// Moving the heavy elements into their groups costs more than the first partitions of the
+/// quicksort, which already separate the priorities after a few levels.
+///
+/// # Examples
+///
+/// ```
+/// use sort_comp::unstable::rust_ipnsort::sort_by_priority;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Task {
+///     Urgent { deadline: u64 },
+///     Normal { id: u32 },
+///     Idle,
+/// }
+///
+/// let mut tasks = vec![
+///     Task::Idle,
+///     Task::Normal { id: 7 },
+///     Task::Urgent { deadline: 30 },
+///     Task::Normal { id: 2 },
+///     Task::Urgent { deadline: 10 },
+/// ];
+///
+/// sort_by_priority(
+///     &mut tasks,
+///     |task| match task {
+///         Task::Urgent { .. } => 0,
+///         Task::Normal { .. } => 1,
+///         Task::Idle => 2,
+///     },
+///     |a, b| match (a, b) {
+///         (Task::Urgent { deadline: x }, Task::Urgent { deadline: y }) => x.cmp(y),
+///         (Task::Normal { id: x }, Task::Normal { id: y }) => x.cmp(y),
+///         _ => std::cmp::Ordering::Equal,
+///     },
+/// );
+///
+/// assert_eq!(
+///     tasks,
+///     [
+///         Task::Urgent { deadline: 10 },
+///         Task::Urgent { deadline: 30 },
+///         Task::Normal { id: 2 },
+///         Task::Normal { id: 7 },
+///         Task::Idle,
+///     ]
+/// );
+/// ```
+#[inline(always)]
+pub fn sort_by_priority<T, P, F>(v: &mut [T], mut priority: P, mut compare: F)
+where
+    P: FnMut(&T) -> u8,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    quicksort(v, |a, b| match priority(a).cmp(&priority(b)) {
+        Ordering::Equal => compare(a, b) == Ordering::Less,
+        ord => ord == Ordering::Less,
+    });
+}
+
+/// Sorts the slice with a C-style comparator function, but might not preserve the order of equal
+/// elements.
+///
+/// `compare` returns a negative value if `a` is less than `b`, zero if they are equal and a
+/// positive value if `a` is greater than `b`, like the comparison function of C `qsort`. Only the
+/// sign is used, `compare(a, b) < 0` is the `is_less` of the sort, so there is no conversion to
+/// `Ordering`. The usual `a - b` comparator overflows for operands far apart, compute the
+/// difference in a wider type or use `(a > b) - (a < b)` instead.
+#[inline(always)]
+pub fn sort_by_c_cmp<T, F>(v: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> i32,
+{
+    quicksort(v, |a, b| compare(a, b) < 0);
+}
+
+/// Sorts the slice by a `bool` key, `false` before `true`, and preserves the order of equal
+/// elements.
+///
+/// This is a single stable partition in *O*(*n*), `key` is called at most once per element.
+/// Elements with a `false` key are moved to the front of `v` as they are found, the ones with a
+/// `true` key are moved into a buffer and appended at the end. Like in the block partition, both
+/// moves are done for every element and only the offsets advance depending on the key, so the loop
+/// has no branch on the key. A stable partition in *O*(*n*) needs *O*(*n*) extra memory, so this
+/// allocates `v.len()` elements.
+pub fn sort_by_bool_key<T, F>(v: &mut [T], mut key: F)
+where
+    F: FnMut(&T) -> bool,
+{
+    // Appends the buffered `true` elements after the `false` ones once all elements are
+    // partitioned, or if `key` panics. At that point `v[false_count..false_count + true_count]` is
+    // the gap left by the `true` elements.
+    struct AppendTrues<T> {
+        dest: *mut T,
+        buf: *const T,
+        true_count: usize,
+    }
+
+    impl<T> Drop for AppendTrues<T> {
+        fn drop(&mut self) {
+            // SAFETY: The buffer holds exactly `true_count` elements, which were moved out of the
+            // gap at `dest`.
+            unsafe {
+                ptr::copy_nonoverlapping(self.buf, self.dest, self.true_count);
+            }
+        }
+    }
+
+    let len = v.len();
+    if len < 2 {
+        return;
+    }
+
+    // The buffer never has a length, the elements in it are owned by `append`.
+    let mut buf = Vec::<T>::with_capacity(len);
+    let buf_base = buf.as_mut_ptr();
+    let v_base = v.as_mut_ptr();
+
+    let mut false_count = 0;
+    let mut append = AppendTrues {
+        dest: v_base,
+        buf: buf_base,
+        true_count: 0,
+    };
+
+    for i in 0..len {
+        // SAFETY: `false_count + true_count == i`, so both destinations are in bounds, and every
+        // slot of `v` below `i` that isn't one of the first `false_count` elements is a gap. `key`
+        // only sees `v[i]` before it is moved.
+        unsafe {
+            let elem = v_base.add(i);
+            let is_true = key(&*elem);
+
+            ptr::copy(elem, v_base.add(false_count), 1);
+            ptr::copy_nonoverlapping(elem, buf_base.add(append.true_count), 1);
+
+            false_count += !is_true as usize;
+            append.true_count += is_true as usize;
+            append.dest = v_base.add(false_count);
+        }
+    }
+}
+
+/// Sorts the slice by the raw bytes of its elements, compared lexicographically, but might not
+/// preserve the order of equal elements.
+///
+/// The result only matches [`sort`] if the byte order of `T` equals its logical order, e.g. for
+/// big-endian encoded unsigned integers, byte strings like `[u8; 16]` or structs of those without
+/// padding. It is the caller's responsibility to uphold this, otherwise the slice is sorted by an
+/// unrelated order. Native-endian integers on little-endian targets do *not* qualify.
+///
+/// Elements of size 1, 2, 4, 8 and 16 are copied into a buffer of big-endian decoded unsigned
+/// integers, which are sorted with integer comparisons and then written back, so for these sizes
+/// `sort_pod_bytes` allocates `v.len()` integers. Other sizes are sorted in-place with a byte-wise
+/// comparison.
+#[cfg(feature = "pod_bytes")]
+pub fn sort_pod_bytes<T: bytemuck::Pod>(v: &mut [T]) {
+    macro_rules! sort_as_be_int {
+        ($t:ty) => {{
+            const SIZE: usize = mem::size_of::<$t>();
+
+            let bytes: &mut [u8] = bytemuck::cast_slice_mut(v);
+            let mut keys = bytes
+                .chunks_exact(SIZE)
+                .map(|elem| <$t>::from_be_bytes(elem.try_into().unwrap()))
+                .collect::<Vec<_>>();
+
+            sort(&mut keys);
+
+            for (elem, key) in bytes.chunks_exact_mut(SIZE).zip(keys) {
+                elem.copy_from_slice(&key.to_be_bytes());
+            }
+        }};
+    }
+
+    if v.len() < 2 {
+        return;
+    }
+
+    match mem::size_of::<T>() {
+        0 => {}
+        1 => sort_as_be_int!(u8),
+        2 => sort_as_be_int!(u16),
+        4 => sort_as_be_int!(u32),
+        8 => sort_as_be_int!(u64),
+        16 => sort_as_be_int!(u128),
+        _ => quicksort(v, |a, b| bytemuck::bytes_of(a) < bytemuck::bytes_of(b)),
+    }
+}
+
+/// Sorts the slice with a fallible key extraction function, but might not preserve the order of
+/// equal elements.
+///
+/// All keys are extracted before sorting. If extraction fails for any element, the first error is
+/// returned and `v` is left unchanged.
+pub fn try_sort_by_key<T, K, E, F>(v: &mut [T], mut f: F) -> Result<(), E>
+where
+    F: FnMut(&T) -> Result<K, E>,
+    K: Ord,
+{
+    // A single element doesn't need a key to be sorted, but extracting it can still fail.
+    if let [elem] = v {
+        f(elem)?;
+    }
+
+    // Without `FALLIBLE` allocation failures abort, only the key function can return an error.
+    sort_by_cached_key_impl::<T, K, E, F, false>(v, f).unwrap_or(Ok(()))
+}
+
+/// Integer type used to store the indices in [`sort_by_cached_key`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CachedKeyIndexWidth {
+    U8,
+    U16,
+    U32,
+    Usize,
+}
+
+/// Returns the smallest index type that can index `len` elements, if it makes `(K, index)` smaller
+/// than the next larger index type. Slices with more than `u32::MAX` elements always use `usize`.
+pub const fn cached_key_index_width<K>(len: usize) -> CachedKeyIndexWidth {
+    let sz_u8 = mem::size_of::<(K, u8)>();
+    let sz_u16 = mem::size_of::<(K, u16)>();
+    let sz_u32 = mem::size_of::<(K, u32)>();
+    let sz_usize = mem::size_of::<(K, usize)>();
+
+    if sz_u8 < sz_u16 && len <= (u8::MAX as usize) {
+        CachedKeyIndexWidth::U8
+    } else if sz_u16 < sz_u32 && len <= (u16::MAX as usize) {
+        CachedKeyIndexWidth::U16
+    } else if sz_u32 < sz_usize && len <= (u32::MAX as usize) {
+        CachedKeyIndexWidth::U32
+    } else {
+        CachedKeyIndexWidth::Usize
+    }
+}
+
+/// Returns the permutation that sorts `v`, without moving the elements of `v`.
+///
+/// The result `p` satisfies `v[p[0]] <= v[p[1]] <= ...`, like numpy's `argsort`. Indices are
+/// stored as `u32` if possible, to halve the memory traffic of the sort, see [`ArgsortIndices`].
+pub fn argsort<T>(v: &[T]) -> ArgsortIndices
+where
+    T: Ord,
+{
+    argsort_by(v, |a, b| a.cmp(b))
+}
+
+/// Returns the permutation that sorts `v` with the comparison function `compare`, without moving
+/// the elements of `v`.
+///
+/// Slices with more than `u32::MAX` elements get `usize` indices, see [`ArgsortIndices`].
+pub fn argsort_by<T, F>(v: &[T], mut compare: F) -> ArgsortIndices
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if v.len() > u32::MAX as usize {
+        return ArgsortIndices::Usize(argsort_usize_by(v, compare));
+    }
+
+    let mut indices = (0..v.len() as u32).collect::<Vec<u32>>();
+    sort_by(&mut indices, |&a, &b| {
+        compare(&v[a as usize], &v[b as usize])
+    });
+    ArgsortIndices::U32(indices)
+}
+
+/// Permutation returned by [`argsort`] and [`argsort_by`], in the smallest index type that can
+/// index the input. Like [`CachedKeyIndexWidth`] for [`sort_by_cached_key`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgsortIndices {
+    /// Inputs of up to `u32::MAX` elements.
+    U32(Vec<u32>),
+    /// Inputs with more than `u32::MAX` elements.
+    Usize(Vec<usize>),
+}
+
+impl ArgsortIndices {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U32(indices) => indices.len(),
+            Self::Usize(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the permutation as `usize` indices, regardless of the stored index type.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let (indices_u32, indices_usize): (&[u32], &[usize]) = match self {
+            Self::U32(indices) => (indices, &[]),
+            Self::Usize(indices) => (&[], indices),
+        };
+
+        indices_u32
+            .iter()
+            .map(|&i| i as usize)
+            .chain(indices_usize.iter().copied())
+    }
+}
+
+/// Like [`argsort_by`] but always with `usize` indices, also for short slices.
+pub fn argsort_usize_by<T, F>(v: &[T], mut compare: F) -> Vec<usize>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut indices = (0..v.len()).collect::<Vec<usize>>();
+    sort_by(&mut indices, |&a, &b| compare(&v[a], &v[b]));
+    indices
+}
+
+/// Sorts the slice, given that `v[..sorted_prefix_len]` is already sorted, but might not preserve
+/// the order of equal elements.
+///
+/// Meant for append-mostly data like logs, where new unsorted elements are appended to a sorted
+/// slice and the split point is known. Only the suffix is sorted, then it is merged into the
+/// prefix in-place with [`rotation_merge`]. Unlike [`sort`], this doesn't have to rediscover the
+/// prefix by scanning it.
+///
+/// # Panics
+///
+/// Panics if `sorted_prefix_len > v.len()`.
+pub fn sort_suffix<T>(v: &mut [T], sorted_prefix_len: usize)
+where
+    T: Ord,
+{
+    assert!(sorted_prefix_len <= v.len(), "sorted prefix out of bounds");
+
+    sort(&mut v[sorted_prefix_len..]);
+    rotation_merge(v, sorted_prefix_len, &mut |a: &T, b: &T| a.lt(b));
+}
+
+/// Certificate returned by [`sort_with_proof`].
+///
+/// The checksums are order-independent hashes of the input and output multisets. Equal checksums
+/// together with `sorted` show, with high probability, that the output is a sorted permutation of
+/// the input. The certificate has a fixed size, regardless of the input length.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SortProof {
+    pub len: usize,
+    pub input_checksum: u64,
+    pub output_checksum: u64,
+    /// Every element of the output is greater than or equal to its predecessor.
+    pub sorted: bool,
+}
+
+impl SortProof {
+    /// Returns `true` if the sort that produced this certificate was correct.
+    pub fn is_valid(&self) -> bool {
+        self.sorted && self.input_checksum == self.output_checksum
+    }
+
+    /// Returns `true` if `output` is a sorted permutation of the input this certificate was
+    /// created for. Needs one pass over `output` and no copy of the input.
+    pub fn verify<T>(&self, output: &[T]) -> bool
+    where
+        T: Ord + Hash,
+    {
+        let (checksum, sorted) = output_checksum(output);
+        output.len() == self.len && sorted && checksum == self.input_checksum
+    }
+}
+
+/// Sorts the slice and returns a [`SortProof`], but might not preserve the order of equal
+/// elements.
+///
+/// Meant for validating experimental backends, and for pipelines that check results instead of
+/// trusting them. Costs two extra passes over `v`, one hashing the input and one hashing the
+/// output while checking that it is sorted.
+pub fn sort_with_proof<T>(v: &mut [T]) -> SortProof
+where
+    T: Ord + Hash,
+{
+    let input_checksum = v
+        .iter()
+        .fold(0u64, |acc, elem| acc.wrapping_add(checksum_elem(elem)));
+
+    sort(v);
+
+    let (output_checksum, sorted) = output_checksum(v);
+
+    SortProof {
+        len: v.len(),
+        input_checksum,
+        output_checksum,
+        sorted,
+    }
+}
+
+/// Returns the multiset checksum of `v` and whether `v` is sorted, computed in a single pass.
+fn output_checksum<T>(v: &[T]) -> (u64, bool)
+where
+    T: Ord + Hash,
+{
+    let mut checksum = 0u64;
+    let mut sorted = true;
+    let mut prev: Option<&T> = None;
+
+    for elem in v {
+        checksum = checksum.wrapping_add(checksum_elem(elem));
+        if let Some(prev) = prev {
+            sorted &= prev <= elem;
+        }
+        prev = Some(elem);
+    }
+
+    (checksum, sorted)
+}
+
+/// Per-element hash for the multiset checksums of [`SortProof`], summing these is commutative.
+fn checksum_elem<T: Hash>(elem: &T) -> u64 {
+    // `DefaultHasher::new` uses fixed keys, unlike `RandomState`. Certificates can be compared
+    // across processes, as long as they were built with the same Rust version.
+    let mut hasher = DefaultHasher::new();
+    elem.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Sorts the slice with the minimum number of writes, but might not preserve the order of equal
+/// elements.
+///
+/// Meant for slices where writes are much more expensive than reads, e.g. memory-mapped flash or
+/// NVM. Uses [`cycle_sort`], every element is written at most once, directly to its final
+/// position, and elements that are already in place are not written at all. The price is
+/// *O*(*n*^2) comparisons, regardless of the input.
+pub fn sort_min_writes<T>(v: &mut [T])
+where
+    T: Ord,
+{
+    cycle_sort(v, &mut |a, b| a.lt(b));
+}
+
+/// Sorts `v` using cycle sort and returns the number of elements written to `v`.
+///
+/// Each cycle takes out one element, scans the rest of the slice to find its final position and
+/// swaps it with the element there, until the cycle closes. If `is_less` panics, the element held
+/// outside the slice is written back to the start of the cycle, so that `v` is a permutation of the
+/// input.
+pub fn cycle_sort<T, F>(v: &mut [T], is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+    let mut writes = 0;
+
+    // Zero-sized types are all in place, and sorting has no meaningful behavior for them.
+    if const { mem::size_of::<T>() == 0 } || len < 2 {
+        return writes;
+    }
+
+    let arr_ptr = v.as_mut_ptr();
+
+    for cycle_start in 0..(len - 1) {
+        // SAFETY: `pos` is at most `cycle_start + (len - (cycle_start + 1))` after counting, and
+        // the duplicate skipping checks `pos < len`. The hole at `cycle_start` is only read once
+        // the cycle closes, all other elements stay valid the whole time. Should `is_less` panic,
+        // `hole` writes `tmp` into the hole.
+        unsafe {
+            let count_less = |item: &T, is_less: &mut F| {
+                let mut count = 0;
+                for i in (cycle_start + 1)..len {
+                    count += is_less(&*arr_ptr.add(i), item) as usize;
+                }
+                count
+            };
+
+            let mut pos = cycle_start + count_less(&*arr_ptr.add(cycle_start), is_less);
+            if pos == cycle_start {
+                continue;
+            }
+
+            let mut tmp = mem::ManuallyDrop::new(ptr::read(arr_ptr.add(cycle_start)));
+            let tmp_ptr: *mut T = &mut *tmp;
+            let hole = InsertionHole {
+                src: tmp_ptr,
+                dest: arr_ptr.add(cycle_start),
+            };
+
+            // Each step writes one element to its final position, a cycle can't be longer than the
+            // number of elements it permutes.
+            let mut cycle_closed = false;
+            for _ in cycle_start..len {
+                // Skip elements equal to `tmp`, they already are in a final position.
+                while pos < len
+                    && !is_less(&*tmp_ptr, &*arr_ptr.add(pos))
+                    && !is_less(&*arr_ptr.add(pos), &*tmp_ptr)
+                {
+                    pos += 1;
+                }
+
+                if pos == len {
+                    break;
+                }
+
+                ptr::swap(tmp_ptr, arr_ptr.add(pos));
+                writes += 1;
+
+                pos = cycle_start + count_less(&*tmp_ptr, is_less);
+                if pos == cycle_start {
+                    cycle_closed = true;
+                    break;
+                }
+            }
+
+            // Closes the cycle by writing `tmp` into the hole.
+            drop(hole);
+            writes += 1;
+
+            if !cycle_closed {
+                // The comparison function does not implement a total order.
+                panic_on_ord_violation();
+            }
+        }
+    }
+
+    writes
+}
+
+/// Sorts the elements `base[start + i * stride]` for `i` in `0..len`, but might not preserve the
+/// order of equal elements. Useful to sort a column of a row-major matrix in place.
+///
+/// The core sort only works on contiguous slices, so for `stride > 1` the elements are moved into
+/// a scratch buffer of `len` elements, sorted there, and moved back. Should the sort panic, the
+/// elements are moved back in unspecified order.
+///
+/// # Panics
+///
+/// Panics if `len > 1` and `stride` is zero, or if the last element is out of bounds.
+pub fn sort_strided<T>(base: &mut [T], start: usize, len: usize, stride: usize)
+where
+    T: Ord,
+{
+    if len < 2 {
+        if len == 1 {
+            assert!(start < base.len(), "strided view out of bounds");
+        }
+
+        return;
+    }
+
+    assert!(stride != 0, "stride must not be zero");
+    let last = (len - 1)
+        .checked_mul(stride)
+        .and_then(|offset| offset.checked_add(start));
+    assert!(
+        matches!(last, Some(last) if last < base.len()),
+        "strided view out of bounds"
+    );
+
+    if stride == 1 {
+        sort(&mut base[start..(start + len)]);
+        return;
+    }
+
+    // Writes the gathered elements back to their strided positions, also if the sort panics.
+    struct ScatterOnDrop<'a, T> {
+        base: &'a mut [T],
+        start: usize,
+        stride: usize,
+        gathered: Vec<T>,
+    }
+
+    impl<T> Drop for ScatterOnDrop<'_, T> {
+        fn drop(&mut self) {
+            let base_ptr = self.base.as_mut_ptr();
+            for (i, elem) in self.gathered.drain(..).enumerate() {
+                // SAFETY: The position was checked to be in-bounds, and the element there was
+                // moved out into `gathered`, so it must not be dropped.
+                unsafe {
+                    ptr::write(base_ptr.add(self.start + i * self.stride), elem);
+                }
+            }
+        }
+    }
+
+    let mut gathered = Vec::with_capacity(len);
+    let base_ptr = base.as_ptr();
+    for i in 0..len {
+        // SAFETY: All positions were checked to be in-bounds. The elements are logically moved
+        // out of `base`, `ScatterOnDrop` moves them back before `base` can be accessed again.
+        gathered.push(unsafe { ptr::read(base_ptr.add(start + i * stride)) });
+    }
+
+    let mut scatter = ScatterOnDrop {
+        base,
+        start,
+        stride,
+        gathered,
+    };
+
+    sort(&mut scatter.gathered);
+}
+
+/// Sorts the concatenation of `segments` as if it were one contiguous slice, but might not
+/// preserve the order of equal elements. Useful for rope or otherwise segmented buffers.
+///
+/// The segments keep their lengths, e.g. the smallest `segments[0].len()` elements end up in
+/// `segments[0]`. If at most one segment is non-empty it is sorted directly. Otherwise all
+/// elements are moved into a buffer of the combined length, sorted there, and moved back in order,
+/// `T` does not have to be `Clone`. Should the sort panic, the elements are moved back in
+/// unspecified order.
+///
+/// Sorting an index of `(segment, offset)` pairs and scattering the elements to their final
+/// positions afterwards avoids the buffer of elements, but was ~3x slower for 1M random `u32`, and
+/// still ~1.4x slower for 1M `[u64; 16]`, spread over segments of 0 to 1023 elements. Every
+/// comparison has to follow two indirections, which costs more than moving the elements.
+pub fn sort_segments<T>(segments: &mut [&mut [T]])
+where
+    T: Ord,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if const { mem::size_of::<T>() == 0 } {
+        return;
+    }
+
+    let mut non_empty = segments.iter_mut().filter(|segment| !segment.is_empty());
+    match (non_empty.next(), non_empty.next()) {
+        (None, _) => return,
+        (Some(segment), None) => {
+            sort(segment);
+            return;
+        }
+        _ => (),
+    }
+
+    // Moves the gathered elements back into the segments in their current order, also if the sort
+    // panics.
+    struct ScatterOnDrop<'a, 'b, T> {
+        segments: &'a mut [&'b mut [T]],
+        gathered: Vec<T>,
+    }
+
+    impl<T> Drop for ScatterOnDrop<'_, '_, T> {
+        fn drop(&mut self) {
+            let mut src = self.gathered.as_ptr();
+            // SAFETY: `gathered` holds exactly one bitwise copy of every element of the segments,
+            // in total as many as the segments are long. Setting the length to zero first makes
+            // sure none of them is dropped twice.
+            unsafe {
+                self.gathered.set_len(0);
+                for segment in self.segments.iter_mut() {
+                    ptr::copy_nonoverlapping(src, segment.as_mut_ptr(), segment.len());
+                    src = src.add(segment.len());
+                }
+            }
+        }
+    }
+
+    let len = segments.iter().map(|segment| segment.len()).sum();
+    let mut gathered = Vec::<T>::with_capacity(len);
+    // SAFETY: The buffer has capacity for all elements, and each one is moved into it exactly
+    // once. Nothing in between can panic, and from here on `ScatterOnDrop` moves them back before
+    // the segments can be accessed again.
+    unsafe {
+        let mut dst = gathered.as_mut_ptr();
+        for segment in segments.iter() {
+            ptr::copy_nonoverlapping(segment.as_ptr(), dst, segment.len());
+            dst = dst.add(segment.len());
+        }
+        gathered.set_len(len);
+    }
+
+    let mut scatter = ScatterOnDrop { segments, gathered };
+
+    sort(&mut scatter.gathered);
+}
+
+/// Sorts key-value pairs by key, but might not preserve the order of pairs with equal keys.
+///
+/// Equivalent to `sort_by(v, |a, b| a.0.cmp(&b.0))`. The small-sort and partition strategies are
+/// chosen by the size of the whole pair, like for any other type. Choosing them by the key size
+/// instead, which puts pairs with small keys and large values into the sorting networks, was not
+/// faster. For random `(u32, [u8; 28])` it was ~3-13% slower than `sort_by` on 100 to 100k
+/// elements, for `(u32, [u8; 12])` within ~8% either way. The networks move whole pairs, and for
+/// large values they also need a large scratch on the stack.
+#[inline(always)]
+pub fn sort_pairs_by_key<K, V>(v: &mut [(K, V)])
+where
+    K: Ord + Copy,
+    V: Copy,
+{
+    quicksort(v, |a, b| a.0.lt(&b.0));
+}
+
+/// Sorts tuples of a descending primary key and an ascending secondary key, but might not preserve
+/// the order of equal elements.
+///
+/// Equivalent to [`sort`], which already handles `(Reverse<u32>, u32)` like any other 8 byte
+/// type. But comparing the tuple compares the fields one after the other, with a branch on whether
+/// the primary keys are equal. If both keys are integers of up to 32 bits, they are instead
+/// combined into one `u64` per comparison, with the bits of the primary key inverted, and compared
+/// without branches. That was ~1.4x faster for 1M `(Reverse<u32>, u32)` with 16 distinct primary
+/// keys, and within the noise of the tuple comparison for all-distinct primary keys. Other key
+/// types are compared as tuples.
+#[inline(always)]
+pub fn sort_mixed_direction<K1, K2>(v: &mut [(cmp::Reverse<K1>, K2)])
+where
+    K1: Ord,
+    K2: Ord,
+{
+    <(cmp::Reverse<K1>, K2) as MixedDirectionSort>::sort(v);
+}
+
+trait MixedDirectionSort: Sized {
+    fn sort(v: &mut [Self]);
+}
+
+impl<K1: Ord, K2: Ord> MixedDirectionSort for (cmp::Reverse<K1>, K2) {
+    default fn sort(v: &mut [Self]) {
+        quicksort(v, |a, b| a.lt(b));
+    }
+}
+
+impl<K1, K2> MixedDirectionSort for (cmp::Reverse<K1>, K2)
+where
+    K1: Ord + PackedKeyField,
+    K2: Ord + PackedKeyField,
+{
+    fn sort(v: &mut [Self]) {
+        #[inline(always)]
+        fn packed_key<K1: PackedKeyField, K2: PackedKeyField>(val: &(cmp::Reverse<K1>, K2)) -> u64 {
+            let primary_mask = (1u64 << K1::BITS) - 1;
+            let primary = val.0 .0.ordered_bits() ^ primary_mask;
+
+            (primary << K2::BITS) | val.1.ordered_bits()
+        }
+
+        quicksort(v, |a, b| packed_key(a) < packed_key(b));
+    }
+}
+
+/// Integers of up to 32 bits that [`sort_mixed_direction`] combines into one `u64` key.
+trait PackedKeyField: Copy {
+    const BITS: u32;
+
+    /// Zero-extended bits of `self` that order the same way as `self`.
+    fn ordered_bits(self) -> u64;
+}
+
+macro_rules! packed_key_field_impl {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl PackedKeyField for $t {
+                const BITS: u32 = <$u>::BITS;
+
+                #[inline(always)]
+                fn ordered_bits(self) -> u64 {
+                    // For signed types, flipping the sign bit moves the negative values below
+                    // the positive ones. Unsigned types are mapped to themselves.
+                    let sign_bit = if <$t>::MIN == 0 { 0 } else { 1 << (<$u>::BITS - 1) };
+                    ((self as $u) ^ sign_bit) as u64
+                }
+            }
+        )*
+    };
+}
+
+packed_key_field_impl!(u8 => u8, u16 => u16, u32 => u32, i8 => u8, i16 => u16, i32 => u32);
+
+/// Sorts data that arrives in chunks, and writes the sorted sequence of all elements to `out`.
+///
+/// See [`sort_external_by`].
+#[inline(always)]
+pub fn sort_external<T, I, W, S, R>(chunks: I, out: &mut W, spill: S)
+where
+    T: Ord,
+    I: IntoIterator<Item = Vec<T>>,
+    W: Extend<T>,
+    S: FnMut(Vec<T>) -> R,
+    R: IntoIterator<Item = T>,
+{
+    sort_external_by(chunks, out, spill, T::cmp);
+}
+
+/// Sorts data that arrives in chunks with a comparator function, and writes the sorted sequence of
+/// all elements to `out`.
+///
+/// Every chunk is sorted with [`sort_by`] as soon as it arrives and handed to `spill`, which stores
+/// the sorted run, e.g. in a temporary file, and returns an iterator that reads it back. After the
+/// last chunk, all runs are merged in a single pass with [`merge_runs_by`], which only keeps the
+/// current head of each run in memory. So only one chunk is in memory at a time, plus whatever the
+/// iterators returned by `spill` buffer. Passing `|run| run` as `spill` keeps all runs in memory.
+pub fn sort_external_by<T, I, W, S, R, F>(chunks: I, out: &mut W, mut spill: S, mut compare: F)
+where
+    I: IntoIterator<Item = Vec<T>>,
+    W: Extend<T>,
+    S: FnMut(Vec<T>) -> R,
+    R: IntoIterator<Item = T>,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let runs = chunks
+        .into_iter()
+        .map(|mut chunk| {
+            sort_by(&mut chunk, &mut compare);
+            spill(chunk).into_iter()
+        })
+        .collect::<Vec<_>>();
+
+    merge_runs_by(runs, out, compare);
+}
+
+/// Merges the sorted `runs` with a k-way merge, and writes the merged sequence to `out`.
+///
+/// The merge uses a loser tree, each element needs about log2(k) comparisons, where k is the
+/// number of runs. The runs are consumed lazily, and only the current head of each run is kept in
+/// memory. If a run is not sorted according to `compare`, the elements are still all written to
+/// `out`, but in an unspecified order.
+pub fn merge_runs_by<T, R, W, F>(runs: Vec<R>, out: &mut W, mut compare: F)
+where
+    R: Iterator<Item = T>,
+    W: Extend<T>,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    out.extend(LoserTreeMerge::new(runs, &mut |a: &T, b: &T| {
+        compare(a, b) == Ordering::Less
+    }));
+}
+
+// --- IMPL ---
+
+/// Returns the index of `elem` in the slice starting at `v_ptr`, if `elem` is part of it.
+#[cfg(feature = "comparison_trace")]
+fn trace_index<T>(v_ptr: *const T, len: usize, elem: &T) -> Option<usize> {
+    let elem_size = mem::size_of::<T>();
+    if elem_size == 0 {
+        return None;
+    }
+
+    let offset = (elem as *const T as usize).wrapping_sub(v_ptr as usize);
+    let index = offset / elem_size;
+
+    (offset % elem_size == 0 && index < len).then_some(index)
+}
+
+/// k-way merge of sorted runs, see [`merge_runs_by`].
+///
+/// `tree[0]` is the index of the run with the overall smallest head, and every inner node
+/// `tree[1..k]` holds the run that lost the comparison at that node. Leaf `i` is node `k + i`.
+/// Exhausted runs compare greater than all elements.
+struct LoserTreeMerge<'a, T, R, F> {
+    runs: Vec<R>,
+    heads: Vec<Option<T>>,
+    tree: Vec<usize>,
+    is_less: &'a mut F,
+}
+
+impl<'a, T, R, F> LoserTreeMerge<'a, T, R, F>
+where
+    R: Iterator<Item = T>,
+    F: FnMut(&T, &T) -> bool,
+{
+    fn new(mut runs: Vec<R>, is_less: &'a mut F) -> Self {
+        let heads = runs.iter_mut().map(|run| run.next()).collect::<Vec<_>>();
+        let k = runs.len();
+
+        let mut merge = Self {
+            runs,
+            heads,
+            tree: vec![0; k.max(1)],
+            is_less,
+        };
+
+        if k > 0 {
+            merge.tree[0] = merge.init_node(1);
+        }
+
+        merge
+    }
+
+    /// Plays all matches in the subtree of `node` and returns the index of the winning run.
+    fn init_node(&mut self, node: usize) -> usize {
+        let k = self.runs.len();
+        if node >= k {
+            return node - k;
+        }
+
+        let a = self.init_node(2 * node);
+        let b = self.init_node(2 * node + 1);
+
+        let (winner, loser) = if self.wins(b, a) { (b, a) } else { (a, b) };
+        self.tree[node] = loser;
+
+        winner
+    }
+
+    /// Returns `true` if the head of run `a` must be output before the head of run `b`.
+    fn wins(&mut self, a: usize, b: usize) -> bool {
+        match (&self.heads[a], &self.heads[b]) {
+            (Some(a), Some(b)) => (self.is_less)(a, b),
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl<'a, T, R, F> Iterator for LoserTreeMerge<'a, T, R, F>
+where
+    R: Iterator<Item = T>,
+    F: FnMut(&T, &T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let k = self.runs.len();
+        if k == 0 {
+            return None;
+        }
+
+        let mut winner = self.tree[0];
+        let elem = self.heads[winner].take()?;
+        self.heads[winner] = self.runs[winner].next();
+
+        // Replay the matches on the path from the leaf of the previous winner to the root.
+        let mut node = (k + winner) / 2;
+        while node > 0 {
+            let loser = self.tree[node];
+            if self.wins(loser, winner) {
+                self.tree[node] = winner;
+                winner = loser;
+            }
+
+            node /= 2;
+        }
+        self.tree[0] = winner;
+
+        Some(elem)
+    }
+}
+
+/// Sorts `v` using pattern-defeating quicksort, which is *O*(*n* \* log(*n*)) worst-case.
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+pub fn quicksort<T, F>(v: &mut [T], mut is_less: F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Hides where the slice comes from, so that the sort can't be specialized for a benchmark
+    // input the optimizer can see through.
+    #[cfg(feature = "bench_barriers")]
+    let v = core::hint::black_box(v);
+
+    // Fixed-size workloads, e.g. sorting 8-sample windows, hit the same length over and over. If it
+    // matches a sorting network exactly, the network beats the insertion sort in
+    // `quicksort_prelude` by a wide margin.
+    if !<T as UnstableSortTypeImpl>::sort_exact_network(v, &mut is_less) {
+        if let Some(limit) = quicksort_prelude(v, &mut is_less, None) {
+            recurse(v, &mut is_less, None, limit, RuntimeConfig::load());
+        }
+    }
+
+    // Marks the sorted slice as read, so that the sort can't be elided or moved across benchmark
+    // iterations that don't look at the result.
+    #[cfg(feature = "bench_barriers")]
+    core::hint::black_box(v);
+}
+
+/// Same as [`quicksort`], but the first partition uses the element at `hint` as pivot, if `hint`
+/// is in bounds.
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+fn quicksort_with_hint<T, F>(v: &mut [T], mut is_less: F, hint: PivotHint)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let Some(limit) = quicksort_prelude(v, &mut is_less, None) else {
+        return;
+    };
+
+    let config = RuntimeConfig::load();
+
+    let pivot = hint.0;
+    if pivot >= v.len() || <T as UnstableSortTypeImpl>::small_sort(v, &mut is_less) {
+        recurse(v, &mut is_less, None, limit, config);
+        return;
+    }
+
+    // This is the first iteration of `recurse` with the pivot choice replaced. The hinted
+    // partition counts towards the imbalance limit like any other, so a bad hint can't degrade
+    // the worst-case. `limit` is at least 8 here because `v.len() > 20`.
+    let (mid, _) = partition_configured(v, pivot, &mut is_less, config.fulcrum_enabled);
+
+    let (left, right) = v.split_at_mut(mid);
+    let (pivot, right) = right.split_at_mut(1);
+    let pivot = &pivot[0];
+
+    recurse(left, &mut is_less, None, limit - 1, config);
+    recurse(right, &mut is_less, Some(pivot), limit - 1, config);
+}
+
+/// Same as [`quicksort`], but chooses pivots with [`choose_pivot_bfprt`].
+///
+/// Doesn't use `quicksort_prelude`, its run merging and the sorting of the rest of mostly
+/// presorted inputs go through `recurse` with the regular pivot choice, which an adversary can
+/// exploit. Only the insertion sort of small inputs and fully presorted inputs are handled up
+/// front, everything else is partitioned around median-of-medians pivots.
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+fn quicksort_guaranteed<T, F, O>(v: &mut [T], mut is_less: F, observer: &mut O)
+where
+    F: FnMut(&T, &T) -> bool,
+    O: RecurseObserver<T>,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if const { mem::size_of::<T>() == 0 } {
+        return;
+    }
+
+    let len = v.len();
+
+    if intrinsics::likely(len < 2) {
+        return;
+    }
+
+    if intrinsics::likely(len <= MAX_LEN_ALWAYS_INSERTION_SORT) {
+        insertion_sort_shift_left(v, 1, &mut is_less);
+        return;
+    }
+
+    let (streak_end, was_reversed) = find_streak(v, &mut is_less);
+    if streak_end == len {
+        if was_reversed {
+            v.reverse();
+        }
+
+        return;
+    }
+
+    // With each partition shrinking the sub-slice to at most ~70%, the recursion depth is below
+    // `2 * log2(len)` plus a small constant for small sub-slices. Doubling the usual limit means
+    // heapsort is only reached if `is_less` violates the total order requirement.
+    recurse_guaranteed(v, &mut is_less, 2 * partition_limit(len), observer);
+}
+
+/// Strategy for sorting a sub-slice once the main quicksort loop ran out of allowed imbalanced
+/// partitions. Research only, see [`sort_by_with_fallback`].
+pub trait Fallback {
+    fn sort<T, F>(v: &mut [T], is_less: &mut F)
+    where
+        F: FnMut(&T, &T) -> bool;
+}
+
+/// Falls back to [`heapsort`]. This is the default.
+pub struct Heapsort;
+
+impl Fallback for Heapsort {
+    #[inline(always)]
+    fn sort<T, F>(v: &mut [T], is_less: &mut F)
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        heapsort(v, is_less);
+    }
+}
+
+/// Continues quicksort with median-of-medians pivots, like [`sort_by_guaranteed`].
+pub struct MedianOfMedians;
+
+impl Fallback for MedianOfMedians {
+    #[inline(never)]
+    fn sort<T, F>(v: &mut [T], is_less: &mut F)
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        // Same limit as `quicksort_guaranteed`, heapsort is only reached with an invalid total
+        // order.
+        recurse_guaranteed(v, is_less, 4 * (v.len() | 1).ilog2(), &mut ());
+    }
+}
+
+type DefaultFallback = Heapsort;
+
+/// Handles everything the quicksort entry points do before entering the main loop: zero-sized
+/// types, small inputs and fully presorted inputs.
+///
+/// Returns the number of allowed imbalanced partitions for `recurse`, or `None` if `v` is already
+/// sorted. `config` is used to sort the rest of mostly presorted inputs, if `None` it is loaded
+/// only then, which keeps the thread-local access off the path for small inputs. The thread-local
+/// overrides don't cover `insertion_always_max`, so `None` uses the compiled-in value for it.
+#[inline(always)]
+fn quicksort_prelude<T, F>(
+    v: &mut [T],
+    is_less: &mut F,
+    config: Option<RuntimeConfig>,
+) -> Option<u32>
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if const { mem::size_of::<T>() == 0 } {
+        return None;
+    }
+
+    let len = v.len();
+
+    // Instrumenting the standard library showed that 90+% of the calls to sort by rustc are either
+    // of size 0 or 1. Make this path extra fast by assuming the branch is likely.
+    if intrinsics::likely(len < 2) {
+        return None;
+    }
+
+    // It's important to differentiate between small-sort performance for small slices and
+    // small-sort performance sorting small sub-slices as part of the main quicksort loop. For the
+    // former, testing showed that the representative benchmarks for real-world performance are cold
+    // CPU state and not single-size hot benchmarks. For the latter the CPU will call them many
+    // times, so hot benchmarks are fine and more realistic. And it's worth it to optimize sorting
+    // small sub-slices with more sophisticated solutions than insertion sort.
+
+    let max_len_always_insertion_sort = config.map_or(MAX_LEN_ALWAYS_INSERTION_SORT, |config| {
+        config.insertion_always_max
+    });
+
+    if intrinsics::likely(len <= max_len_always_insertion_sort) {
+        // More specialized and faster options, extending the range of allocation free sorting
+        // are possible but come at a great cost of additional code, which is problematic for
+        // compile-times.
+        insertion_sort_shift_left(v, 1, is_less);
+
+        return None;
+    }
+
+    // This is not a separate full-length pass. `find_streak` and `merge_few_runs` stop at the first
+    // pair that breaks a streak, for random inputs that's a handful of comparisons. Only presorted
+    // prefixes are scanned further, in proportion to their length.
+    let (streak_end, was_reversed) = find_streak(v, is_less);
+    if streak_end == len {
+        if was_reversed {
+            v.reverse();
+        }
+
+        return None;
+    }
+
+    if merge_few_runs(v, streak_end, was_reversed, is_less) {
+        return None;
+    }
+
+    if streak_end >= len - len / 4 {
+        // Most of the slice is already presorted, only sort the rest and merge both runs. The merge
+        // has to happen in-place, this sort promises not to allocate. It moves elements more often
+        // than a buffered merge, so the prefix has to be long to pay off. With half of the slice
+        // presorted, this was ~35-80% slower than sorting the whole slice for random `i32` of 1k to
+        // 1M elements and up to ~20% slower for `String`. From three quarters on it was as fast or
+        // faster, for 90% up to ~2.5x for `String`.
+        if was_reversed {
+            v[..streak_end].reverse();
+        }
+
+        let rest = &mut v[streak_end..];
+        let config = config.unwrap_or_else(RuntimeConfig::load);
+        recurse(rest, is_less, None, partition_limit(rest.len()), config);
+
+        rotation_merge(v, streak_end, is_less);
+
+        return None;
+    }
+
+    Some(partition_limit(len))
+}
+
+/// Number of partitions `recurse` may do on a slice of length `len` before it switches to heapsort,
+/// `2 * floor(log2(len))`.
+///
+/// Every partition counts, not only imbalanced ones as in `rust_std`, so the limit is a bound on
+/// the recursion depth. Balanced partitions reach the small-sort after `log2(len / threshold)`
+/// levels, the second `log2(len)` is headroom for bad pivots. Even the smallest slices that get
+/// here, just past the always-insertion-sort length, get a limit of 8 while they reach the
+/// small-sort after one or two partitions, so only an adversary gets them into heapsort.
+/// `floor(log2(len)) + 1`, the limit `rust_std` uses for imbalanced partitions, leaves no headroom
+/// with this counting. On random inputs of 1k to 10k elements the recursion reached a depth of 14,
+/// which is that limit, and about one in a hundred sorts fell back to heapsort. With the current
+/// limit, 85k random, low-cardinality and saw inputs of 21 to 10k elements had no fallback at all,
+/// with a maximum depth of 14 at 10k elements where the limit is 26. Random inputs of up to 1k
+/// elements measured the same speed with both limits, within noise.
+#[inline(always)]
+fn partition_limit(len: usize) -> u32 {
+    // The binary OR by one is used to eliminate the zero-check in the logarithm.
+    2 * (len | 1).ilog2()
+}
+
+/// Maximum number of presorted runs of any length that [`merge_few_runs`] merges instead of sorting
+/// the slice.
+const MAX_PRESORTED_RUNS: usize = 3;
+
+/// Maximum number of presorted runs that [`merge_few_runs`] merges instead of sorting the slice, if
+/// all of them are long.
+///
+/// Merging more than [`MAX_PRESORTED_RUNS`] runs only pays off with a buffered merge, in-place
+/// merging all of them with [`rotation_merge`] was measured to be slower than sorting from scratch.
+/// The buffer has to be allocated, so this is opt-in with the `run_merge` feature.
+#[cfg(feature = "run_merge")]
+const MAX_LONG_PRESORTED_RUNS: usize = 32;
+#[cfg(not(feature = "run_merge"))]
+const MAX_LONG_PRESORTED_RUNS: usize = MAX_PRESORTED_RUNS;
+
+/// Checks if `v` consists of a few presorted runs, the first of which ends at `first_run_end`. If
+/// so, the runs are merged and `true` is returned.
+///
+/// Up to [`MAX_PRESORTED_RUNS`] runs of any length are accepted and merged in-place. This catches
+/// inputs like a sorted slice with one block moved to another position. Beyond that, up to
+/// [`MAX_LONG_PRESORTED_RUNS`] runs are accepted if none of them, except the last one, is shorter
+/// than `len / (2 * MAX_LONG_PRESORTED_RUNS)`. This catches several concatenated sorted sequences,
+/// which quicksort would otherwise sort from scratch. The scan stops at the first run that rules
+/// out merging, for random inputs that's only a handful of comparisons.
+fn merge_few_runs<T, F>(
+    v: &mut [T],
+    first_run_end: usize,
+    first_run_reversed: bool,
+    is_less: &mut F,
+) -> bool
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+    let min_long_run_len = len / (2 * MAX_LONG_PRESORTED_RUNS);
+
+    let mut run_ends = [0; MAX_LONG_PRESORTED_RUNS];
+    let mut run_reversed = [false; MAX_LONG_PRESORTED_RUNS];
+    run_ends[0] = first_run_end;
+    run_reversed[0] = first_run_reversed;
+    let mut run_count = 1;
+    let mut all_long = first_run_end >= min_long_run_len;
+
+    while run_ends[run_count - 1] < len {
+        if run_count == MAX_LONG_PRESORTED_RUNS || (run_count >= MAX_PRESORTED_RUNS && !all_long) {
+            return false;
+        }
+
+        let run_start = run_ends[run_count - 1];
+        let (streak_len, was_reversed) = find_streak(&v[run_start..], is_less);
+        run_ends[run_count] = run_start + streak_len;
+        run_reversed[run_count] = was_reversed;
+        run_count += 1;
+
+        // The last run may be the short remainder of otherwise evenly sized runs.
+        all_long &= streak_len >= min_long_run_len || run_start + streak_len == len;
+    }
+
+    let mut run_start = 0;
+    for i in 0..run_count {
+        if run_reversed[i] {
+            v[run_start..run_ends[i]].reverse();
+        }
+        run_start = run_ends[i];
+    }
+
+    if run_count <= MAX_PRESORTED_RUNS {
+        for i in 1..run_count {
+            rotation_merge(&mut v[..run_ends[i]], run_ends[i - 1], is_less);
+        }
+    } else {
+        // Merge neighboring runs pairwise, so every element takes part in at most
+        // `ceil(log2(run_count))` merges. The shorter run of every merge is at most half of `v`.
+        let mut buf = Vec::<T>::with_capacity(len / 2);
+        let run_start = |i: usize| if i == 0 { 0 } else { run_ends[i - 1] };
+        let mut width = 1;
+        while width < run_count {
+            for i in (0..run_count - width).step_by(2 * width) {
+                let start = run_start(i);
+                let mid = run_start(i + width);
+                let end = run_ends[cmp::min(i + 2 * width, run_count) - 1];
+                // SAFETY: Both runs are non-empty, `T` is not a zero-sized type and `buf` can hold
+                // the shorter run.
+                unsafe {
+                    crate::stable::rust_std::merge(
+                        &mut v[start..end],
+                        mid - start,
+                        buf.as_mut_ptr(),
+                        is_less,
+                    );
+                }
+            }
+            width *= 2;
+        }
+    }
+
+    true
+}
+
+/// Finds a streak of presorted elements starting at the beginning of the slice. Returns the first
+/// value that is not part of said streak, and a bool denoting wether the streak was reversed.
+/// Streaks can be increasing or decreasing.
+///
+/// Leading equal elements fit both directions. The slice is scanned as ascending streak, with one
+/// comparison per element, and only at the first descending pair it is checked if all elements
+/// before it are equal. In that case the streak continues as reversed one, this way `[3, 3, 2, 1]`
+/// is found as one reversed streak. Reversed streaks only continue while strictly descending.
+fn find_streak<T, F>(v: &[T], is_less: &mut F) -> (usize, bool)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+
+    if len < 2 {
+        return (len, false);
+    }
+
+    let mut end = 1;
+
+    // SAFETY: See below specific.
+    unsafe {
+        // SAFETY: We know end >= 1 and check end < len.
+        // From that follows that accessing v at end and end - 1 is safe.
+        while end < len && !is_less(v.get_unchecked(end), v.get_unchecked(end - 1)) {
+            end += 1;
+        }
+
+        if end == len {
+            return (len, false);
+        }
+
+        // `v[..end]` is ascending, so it's all equal if the first element is not less than the
+        // last one.
+        let all_equal = end == 1 || !is_less(v.get_unchecked(0), v.get_unchecked(end - 1));
+        if !all_equal {
+            return (end, false);
+        }
+
+        end += 1;
+
+        // SAFETY: We know end >= 2 and check end < len.
+        // From that follows that accessing v at end and end - 1 is safe.
+        while end < len && is_less(v.get_unchecked(end), v.get_unchecked(end - 1)) {
+            end += 1;
+        }
+
+        (end, true)
+    }
+}
+
+/// Returns `true` if `v` is a single ascending or descending streak, and sorts it in that case.
+///
+/// For unsorted inputs this usually stops after a few comparisons.
+fn sort_if_presorted<T, F>(v: &mut [T], is_less: &mut F) -> bool
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let (streak_end, was_reversed) = find_streak(v, is_less);
+    if streak_end != v.len() {
+        return false;
+    }
+
+    if was_reversed {
+        v.reverse();
+    }
+
+    true
+}
+
+/// Returns `true` if all elements in `v` are equal.
+///
+/// Only scans the slice if the first and last element are equal to `v[pivot]`, so this is cheap
+/// for slices that are unlikely to be all-equal. A slice that is non-descending and starts and
+/// ends with equal elements can only contain equal elements, which allows verifying the guess with
+/// a single comparison per element.
+fn is_all_equal<T, F>(v: &[T], pivot: usize, is_less: &mut F) -> bool
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+
+    let first = &v[0];
+    let last = &v[len - 1];
+    let pivot = &v[pivot];
+
+    let endpoints_equal_pivot = !is_less(first, pivot)
+        && !is_less(pivot, first)
+        && !is_less(last, pivot)
+        && !is_less(pivot, last);
+
+    // Not `find_streak`, that takes two comparisons per leading equal element.
+    endpoints_equal_pivot && v.windows(2).all(|w| !is_less(&w[1], &w[0]))
+}
+
+/// Merges the sorted runs `v[..mid]` and `v[mid..]` in-place, without any auxiliary memory.
+///
+/// Splits the longer run at its middle, finds the matching split point in the other run with a
+/// binary search, and rotates the two inner parts past each other. This leaves two independent and
+/// smaller merge problems. Needs *O*(*n* \* log(*n*)) comparisons and more moves than a buffered
+/// merge, but most of the work is skipped if one run is much shorter than the other.
+/// Merging two halves of 10k `String` takes about 1.6x the time of the buffered merge in the
+/// stable sort, see the `rotation_merge` benchmark.
+///
+/// `is_less` is only called during the binary searches and never while elements are moved, so
+/// `v` is always a permutation of the original elements, even if `is_less` panics.
+pub fn rotation_merge<T, F>(mut v: &mut [T], mut mid: usize, is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    loop {
+        let len = v.len();
+
+        if mid == 0 || mid >= len {
+            return;
+        }
+
+        // The runs are already in order.
+        if !is_less(&v[mid], &v[mid - 1]) {
+            return;
+        }
+
+        let (left_cut, right_cut) = if mid >= len - mid {
+            // All elements of the right run before `right_cut` are less than `v[left_cut]`.
+            let left_cut = mid / 2;
+            let right_cut = mid + v[mid..].partition_point(|elem| is_less(elem, &v[left_cut]));
+            (left_cut, right_cut)
+        } else {
+            // All elements of the left run before `left_cut` are not greater than `v[right_cut]`.
+            let right_cut = mid + (len - mid) / 2;
+            let left_cut = v[..mid].partition_point(|elem| !is_less(&v[right_cut], elem));
+            (left_cut, right_cut)
+        };
+
+        v[left_cut..right_cut].rotate_left(mid - left_cut);
+        let new_mid = left_cut + (right_cut - mid);
+
+        // Recurse into the shorter side only and continue with the longer one, this bounds the
+        // recursion depth to *O*(log(*n*)).
+        let (left, right) = mem::take(&mut v).split_at_mut(new_mid);
+        if left.len() < right.len() {
+            rotation_merge(left, left_cut, is_less);
+            v = right;
+            mid -= left_cut;
+        } else {
+            rotation_merge(right, mid - left_cut, is_less);
+            v = left;
+            mid = left_cut;
+        }
+    }
+}
+
+/// Guesses whether a significant part of `v[1..]` is equal to `v[0]`, given that no element of
+/// `v[1..]` is less than `v[0]`.
+///
+/// Checks three samples, so this is cheap enough to be done after every partition. Two of them
+/// have to be equal, the extra pass that moves equal elements next to the pivot only pays off if
+/// they make up a large part of `v`. Slices without duplicates never produce a false positive. For
+/// 1M random `i32` the samples add 0.6% comparisons and no measurable time.
+fn has_many_equal_to_pivot<T, F>(v: &[T], is_less: &mut F) -> bool
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    const MIN_LEN: usize = 8;
+
+    let len = v.len();
+    if len < MIN_LEN {
+        return false;
+    }
+
+    let pivot = &v[0];
+    let len_div_4 = len / 4;
+
+    let equal_count = !is_less(pivot, &v[len_div_4]) as usize
+        + !is_less(pivot, &v[len_div_4 * 2]) as usize
+        + !is_less(pivot, &v[len_div_4 * 3]) as usize;
+
+    equal_count >= 2
+}
+
+/// Sorts `v` using heapsort, which guarantees *O*(*n* \* log(*n*)) worst-case.
+///
+/// Never inline this, it sits the main hot-loop in `recurse` and is meant as unlikely algorithmic
+/// fallback.
+#[inline(never)]
+pub fn heapsort<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // This binary heap respects the invariant `parent >= child`.
+    let mut sift_down = |v: &mut [T], mut node: usize| {
+        let len = v.len();
+
+        // Children of `node`.
+        let mut child = 2 * node + 1;
+
+        // Sift down through the levels where `node` has two children. Choosing the greater child
+        // needs no bounds branch there, and the comparison result is applied branchless, which is
+        // especially good for primitives.
+        while child + 1 < len {
+            // SAFETY: `node < child` and `child + 1 < len`, so all accesses are in-bounds.
+            unsafe {
+                child += is_less(v.get_unchecked(child), v.get_unchecked(child + 1)) as usize;
+
+                // Stop if the invariant holds at `node`.
+                if !is_less(v.get_unchecked(node), v.get_unchecked(child)) {
+                    return;
+                }
+
+                // Swap `node` with the greater child, move one step down, and continue sifting.
+                ptr::swap_nonoverlapping(v.as_mut_ptr().add(node), v.as_mut_ptr().add(child), 1);
+            }
+            node = child;
+            child = 2 * node + 1;
+        }
+
+        // Only the parent of the last element can have a single child, fix it up once at the end.
+        if child + 1 == len && is_less(&v[node], &v[child]) {
+            v.swap(node, child);
+        }
+    };
+
+    // Build the heap in linear time.
+    for i in (0..v.len() / 2).rev() {
+        sift_down(v, i);
+    }
+
+    // Pop maximal elements from the heap.
+    for i in (1..v.len()).rev() {
+        v.swap(0, i);
+        sift_down(&mut v[..i], 0);
+    }
+}
+
+/// TODO explain
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+#[inline(always)]
+unsafe fn swap_elements_between_blocks<T>(
+    l_ptr: *mut T,
+    r_ptr: *mut T,
+    mut l_offsets_ptr: *const u8,
+    mut r_offsets_ptr: *const u8,
+    count: usize,
+) -> (*const u8, *const u8) {
+    macro_rules! left {
+        () => {
+            l_ptr.add(*l_offsets_ptr as usize)
+        };
+    }
+    macro_rules! right {
+        () => {
+            r_ptr.sub(*r_offsets_ptr as usize + 1)
+        };
+    }
+
+    if count <= 1 {
+        if count == 1 {
+            // SAFETY: TODO
+            unsafe {
+                ptr::swap_nonoverlapping(left!(), right!(), 1);
+                l_offsets_ptr = l_offsets_ptr.add(1);
+                r_offsets_ptr = r_offsets_ptr.add(1);
+            }
+        }
+
+        return (l_offsets_ptr, r_offsets_ptr);
+    }
+
+    // Instead of swapping one pair at the time, it is more efficient to perform a cyclic
+    // permutation. This is not strictly equivalent to swapping, but produces a similar
+    // result using fewer memory operations.
+    //
     // Example cyclic permutation to swap A,B,C,D with W,X,Y,Z
     //
     // A -> TMP
@@ -334,6 +3329,20 @@ unsafe fn swap_elements_between_blocks<T>(
     (l_offsets_ptr, r_offsets_ptr)
 }
 
+/// Checks an invariant that the unsafe code in [`partition_in_blocks`] relies on. With the
+/// `paranoid` feature the check is a regular `assert!`, so fuzzing release builds turns a violation
+/// into a panic instead of UB. Otherwise it is a `debug_assert!` and compiles to nothing in release
+/// builds.
+macro_rules! paranoid_assert {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "paranoid") {
+            assert!($($arg)*);
+        } else {
+            debug_assert!($($arg)*);
+        }
+    };
+}
+
 /// Partitions `v` into elements smaller than `pivot`, followed by elements greater than or equal
 /// to `pivot`.
 ///
@@ -384,7 +3393,7 @@ where
 
     // Returns the number of elements between pointers `l` (inclusive) and `r` (exclusive).
     fn width<T>(l: *const T, r: *const T) -> usize {
-        debug_assert!(r.addr() >= l.addr());
+        paranoid_assert!(r.addr() >= l.addr());
 
         unsafe { r.sub_ptr(l) }
     }
@@ -414,8 +3423,8 @@ where
                 block_l = rem / 2;
                 block_r = rem - block_l;
             }
-            debug_assert!(block_l <= BLOCK && block_r <= BLOCK);
-            debug_assert!(width(l, r) == block_l + block_r);
+            paranoid_assert!(block_l <= BLOCK && block_r <= BLOCK);
+            paranoid_assert!(width(l, r) == block_l + block_r);
         }
 
         if start_l == end_l {
@@ -442,6 +3451,8 @@ where
                     elem = elem.add(1);
                 }
             }
+
+            paranoid_assert!(width(start_l, end_l) <= block_l);
         }
 
         if start_r == end_r {
@@ -469,10 +3480,13 @@ where
                     end_r = end_r.wrapping_add(is_less(&*elem, pivot) as usize);
                 }
             }
+
+            paranoid_assert!(width(start_r, end_r) <= block_r);
         }
 
         // Number of out-of-order elements to swap between the left and right side.
         let count = cmp::min(width(start_l, end_l), width(start_r, end_r));
+        paranoid_assert!(count <= BLOCK);
 
         // SAFETY: TODO
         unsafe {
@@ -490,6 +3504,7 @@ where
             // safe. Otherwise, the debug assertions in the `is_done` case guarantee that
             // `width(l, r) == block_l + block_r`, namely, that the block sizes have been adjusted to account
             // for the smaller number of remaining elements.
+            paranoid_assert!(block_l <= width(l, r));
             l = unsafe { l.add(block_l) };
         }
 
@@ -498,6 +3513,7 @@ where
 
             // SAFETY: Same argument as [block-width-guarantee]. Either this is a full block `2*BLOCK`-wide,
             // or `block_r` has been adjusted for the last handful of elements.
+            paranoid_assert!(block_r <= width(l, r));
             r = unsafe { r.sub(block_r) };
         }
 
@@ -509,11 +3525,18 @@ where
     // All that remains now is at most one block (either the left or the right) with out-of-order
     // elements that need to be moved. Such remaining elements can be simply shifted to the end
     // within their block.
+    //
+    // For `u32` and `u64` each `ptr::swap` is already two loads and two stores. Replacing the swaps
+    // with a cyclic rotation through a single temporary, as in `swap_elements_between_blocks`,
+    // does the same number of memory operations, but chains every copy on the previous one. That
+    // was ~10-40% slower for a remainder of 256 elements with half of them out of order. It also
+    // yields a different, if equally valid, order, because an offset can point at a position
+    // that an earlier swap already filled.
 
     if start_l < end_l {
         // The left block remains.
         // Move its remaining out-of-order elements to the far right.
-        debug_assert_eq!(width(l, r), block_l);
+        paranoid_assert!(width(l, r) == block_l);
         while start_l < end_l {
             // remaining-elements-safety
             // SAFETY: while the loop condition holds there are still elements in `offsets_l`, so it
@@ -528,6 +3551,7 @@ where
             //    the last block, so the `l.offset` calls are valid.
             unsafe {
                 end_l = end_l.sub(1);
+                paranoid_assert!((*end_l as usize) < width(l, r));
                 ptr::swap(l.add(*end_l as usize), r.sub(1));
                 r = r.sub(1);
             }
@@ -536,11 +3560,12 @@ where
     } else if start_r < end_r {
         // The right block remains.
         // Move its remaining out-of-order elements to the far left.
-        debug_assert_eq!(width(l, r), block_r);
+        paranoid_assert!(width(l, r) == block_r);
         while start_r < end_r {
             // SAFETY: See the reasoning in [remaining-elements-safety].
             unsafe {
                 end_r = end_r.sub(1);
+                paranoid_assert!((*end_r as usize) < width(l, r));
                 ptr::swap(l, r.sub(*end_r as usize + 1));
                 l = l.add(1);
             }
@@ -552,15 +3577,61 @@ where
     }
 }
 
+/// Rotation state of [`fulcrum_partition_impl`], doubles as its panic guard.
+///
+/// The first and last `ROTATION_ELEMS` elements are moved into the swap buffer up-front, which
+/// leaves `2 * ROTATION_ELEMS` holes in `v`. Each rotation step moves one element into a hole and
+/// leaves a new hole where it was read from, so the hole count never changes. The holes are
+/// `[elem_i, a_ptr)` on the left and `(t_ptr, r_ptr + elem_i]` on the right, where the unprocessed
+/// front of the active side is `x_ptr`. Once the swap buffer is drained, the holes are the
+/// contiguous range `[elem_i, r_ptr + elem_i]` and the remaining swap elements are
+/// `[x_ptr, swap_end)`. `is_less` is called before the copy of each step, so if it panics, the
+/// guard can fill the holes with the elements still in the swap buffer.
 struct FulcrumState<T> {
+    arr_ptr: *mut T,
+    swap_ptr: *mut T,
+    swap_len: usize,
     r_ptr: *mut T,
     x_ptr: *mut T,
+    a_ptr: *mut T,
+    t_ptr: *mut T,
     elem_i: usize,
+    offset_val: isize,
+    draining_swap: bool,
+}
+
+impl<T> Drop for FulcrumState<T> {
+    fn drop(&mut self) {
+        // SAFETY: See the invariants documented on `FulcrumState`. The holes and the remaining
+        // swap elements are always the same count, and the swap buffer never overlaps `v`.
+        unsafe {
+            let hole_ptr = self.arr_ptr.add(self.elem_i);
+
+            if self.draining_swap {
+                let swap_end = self.swap_ptr.add(self.swap_len);
+                ptr::copy_nonoverlapping(self.x_ptr, hole_ptr, swap_end.sub_ptr(self.x_ptr));
+            } else {
+                let (a_ptr, t_ptr) = if self.offset_val > 0 {
+                    (self.x_ptr, self.t_ptr)
+                } else {
+                    (self.a_ptr, self.x_ptr)
+                };
+
+                let left_holes = a_ptr.sub_ptr(hole_ptr);
+                let right_holes = self.r_ptr.add(self.elem_i).sub_ptr(t_ptr);
+                debug_assert_eq!(left_holes + right_holes, self.swap_len);
+
+                ptr::copy_nonoverlapping(self.swap_ptr, hole_ptr, left_holes);
+                ptr::copy_nonoverlapping(self.swap_ptr.add(left_holes), t_ptr.add(1), right_holes);
+            }
+        }
+    }
 }
 
+/// Moves `loop_len` elements starting at `state.x_ptr` in direction `offset_val` into the holes,
+/// elements smaller than `pivot` to the left side and the others to the right side.
 #[inline(always)]
 unsafe fn fulcrum_rotate<T, F>(
-    arr_ptr: *mut T,
     state: &mut FulcrumState<T>,
     offset_val: isize,
     loop_len: usize,
@@ -569,6 +3640,9 @@ unsafe fn fulcrum_rotate<T, F>(
 ) where
     F: FnMut(&T, &T) -> bool,
 {
+    let arr_ptr = state.arr_ptr;
+    state.offset_val = offset_val;
+
     for _ in 0..loop_len {
         let is_l = is_less(&*state.x_ptr, pivot);
         let target_ptr = if is_l {
@@ -576,131 +3650,532 @@ unsafe fn fulcrum_rotate<T, F>(
         } else {
             state.r_ptr.add(state.elem_i)
         };
-        ptr::copy(state.x_ptr, target_ptr, 1);
-        state.elem_i += is_l as usize;
-        state.x_ptr = state.x_ptr.wrapping_offset(offset_val);
-        state.r_ptr = state.r_ptr.wrapping_sub(1);
+        ptr::copy(state.x_ptr, target_ptr, 1);
+        state.elem_i += is_l as usize;
+        state.x_ptr = state.x_ptr.wrapping_offset(offset_val);
+        state.r_ptr = state.r_ptr.wrapping_sub(1);
+    }
+}
+
+/// Partitions `v` with a single left to right scan, swapping every element with the first element
+/// not known to be less than `pivot`, and advancing that position if the element was less.
+///
+/// The swap happens unconditionally, so the loop has no data dependent branches. Each element is
+/// compared before anything is moved, so a panic in `is_less` leaves a permutation of `v`.
+fn partition_lomuto_branchless<T, F>(v: &mut [T], pivot: &T, is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+    let arr_ptr = v.as_mut_ptr();
+    let mut lt_count = 0;
+
+    // SAFETY: `lt_count <= i < len`, so both pointers are in bounds. `ptr::swap` allows them to be
+    // equal.
+    unsafe {
+        for i in 0..len {
+            let right = arr_ptr.add(i);
+            let right_is_lt = is_less(&*right, pivot);
+            ptr::swap(arr_ptr.add(lt_count), right);
+            lt_count += right_is_lt as usize;
+        }
+    }
+
+    lt_count
+}
+
+// Inspired by Igor van den Hoven and his work in quadsort/crumsort.
+fn fulcrum_partition_impl<T, F, const ROTATION_ELEMS: usize>(
+    v: &mut [T],
+    pivot: &T,
+    is_less: &mut F,
+) -> usize
+where
+    T: Freeze,
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+
+    const SWAP_SIZE: usize = 64;
+
+    assert!(len >= (ROTATION_ELEMS * 2) && ROTATION_ELEMS <= 32);
+
+    let mut swap = MaybeUninit::<[T; SWAP_SIZE]>::uninit();
+    let swap_ptr = swap.as_mut_ptr() as *mut T;
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // SAFETY: `len >= 2 * ROTATION_ELEMS` and `2 * ROTATION_ELEMS <= SWAP_SIZE`, so all copies stay
+    // in bounds. From here on every element of `v` has exactly one live copy, either in `v` or in
+    // `swap`, and `state` restores `v` if `is_less` panics, see `FulcrumState`.
+    unsafe {
+        ptr::copy_nonoverlapping(arr_ptr, swap_ptr, ROTATION_ELEMS);
+        ptr::copy_nonoverlapping(
+            arr_ptr.add(len - ROTATION_ELEMS),
+            swap_ptr.add(ROTATION_ELEMS),
+            ROTATION_ELEMS,
+        );
+
+        let mut state = FulcrumState {
+            arr_ptr,
+            swap_ptr,
+            swap_len: ROTATION_ELEMS * 2,
+            r_ptr: arr_ptr.add(len - 1),
+            x_ptr: arr_ptr.add(ROTATION_ELEMS),
+            a_ptr: arr_ptr.add(ROTATION_ELEMS),
+            t_ptr: arr_ptr.add(len - (ROTATION_ELEMS + 1)),
+            elem_i: 0,
+            offset_val: 1,
+            draining_swap: false,
+        };
+
+        // Rotate from the side with fewer holes, so that the other side has at least
+        // `ROTATION_ELEMS` holes to take the elements that belong there.
+        let advance_left = |state: &FulcrumState<T>| {
+            (state.a_ptr.sub_ptr(arr_ptr) - state.elem_i) <= ROTATION_ELEMS
+        };
+
+        for _ in 0..((len / ROTATION_ELEMS) - 2) {
+            let loop_len = ROTATION_ELEMS;
+            if advance_left(&state) {
+                state.x_ptr = state.a_ptr;
+                fulcrum_rotate(&mut state, 1, loop_len, pivot, is_less);
+                state.a_ptr = state.x_ptr;
+            } else {
+                state.x_ptr = state.t_ptr;
+                fulcrum_rotate(&mut state, -1, loop_len, pivot, is_less);
+                state.t_ptr = state.x_ptr;
+            }
+        }
+
+        let loop_len = len % ROTATION_ELEMS;
+        if advance_left(&state) {
+            state.x_ptr = state.a_ptr;
+            fulcrum_rotate(&mut state, 1, loop_len, pivot, is_less);
+            state.a_ptr = state.x_ptr;
+        } else {
+            state.x_ptr = state.t_ptr;
+            fulcrum_rotate(&mut state, -1, loop_len, pivot, is_less);
+            state.t_ptr = state.x_ptr;
+        }
+
+        let loop_len = ROTATION_ELEMS * 2;
+        state.x_ptr = swap_ptr;
+        state.draining_swap = true;
+        fulcrum_rotate(&mut state, 1, loop_len, pivot, is_less);
+
+        let is_less_count = state.elem_i;
+        mem::forget(state);
+
+        is_less_count
+    }
+}
+
+// Panics if `v.len() < 32`. Shorter slices use a smaller rotation buffer, so that the rotation
+// doesn't degenerate into copying the whole slice through the swap buffer.
+fn fulcrum_partition<T, F>(v: &mut [T], pivot: &T, is_less: &mut F) -> usize
+where
+    T: Freeze,
+    F: FnMut(&T, &T) -> bool,
+{
+    if v.len() < 256 {
+        fulcrum_partition_impl::<T, F, 16>(v, pivot, is_less)
+    } else {
+        fulcrum_partition_impl::<T, F, 32>(v, pivot, is_less)
+    }
+}
+
+// Only used for types with efficient in-place swaps, see `UnstableSortTypeImpl::partition`. Panic
+// safe, but disabled by default until it has been benchmarked against the Lomuto and block
+// partitions. Use `set_fulcrum_enabled` to measure it.
+const FULCRUM_ENABLED: bool = false;
+
+// Fulcrum partitioning needs at least two rotation buffers worth of elements.
+const FULCRUM_MIN_LEN: usize = 32;
+
+// Largest slice in bytes that is partitioned with `partition_lomuto_branchless` instead of
+// `partition_in_blocks`, if fulcrum partitioning isn't used. In the `partition_lomuto` bench on
+// random `i32` and `u64` inputs, the Lomuto loop was 25-40% faster up to 800 bytes. From 1.6 KiB to
+// 3.2 KiB it was between 20% faster and 12% slower, depending on the run, and at 3.6 KiB of `i32`
+// it was 40% slower. Both work on data that fits into L1 far beyond that point, the block partition
+// simply needs fewer instructions per element once its setup cost is amortized. If fulcrum
+// partitioning is enabled, it keeps precedence.
+const LOMUTO_MAX_BYTES: usize = 2 * 1024;
+
+// Smallest runtime small-sort threshold, `choose_pivot` needs at least 7 elements.
+const MIN_SMALL_SORT_THRESHOLD: usize = 8;
+
+// This path is critical for very small inputs. Always pick insertion sort for these inputs, without
+// any other analysis. This is perf critical for small inputs, in cold code.
+const MAX_LEN_ALWAYS_INSERTION_SORT: usize = 20;
+
+// Smallest runtime `median3_rec` threshold. `median3_rec` recurses while `n * 8` reaches the
+// threshold, with 8 that stops at `n == 0` at the latest.
+const MIN_PSEUDO_MEDIAN_REC_THRESHOLD: usize = 8;
+
+thread_local! {
+    static SMALL_SORT_THRESHOLD_OVERRIDE: Cell<usize> = const { Cell::new(usize::MAX) };
+    static FULCRUM_ENABLED_OVERRIDE: Cell<bool> = const { Cell::new(FULCRUM_ENABLED) };
+}
+
+/// Research and benchmark only. Limits the small-sort to slices of at most `threshold` elements,
+/// for all sorts on the current thread that go through the main `recurse` loop.
+///
+/// The small-sorts use fixed-size stack buffers, so the threshold can only be lowered below the
+/// compiled-in `max_len_small_sort`, larger values have no effect. Values below 8 are raised to 8.
+/// `usize::MAX` restores the compiled-in behavior, which is also the default.
+pub fn set_small_sort_threshold(threshold: usize) {
+    SMALL_SORT_THRESHOLD_OVERRIDE.with(|val| val.set(threshold.max(MIN_SMALL_SORT_THRESHOLD)));
+}
+
+/// Research and benchmark only. Enables or disables fulcrum partitioning for all sorts on the
+/// current thread that go through the main `recurse` loop.
+///
+/// Defaults to the compiled-in `FULCRUM_ENABLED`.
+pub fn set_fulcrum_enabled(enabled: bool) {
+    FULCRUM_ENABLED_OVERRIDE.with(|val| val.set(enabled));
+}
+
+/// Research and test only. Partitions `v` into elements smaller than `pivot`, followed by elements
+/// greater than or equal to `pivot`, and returns the number of elements smaller than `pivot`.
+///
+/// Uses fulcrum partitioning for the types and lengths where [`sort`] would, regardless of
+/// [`set_fulcrum_enabled`], and the same Lomuto or block partitioning as [`sort`] otherwise. For
+/// `v.len() >= 256` the fulcrum layout is the same as the one of
+/// `other::partition::fulcrum_partition_revised`.
+pub fn partition_fulcrum_by<T, F>(v: &mut [T], pivot: &T, is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    <T as UnstableSortTypeImpl>::partition(v, pivot, is_less, true)
+}
+
+/// Research and test only. Same as [`partition_fulcrum_by`], but always uses block partitioning.
+pub fn partition_blocks_by<T, F>(v: &mut [T], pivot: &T, is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    partition_in_blocks(v, pivot, is_less)
+}
+
+/// Research and test only. Same as [`partition_fulcrum_by`], but always uses the branchless
+/// Lomuto partition.
+pub fn partition_lomuto_by<T, F>(v: &mut [T], pivot: &T, is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    partition_lomuto_branchless(v, pivot, is_less)
+}
+
+/// Research and test only. Returns the length of the presorted streak at the start of `v`, and
+/// whether it is descending, the same way [`sort`] detects presorted inputs.
+pub fn find_streak_by<T, F>(v: &[T], is_less: &mut F) -> (usize, bool)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    find_streak(v, is_less)
+}
+
+/// Which type-based code paths [`sort`] and [`sort_by`] take for `T`, see [`describe_dispatch`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DispatchInfo {
+    /// Small slices are sorted with sorting networks, and inputs of some exact lengths with a
+    /// single optimal network, instead of insertion sort and merging.
+    pub uses_network: bool,
+    /// Fulcrum partitioning is used for slices of at least 32 elements, by default. See
+    /// [`set_fulcrum_enabled`].
+    pub uses_fulcrum_eligible: bool,
+    /// Slices of at most 2 KiB are partitioned with a branchless Lomuto loop instead of the block
+    /// partition, if fulcrum partitioning isn't used for them.
+    pub uses_lomuto: bool,
+    /// Longest slice that is sorted with the small-sort, instead of being partitioned further.
+    pub small_sort_max: usize,
+    /// `T` is at most as large as `u64`, so swapping it in place compiles to a few register moves.
+    pub efficient_swap: bool,
+}
+
+/// Research only. Reports which type-based code paths the sort takes for `T`.
+///
+/// The paths are chosen at compile time by specialization on `Freeze` and the size of `T`, which is
+/// invisible from the outside. The result is computed from the same predicates, so it tells
+/// whether a type gets the fast paths without profiling. E.g. a newtype around `u64` gets them,
+/// while wrapping it in a `Cell` or growing it to 16 bytes loses them. Runtime overrides like
+/// [`set_small_sort_threshold`] are not reflected.
+pub fn describe_dispatch<T>() -> DispatchInfo {
+    let is_freeze = <T as IsFreeze>::value();
+    let efficient_swap = has_efficient_in_place_swap::<T>();
+
+    DispatchInfo {
+        uses_network: is_freeze && efficient_swap,
+        uses_fulcrum_eligible: is_freeze && efficient_swap && FULCRUM_ENABLED,
+        uses_lomuto: is_freeze && has_efficient_lomuto_partition::<T>(),
+        small_sort_max: max_len_small_sort::<T>(),
+        efficient_swap,
+    }
+}
+
+/// Runtime overrides of compile-time research knobs. Loaded once per top-level sort call, so that
+/// the thread-local access doesn't show up in the hot loop.
+#[derive(Copy, Clone)]
+struct RuntimeConfig {
+    small_sort_threshold: usize,
+    fulcrum_enabled: bool,
+    small_sort: SmallSort,
+    insertion_always_max: usize,
+    pseudo_median_rec_threshold: usize,
+}
+
+impl RuntimeConfig {
+    /// The compiled-in behavior, ignoring the thread-local overrides.
+    const COMPILED: Self = Self {
+        small_sort_threshold: usize::MAX,
+        fulcrum_enabled: FULCRUM_ENABLED,
+        small_sort: SmallSort::Auto,
+        insertion_always_max: MAX_LEN_ALWAYS_INSERTION_SORT,
+        pseudo_median_rec_threshold: PSEUDO_MEDIAN_REC_THRESHOLD,
+    };
+
+    fn load() -> Self {
+        Self {
+            small_sort_threshold: SMALL_SORT_THRESHOLD_OVERRIDE.with(|val| val.get()),
+            fulcrum_enabled: FULCRUM_ENABLED_OVERRIDE.with(|val| val.get()),
+            small_sort: SmallSort::Auto,
+            insertion_always_max: MAX_LEN_ALWAYS_INSERTION_SORT,
+            pseudo_median_rec_threshold: PSEUDO_MEDIAN_REC_THRESHOLD,
+        }
+    }
+
+    /// Longest slice of `T` that is small-sorted instead of partitioned with this config, see
+    /// `small_sort_configured`.
+    fn small_sort_max_len<T>(&self) -> usize {
+        let strategy_max_len = match self.small_sort {
+            SmallSort::Auto => max_len_small_sort::<T>(),
+            SmallSort::Network if <T as IsFreeze>::value() => max_len_small_sort::<i32>(),
+            _ => max_len_small_sort::<String>(),
+        };
+
+        self.small_sort_threshold.min(strategy_max_len)
     }
 }
 
-// Inspired by Igor van den Hoven and his work in quadsort/crumsort.
-// TODO document.
-fn fulcrum_partition_impl<T, F, const ROTATION_ELEMS: usize>(
-    v: &mut [T],
-    pivot: &T,
-    is_less: &mut F,
-) -> usize
+/// Research only. Small-sort strategy of the main quicksort loop, see [`sort_by_with_small_sort`].
+///
+/// The strategies other than `Insertion` rely on the `Freeze` optimizations. For types that don't
+/// qualify, they behave like `Auto`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SmallSort {
+    /// Insertion sort, for sub-slices of up to 20 elements.
+    Insertion,
+    /// Sorting networks with insertion sort for the remainder, for sub-slices of up to 36
+    /// elements. This is what `Auto` picks for small types like integers.
+    Network,
+    /// `sort8_indirect` of both halves, followed by a bidirectional merge, for sub-slices of up to
+    /// 20 elements. This is what `Auto` picks for larger types.
+    IndirectMerge,
+    /// Picks the strategy based on the type, the default.
+    Auto,
+}
+
+/// Same as [`sort_by`], but uses `small_sort` for the sub-slices of the main quicksort loop.
+///
+/// Research only, allows comparing the effect of the small-sort strategies on the overall sort.
+/// Inputs that are fully handled before the main loop, like very small or presorted ones, are not
+/// affected.
+pub fn sort_by_with_small_sort<T, F>(v: &mut [T], mut compare: F, small_sort: SmallSort)
 where
-    T: Freeze,
-    F: FnMut(&T, &T) -> bool,
+    F: FnMut(&T, &T) -> Ordering,
 {
-    // TODO explain ideas. and panic safety. cleanup.
-    let len = v.len();
-
-    const SWAP_SIZE: usize = 64;
-
-    assert!(len >= (ROTATION_ELEMS * 2) && ROTATION_ELEMS <= 32);
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
 
-    let advance_left = |a_ptr: *const T, arr_ptr: *const T, elem_i: usize| -> bool {
-        // SAFETY: TODO
-        unsafe { (a_ptr.sub_ptr(arr_ptr) - elem_i) <= ROTATION_ELEMS }
+    let config = RuntimeConfig {
+        small_sort,
+        ..RuntimeConfig::load()
     };
 
-    let mut swap = MaybeUninit::<[T; SWAP_SIZE]>::uninit();
-    let swap_ptr = swap.as_mut_ptr() as *mut T;
+    if let Some(limit) = quicksort_prelude(v, &mut is_less, Some(config)) {
+        recurse(v, &mut is_less, None, limit, config);
+    }
+}
 
-    let arr_ptr = v.as_mut_ptr();
+/// Research only. Per-call replacements of compile-time thresholds, see [`sort_by_tuned`].
+///
+/// Values outside of the safe range are clamped, see the fields. The default reproduces
+/// [`sort_by`] without the thread-local overrides.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TuneParams {
+    /// Sub-slices of the main quicksort loop of up to this length are small-sorted. Same as
+    /// [`set_small_sort_threshold`]: the small-sorts use fixed-size stack buffers, so values above
+    /// the compiled-in `max_len_small_sort` of the type have no effect, and values below 8 are
+    /// raised to 8. Defaults to `usize::MAX`.
+    pub small_sort_max: usize,
+    /// Inputs of up to this length are insertion sorted, without looking for presorted runs first.
+    /// Insertion sort needs no buffer, so every value is safe, larger ones make the sort quadratic
+    /// for more inputs. Defaults to 20.
+    pub insertion_always_max: usize,
+    /// Slices of at least this length sample the pivot with the recursive median of 3, shorter ones
+    /// use an approximate median of 7. Values below 8 are raised to 8. Defaults to 64.
+    pub median_of_medians_min: usize,
+}
 
-    // SAFETY: TODO
-    unsafe {
-        ptr::copy_nonoverlapping(arr_ptr, swap_ptr, ROTATION_ELEMS);
-        ptr::copy_nonoverlapping(
-            arr_ptr.add(len - ROTATION_ELEMS),
-            swap_ptr.add(ROTATION_ELEMS),
-            ROTATION_ELEMS,
-        );
+impl Default for TuneParams {
+    fn default() -> Self {
+        Self {
+            small_sort_max: usize::MAX,
+            insertion_always_max: MAX_LEN_ALWAYS_INSERTION_SORT,
+            median_of_medians_min: PSEUDO_MEDIAN_REC_THRESHOLD,
+        }
+    }
+}
 
-        let mut state = FulcrumState {
-            r_ptr: arr_ptr.add(len - 1),
-            x_ptr: ptr::null_mut(),
-            elem_i: 0,
-        };
+/// Same as [`sort_deterministic`], but with the thresholds in `params` instead of the compiled-in
+/// ones.
+///
+/// Research only, allows sweeping the thresholds without recompiling. `params` is read once at
+/// entry.
+pub fn sort_by_tuned<T, F>(v: &mut [T], mut compare: F, params: TuneParams)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Ordering::Less;
+
+    let config = RuntimeConfig {
+        small_sort_threshold: params.small_sort_max.max(MIN_SMALL_SORT_THRESHOLD),
+        insertion_always_max: params.insertion_always_max,
+        pseudo_median_rec_threshold: params
+            .median_of_medians_min
+            .max(MIN_PSEUDO_MEDIAN_REC_THRESHOLD),
+        ..RuntimeConfig::COMPILED
+    };
 
-        let mut a_ptr = arr_ptr.add(ROTATION_ELEMS);
-        let mut t_ptr = arr_ptr.add(len - (ROTATION_ELEMS + 1));
+    if let Some(limit) = quicksort_prelude(v, &mut is_less, Some(config)) {
+        recurse(v, &mut is_less, None, limit, config);
+    }
+}
 
-        for _ in 0..((len / ROTATION_ELEMS) - 2) {
-            let loop_len = ROTATION_ELEMS;
-            if advance_left(a_ptr, arr_ptr, state.elem_i) {
-                state.x_ptr = a_ptr;
-                fulcrum_rotate(arr_ptr, &mut state, 1, loop_len, pivot, is_less);
-                a_ptr = state.x_ptr;
-            } else {
-                state.x_ptr = t_ptr;
-                fulcrum_rotate(arr_ptr, &mut state, -1, loop_len, pivot, is_less);
-                t_ptr = state.x_ptr;
-            }
-        }
+/// Sorts `v` with the small-sort selected by `strategy`, if `v` is short enough for it. Returns
+/// `true` if `v` was sorted.
+#[inline(always)]
+fn small_sort_configured<T, F>(v: &mut [T], is_less: &mut F, strategy: SmallSort) -> bool
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    if intrinsics::likely(strategy == SmallSort::Auto) {
+        <T as UnstableSortTypeImpl>::small_sort(v, is_less)
+    } else {
+        <T as SmallSortStrategy>::small_sort_with(v, is_less, strategy)
+    }
+}
 
-        let loop_len = len % ROTATION_ELEMS;
-        if advance_left(a_ptr, arr_ptr, state.elem_i) {
-            state.x_ptr = a_ptr;
-            fulcrum_rotate(arr_ptr, &mut state, 1, loop_len, pivot, is_less);
-        } else {
-            state.x_ptr = t_ptr;
-            fulcrum_rotate(arr_ptr, &mut state, -1, loop_len, pivot, is_less);
+trait SmallSortStrategy: Sized {
+    fn small_sort_with<F>(v: &mut [Self], is_less: &mut F, strategy: SmallSort) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool;
+}
+
+impl<T> SmallSortStrategy for T {
+    default fn small_sort_with<F>(v: &mut [Self], is_less: &mut F, strategy: SmallSort) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        match strategy {
+            SmallSort::Insertion => small_sort_insertion(v, is_less),
+            _ => <T as UnstableSortTypeImpl>::small_sort(v, is_less),
         }
+    }
+}
 
-        let loop_len = ROTATION_ELEMS * 2;
-        state.x_ptr = swap_ptr;
-        fulcrum_rotate(arr_ptr, &mut state, 1, loop_len, pivot, is_less);
+impl<T: Freeze> SmallSortStrategy for T {
+    fn small_sort_with<F>(v: &mut [Self], is_less: &mut F, strategy: SmallSort) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        let len = v.len();
 
-        state.elem_i
+        match strategy {
+            SmallSort::Insertion => small_sort_insertion(v, is_less),
+            SmallSort::Network if len <= max_len_small_sort::<i32>() => {
+                small_sort_network(v, is_less);
+                true
+            }
+            SmallSort::IndirectMerge if len <= max_len_small_sort::<String>() => {
+                small_sort_general(v, is_less);
+                true
+            }
+            SmallSort::Auto => <T as UnstableSortTypeImpl>::small_sort(v, is_less),
+            _ => false,
+        }
     }
 }
 
-fn fulcrum_partition<T, F>(v: &mut [T], pivot: &T, is_less: &mut F) -> usize
+fn small_sort_insertion<T, F>(v: &mut [T], is_less: &mut F) -> bool
 where
-    T: Freeze,
     F: FnMut(&T, &T) -> bool,
 {
-    // TODO explain.
-    if v.len() < 256 {
-        fulcrum_partition_impl::<T, F, 16>(v, pivot, is_less)
+    const MAX_LEN_INSERTION_SORT: usize = 20;
+
+    if v.len() <= MAX_LEN_INSERTION_SORT {
+        if v.len() >= 2 {
+            insertion_sort_shift_left(v, 1, is_less);
+        }
+
+        true
     } else {
-        fulcrum_partition_impl::<T, F, 32>(v, pivot, is_less)
+        false
     }
 }
 
-// Disabled by default because it currently has panic safety issues.
-const FULCRUM_ENABLED: bool = false;
-
 /// Partitions `v` into elements smaller than `v[pivot]`, followed by elements greater than or
 /// equal to `v[pivot]`.
 ///
+/// Returns the number of elements smaller than `v[pivot]`.
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+fn partition<T, F>(v: &mut [T], pivot: usize, is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    partition_configured(v, pivot, is_less, FULCRUM_ENABLED).0
+}
+
+/// Same as [`partition`], but `fulcrum_enabled` replaces the compiled-in `FULCRUM_ENABLED`.
+///
 /// Returns a tuple of:
 ///
 /// 1. Number of elements smaller than `v[pivot]`.
 /// 2. True if `v` was already partitioned.
-#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
-fn partition<T, F>(v: &mut [T], pivot: usize, is_less: &mut F) -> usize
+#[inline(always)]
+fn partition_configured<T, F>(
+    v: &mut [T],
+    pivot: usize,
+    is_less: &mut F,
+    fulcrum_enabled: bool,
+) -> (usize, bool)
 where
     F: FnMut(&T, &T) -> bool,
 {
-    let mid = {
-        // Place the pivot at the beginning of slice.
-        v.swap(0, pivot);
-        let (pivot, v) = v.split_at_mut(1);
-        let pivot = &mut pivot[0];
+    assert!(pivot < v.len());
+
+    // Read the pivot into a stack-allocated variable for efficiency, and move `v[0]` into the gap it
+    // leaves. This has the same effect as swapping the pivot to the front and reading it from there,
+    // with two instead of four moves of `T`, which matters for large types.
+    let v_base = v.as_mut_ptr();
+    // SAFETY: `pivot` is in bounds, checked above. `v[pivot]` is filled with `v[0]` if they are
+    // distinct, after that `v[0]` is the only gap in `v`.
+    let tmp = mem::ManuallyDrop::new(unsafe {
+        let tmp = ptr::read(v_base.add(pivot));
+        if pivot != 0 {
+            ptr::copy_nonoverlapping(v_base, v_base.add(pivot), 1);
+        }
+        tmp
+    });
 
-        // Read the pivot into a stack-allocated variable for efficiency. If a following comparison
-        // operation panics, the pivot will be automatically written back into the slice.
+    let (mid, was_partitioned) = {
+        let (hole, v) = v.split_at_mut(1);
 
-        // SAFETY: `pivot` is a reference to the first element of `v`, so `ptr::read` is safe.
-        let tmp = mem::ManuallyDrop::new(unsafe { ptr::read(pivot) });
-        let _pivot_guard = InsertionHole {
+        // If a following comparison operation panics, the pivot will be automatically written back
+        // into the gap at the beginning of the slice.
+        let pivot_guard = InsertionHole {
             src: &*tmp,
-            dest: pivot,
+            dest: &mut hole[0],
         };
         let pivot = &*tmp;
 
@@ -721,22 +4196,59 @@ where
         //     }
         // }
 
-        let is_less_count = <T as UnstableSortTypeImpl>::partition(v, pivot, is_less);
+        // Find the first pair of out-of-order elements. If there is none, `v` is already
+        // partitioned and doesn't have to be touched. Otherwise only the range in between is
+        // partitioned, so no element is compared twice.
+        let len = v.len();
+        let mut l = 0;
+        let mut r = len;
 
-        is_less_count
+        // SAFETY: `l < r <= len` holds for every access.
+        unsafe {
+            while l < r && is_less(v.get_unchecked(l), pivot) {
+                l += 1;
+            }
+
+            while l < r && !is_less(v.get_unchecked(r - 1), pivot) {
+                r -= 1;
+            }
+        }
+
+        let result = if l >= r {
+            (l, true)
+        } else {
+            let is_less_count = <T as UnstableSortTypeImpl>::partition(
+                &mut v[l..r],
+                pivot,
+                is_less,
+                fulcrum_enabled,
+            );
+
+            (l + is_less_count, false)
+        };
 
         // pivot quality measurement.
         // println!("len: {} is_less: {}", v.len(), l + is_less_count);
 
-        // `_pivot_guard` goes out of scope and writes the pivot (which is a stack-allocated
-        // variable) back into the slice where it originally was. This step is critical in ensuring
-        // safety!
+        // No more comparisons, the gap is filled below without writing the pivot back first.
+        mem::forget(pivot_guard);
+
+        result
     };
 
-    // Place the pivot between the two partitions.
-    v.swap(0, mid);
+    // Place the pivot between the two partitions. `v[mid]` moves into the gap at the beginning,
+    // which is the same as writing the pivot back and swapping it with `v[mid]`.
+    // SAFETY: `mid < v.len()`, because `mid` counts elements of `v[1..]`. `v[0]` is a gap, so
+    // `v[mid]` can be moved there, and then `v[mid]` is filled with the pivot.
+    unsafe {
+        let v_base = v.as_mut_ptr();
+        if mid != 0 {
+            ptr::copy_nonoverlapping(v_base.add(mid), v_base, 1);
+        }
+        ptr::copy_nonoverlapping(&*tmp, v_base.add(mid), 1);
+    }
 
-    mid
+    (mid, was_partitioned)
 }
 
 /// Partitions `v` into elements equal to `v[pivot]` followed by elements greater than `v[pivot]`.
@@ -757,68 +4269,305 @@ where
 ///
 /// `limit` is the number of allowed imbalanced partitions before switching to `heapsort`. If zero,
 /// this function will immediately switch to heapsort.
-#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+#[inline(always)]
 fn recurse<'a, T, F>(
+    v: &'a mut [T],
+    is_less: &mut F,
+    ancestor_pivot: Option<&'a T>,
+    limit: u32,
+    config: RuntimeConfig,
+) where
+    F: FnMut(&T, &T) -> bool,
+{
+    recurse_with_fallback::<T, F, DefaultFallback, _>(
+        v,
+        is_less,
+        ancestor_pivot,
+        limit,
+        config,
+        &mut (),
+    );
+}
+
+/// Same as [`recurse`], but switches to `FB` instead of `heapsort` once `limit` reaches zero.
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+fn recurse_with_fallback<'a, T, F, FB, O>(
     mut v: &'a mut [T],
     is_less: &mut F,
     mut ancestor_pivot: Option<&'a T>,
     mut limit: u32,
+    config: RuntimeConfig,
+    observer: &mut O,
 ) where
     F: FnMut(&T, &T) -> bool,
+    FB: Fallback,
+    O: RecurseObserver<T>,
+{
+    loop {
+        // println!("len: {}", v.len());
+
+        if v.len() <= config.small_sort_threshold
+            && small_sort_configured(v, is_less, config.small_sort)
+        {
+            return;
+        }
+
+        // If too many bad pivot choices were made, simply fall back to heapsort in order to
+        // guarantee `O(n * log(n))` worst-case.
+        if limit == 0 {
+            observer.on_fallback();
+            FB::sort(v, is_less);
+            return;
+        }
+
+        limit -= 1;
+        observer.on_partition(limit);
+
+        // Choose a pivot and try guessing whether the slice is already sorted.
+        let pivot = choose_pivot(v, is_less, config);
+
+        // If the chosen pivot is equal to the predecessor, then it's the smallest element in the
+        // slice. Partition the slice into elements equal to and elements greater than the pivot.
+        // This case is usually hit when the slice contains many duplicate elements.
+        if let Some(p) = ancestor_pivot {
+            if !is_less(p, &v[pivot]) {
+                let mid = partition_equal(v, pivot, is_less);
+                observer.on_split(&v[..0], &v[..(mid + 1)], &v[(mid + 1)..]);
+
+                // Continue sorting elements greater than the pivot. We know that mid contains the
+                // pivot. So we can continue after mid.
+                v = &mut v[(mid + 1)..];
+                ancestor_pivot = None;
+                continue;
+            }
+        }
+
+        // Without an equal ancestor pivot, a slice of only equal elements would go through a full
+        // partition before `partition_equal` can catch it. This happens for example for the left
+        // side of low-cardinality inputs. For 1M random `i32` that costs 0.3% more comparisons and
+        // no measurable time, with 4 or 16 distinct values it saves ~7% of the comparisons.
+        if is_all_equal(v, pivot, is_less) {
+            return;
+        }
+
+        // Partition the slice.
+        let (mid, was_partitioned) =
+            partition_configured(v, pivot, is_less, config.fulcrum_enabled);
+
+        // Split the slice into `left`, `pivot`, and `right`. If `right` looks like it contains many
+        // elements equal to the pivot, also move those next to the pivot. This excludes them from
+        // further recursion right away, instead of waiting for a later partition to pick one of
+        // them as pivot and catch them with the ancestor pivot check.
+        let (left, right) = v.split_at_mut(mid);
+        let split_equal = has_many_equal_to_pivot(right, is_less);
+        let equal_len = if split_equal {
+            partition_equal(right, 0, is_less) + 1
+        } else {
+            1
+        };
+        observer.on_split(left, &right[..equal_len], &right[equal_len..]);
+        let (equal, right) = right.split_at_mut(equal_len);
+        let pivot = &equal[0];
+
+        // If all elements equal to the pivot were split off, `right` only contains elements greater
+        // than the pivot, and the ancestor pivot check for it would be a wasted comparison that
+        // always fails.
+        let right_ancestor_pivot = if split_equal { None } else { Some(pivot) };
+
+        // An already partitioned slice hints at presorted input. Sides that turn out to be
+        // presorted don't need any further work.
+        if was_partitioned {
+            match (
+                sort_if_presorted(left, is_less),
+                sort_if_presorted(right, is_less),
+            ) {
+                (true, true) => return,
+                (true, false) => {
+                    v = right;
+                    ancestor_pivot = right_ancestor_pivot;
+                    continue;
+                }
+                (false, true) => {
+                    v = left;
+                    continue;
+                }
+                (false, false) => {}
+            }
+        }
+
+        // Recurse into the shorter side only in order to minimize the total number of recursive
+        // calls and consume less stack space. Then just continue with the longer side (this is
+        // akin to tail recursion).
+        if left.len() < right.len() {
+            recurse_with_fallback::<T, F, FB, O>(
+                left,
+                is_less,
+                ancestor_pivot,
+                limit,
+                config,
+                observer,
+            );
+            v = right;
+            ancestor_pivot = right_ancestor_pivot;
+        } else {
+            recurse_with_fallback::<T, F, FB, O>(
+                right,
+                is_less,
+                right_ancestor_pivot,
+                limit,
+                config,
+                observer,
+            );
+            v = left;
+        }
+    }
+}
+
+/// Hooks into [`recurse_with_fallback`] to gather statistics, see [`sort_by_stats`]. The normal
+/// sort uses `()`, for which all hooks compile to nothing.
+trait RecurseObserver<T> {
+    /// Called once per loop iteration, with the limit remaining after it was decremented.
+    fn on_partition(&mut self, limit: u32);
+
+    /// Called before switching to the fallback.
+    fn on_fallback(&mut self);
+
+    /// Called after a sub-slice was split into `left`, `equal` and `right`, which are adjacent in
+    /// that order. `equal` starts with the pivot and is never empty.
+    #[inline(always)]
+    fn on_split(&mut self, _left: &[T], _equal: &[T], _right: &[T]) {}
+}
+
+impl<T> RecurseObserver<T> for () {
+    #[inline(always)]
+    fn on_partition(&mut self, _limit: u32) {}
+
+    #[inline(always)]
+    fn on_fallback(&mut self) {}
+}
+
+/// Sorts `v` recursively, like [`recurse`], but with median-of-medians pivots.
+///
+/// Elements equal to the pivot are split off after each partition, which keeps both remaining
+/// sides below ~70% of `v` even with many duplicates. `limit` is the maximum recursion depth
+/// before switching to `heapsort`, which can only be reached with an invalid total order.
+#[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
+fn recurse_guaranteed<T, F, O>(mut v: &mut [T], is_less: &mut F, mut limit: u32, observer: &mut O)
+where
+    F: FnMut(&T, &T) -> bool,
+    O: RecurseObserver<T>,
+{
+    loop {
+        if <T as UnstableSortTypeImpl>::small_sort(v, is_less) {
+            return;
+        }
+
+        if limit == 0 {
+            observer.on_fallback();
+            heapsort(v, is_less);
+            return;
+        }
+
+        limit -= 1;
+
+        let pivot = choose_pivot_bfprt(v, is_less);
+        let mid = partition(v, pivot, is_less);
+
+        // `right` starts with the pivot and only contains elements greater than or equal to it.
+        let (left, right) = v.split_at_mut(mid);
+        let equal_len = partition_equal(right, 0, is_less) + 1;
+        let right = &mut right[equal_len..];
+
+        if left.len() < right.len() {
+            recurse_guaranteed(left, is_less, limit, observer);
+            v = right;
+        } else {
+            recurse_guaranteed(right, is_less, limit, observer);
+            v = left;
+        }
+    }
+}
+
+/// Chooses a pivot in `v` with the median-of-medians (BFPRT) algorithm and returns its index.
+///
+/// The median of every group of 5 elements is moved to the front of `v`, and the true median of
+/// those medians is selected recursively. At least ~30% of `v` is less than or equal to the
+/// pivot, and at least ~30% is greater than or equal to it. Runs in *O*(*n*) time and reorders
+/// `v`.
+pub fn choose_pivot_bfprt<T, F>(v: &mut [T], is_less: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    const GROUP_LEN: usize = 5;
+
+    let len = v.len();
+
+    if len <= GROUP_LEN {
+        if len >= 2 {
+            insertion_sort_shift_left(v, 1, is_less);
+        }
+
+        return len / 2;
+    }
+
+    let group_count = len / GROUP_LEN;
+    for group_idx in 0..group_count {
+        let group_start = group_idx * GROUP_LEN;
+        insertion_sort_shift_left(&mut v[group_start..(group_start + GROUP_LEN)], 1, is_less);
+
+        // Only elements of already processed groups are moved out of their group.
+        v.swap(group_idx, group_start + GROUP_LEN / 2);
+    }
+
+    let median_idx = group_count / 2;
+    select_bfprt(&mut v[..group_count], median_idx, is_less);
+
+    median_idx
+}
+
+/// Reorders `v` so that the element at `index` is at its final sorted position, in worst-case
+/// *O*(*n*) time.
+///
+/// If `is_less` doesn't implement a total order, the selection stops after too many steps and
+/// leaves an arbitrary element at `index`.
+fn select_bfprt<T, F>(mut v: &mut [T], mut index: usize, is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
 {
+    // Same reasoning as the limit in `quicksort_guaranteed`.
+    let mut limit = 4 * (v.len() | 1).ilog2();
+
     loop {
-        // println!("len: {}", v.len());
+        if v.len() <= 5 {
+            if v.len() >= 2 {
+                insertion_sort_shift_left(v, 1, is_less);
+            }
 
-        if <T as UnstableSortTypeImpl>::small_sort(v, is_less) {
             return;
         }
 
-        // If too many bad pivot choices were made, simply fall back to heapsort in order to
-        // guarantee `O(n * log(n))` worst-case.
         if limit == 0 {
-            heapsort(v, is_less);
             return;
         }
 
         limit -= 1;
 
-        // Choose a pivot and try guessing whether the slice is already sorted.
-        let pivot = choose_pivot(v, is_less);
-
-        // If the chosen pivot is equal to the predecessor, then it's the smallest element in the
-        // slice. Partition the slice into elements equal to and elements greater than the pivot.
-        // This case is usually hit when the slice contains many duplicate elements.
-        if let Some(p) = ancestor_pivot {
-            if !is_less(p, &v[pivot]) {
-                let mid = partition_equal(v, pivot, is_less);
-
-                // Continue sorting elements greater than the pivot. We know that mid contains the
-                // pivot. So we can continue after mid.
-                v = &mut v[(mid + 1)..];
-                ancestor_pivot = None;
-                continue;
-            }
-        }
-
-        // Partition the slice.
+        let pivot = choose_pivot_bfprt(v, is_less);
         let mid = partition(v, pivot, is_less);
 
-        // Split the slice into `left`, `pivot`, and `right`.
-        let (left, right) = v.split_at_mut(mid);
-        let (pivot, right) = right.split_at_mut(1);
-        let pivot = &pivot[0];
+        if index < mid {
+            v = &mut v[..mid];
+            continue;
+        }
 
-        // Recurse into the shorter side only in order to minimize the total number of recursive
-        // calls and consume less stack space. Then just continue with the longer side (this is
-        // akin to tail recursion).
-        if left.len() < right.len() {
-            recurse(left, is_less, ancestor_pivot, limit);
-            v = right;
-            ancestor_pivot = Some(pivot);
-        } else {
-            recurse(right, is_less, Some(pivot), limit);
-            v = left;
+        let right = &mut v[mid..];
+        let equal_len = partition_equal(right, 0, is_less) + 1;
+        if index - mid < equal_len {
+            return;
         }
+
+        index -= mid + equal_len;
+        v = &mut right[equal_len..];
     }
 }
 
@@ -830,11 +4579,27 @@ trait UnstableSortTypeImpl: Sized {
     where
         F: FnMut(&Self, &Self) -> bool;
 
+    /// Same as `small_sort`, but uses `scratch` instead of setting up a stack scratch of its own.
+    fn small_sort_with_scratch<F>(
+        v: &mut [Self],
+        scratch: &mut MaybeUninit<[Self; SMALL_SORT_SCRATCH_LEN]>,
+        is_less: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool;
+
     /// Partitions `v` into elements smaller than `pivot`, followed by elements greater than or
     /// equal to `pivot`.
     ///
-    /// Returns the number of elements smaller than `pivot`.
-    fn partition<F>(v: &mut [Self], pivot: &Self, is_less: &mut F) -> usize
+    /// Returns the number of elements smaller than `pivot`. `fulcrum_enabled` allows fulcrum
+    /// partitioning for types that profit from it.
+    fn partition<F>(v: &mut [Self], pivot: &Self, is_less: &mut F, fulcrum_enabled: bool) -> usize
+    where
+        F: FnMut(&Self, &Self) -> bool;
+
+    /// Sorts `v` with a single sorting network, if there is one for exactly `v.len()` elements and
+    /// the type profits from it. Returns `false` without touching `v` otherwise.
+    fn sort_exact_network<F>(v: &mut [Self], is_less: &mut F) -> bool
     where
         F: FnMut(&Self, &Self) -> bool;
 }
@@ -859,12 +4624,35 @@ impl<T> UnstableSortTypeImpl for T {
         }
     }
 
-    default fn partition<F>(v: &mut [Self], pivot: &Self, is_less: &mut F) -> usize
+    default fn small_sort_with_scratch<F>(
+        v: &mut [Self],
+        _scratch: &mut MaybeUninit<[Self; SMALL_SORT_SCRATCH_LEN]>,
+        is_less: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        <T as UnstableSortTypeImpl>::small_sort(v, is_less)
+    }
+
+    default fn partition<F>(
+        v: &mut [Self],
+        pivot: &Self,
+        is_less: &mut F,
+        _fulcrum_enabled: bool,
+    ) -> usize
     where
         F: FnMut(&Self, &Self) -> bool,
     {
         partition_in_blocks(v, pivot, is_less)
     }
+
+    default fn sort_exact_network<F>(_v: &mut [Self], _is_less: &mut F) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        false
+    }
 }
 
 const PSEUDO_MEDIAN_REC_THRESHOLD: usize = 64;
@@ -874,32 +4662,45 @@ const PSEUDO_MEDIAN_REC_THRESHOLD: usize = 64;
 /// Idea taken from glidesort by Orson Peters.
 ///
 /// This chooses a pivot by sampling an adaptive amount of points, mimicking the median quality of
-/// median of square root.
-fn choose_pivot<T, F>(v: &[T], is_less: &mut F) -> usize
+/// median of square root. `config.pseudo_median_rec_threshold` replaces
+/// `PSEUDO_MEDIAN_REC_THRESHOLD` and must be at least `MIN_PSEUDO_MEDIAN_REC_THRESHOLD`.
+fn choose_pivot<T, F>(v: &[T], is_less: &mut F, config: RuntimeConfig) -> usize
 where
     F: FnMut(&T, &T) -> bool,
 {
     let len = v.len();
+    let rec_threshold = config.pseudo_median_rec_threshold;
 
     // It's a logic bug if this get's called on slice that would be small-sorted.
-    debug_assert!(len > max_len_small_sort::<T>());
+    debug_assert!(len > config.small_sort_max_len::<T>());
+    debug_assert!(rec_threshold >= MIN_PSEUDO_MEDIAN_REC_THRESHOLD);
+
+    // The gathered samples are sized for the compiled-in threshold, lower ones visit more samples.
+    if rec_threshold == PSEUDO_MEDIAN_REC_THRESHOLD {
+        if let Some(pivot) = <T as GatherPivotSamples>::median3_rec_gathered(v, is_less) {
+            return pivot;
+        }
+    }
 
     let len_div_2 = len / 2;
     let arr_ptr = v.as_ptr();
 
-    let median_guess_ptr = if len < PSEUDO_MEDIAN_REC_THRESHOLD {
+    let median_guess_ptr = if len < rec_threshold {
         // For small sizes it's crucial to pick a good median, just doing median3 is not great.
         let start = len_div_2 - 3;
         median7_approx(&v[start..(start + 7)], is_less)
     } else {
-        // SAFETY: TODO
+        let ([offset_a, offset_b, offset_c], section_len) = median3_rec_sections(len);
+
+        // SAFETY: The three sections of `section_len` elements each lie inside `v`, see
+        // `median3_rec_sections`. All offsets are below `len`, so the pointers stay in bounds and
+        // the byte offsets fit into `isize`, like every index into `v`.
         unsafe {
-            let len_div_8 = len / 8;
-            let a = arr_ptr;
-            let b = arr_ptr.add(len_div_8 * 4);
-            let c = arr_ptr.add(len_div_8 * 7);
+            let a = arr_ptr.add(offset_a);
+            let b = arr_ptr.add(offset_b);
+            let c = arr_ptr.add(offset_c);
 
-            median3_rec(a, b, c, len_div_8, is_less)
+            median3_rec(a, b, c, section_len, rec_threshold, is_less)
         }
     };
 
@@ -907,10 +4708,153 @@ where
     unsafe { median_guess_ptr.sub_ptr(arr_ptr) }
 }
 
+trait GatherPivotSamples: Sized {
+    /// Returns the same pivot as the `median3_rec` path of `choose_pivot`, or `None` if the type
+    /// or length has no gathered implementation.
+    fn median3_rec_gathered<F>(v: &[Self], is_less: &mut F) -> Option<usize>
+    where
+        F: FnMut(&Self, &Self) -> bool;
+}
+
+impl<T> GatherPivotSamples for T {
+    default fn median3_rec_gathered<F>(_v: &[Self], _is_less: &mut F) -> Option<usize>
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        None
+    }
+}
+
+macro_rules! gather_pivot_samples_impl {
+    ($($t:ty),*) => {
+        $(
+            #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+            impl GatherPivotSamples for $t {
+                #[inline(always)]
+                fn median3_rec_gathered<F>(v: &[Self], is_less: &mut F) -> Option<usize>
+                where
+                    F: FnMut(&Self, &Self) -> bool,
+                {
+                    median3_rec_gathered_4_byte(v, is_less)
+                }
+            }
+        )*
+    };
+}
+
+// `Wrapping` is `repr(transparent)` and compares like the inner integer. It isn't covered by the
+// generic dispatch like the `Freeze` impls, the list of types is explicit.
+gather_pivot_samples_impl!(
+    i32,
+    u32,
+    f32,
+    core::num::Wrapping<i32>,
+    core::num::Wrapping<u32>
+);
+
+/// Smallest slice for which the pivot samples are gathered, below that `median3_rec` only looks at
+/// a few dozen elements.
+const GATHER_PIVOT_MIN_LEN: usize = 4096;
+
+/// Maximum number of samples `median3_rec` looks at for slices shorter than 8^8 elements.
+const GATHER_PIVOT_MAX_SAMPLES: usize = 3usize.pow(7);
+
+/// Same as the `median3_rec` path of `choose_pivot`, but for 4 byte `Copy` types.
+///
+/// `median3_rec` interleaves the loads of its samples with the comparisons, and each sample is
+/// likely a cache miss for large slices. This first collects the sample indices in the order
+/// `median3_rec` visits them, loads all samples with SIMD gathers, and then runs the same median of
+/// 3 tournament on the copies, level by level. The comparisons are the same, so the chosen pivot
+/// is identical. Comparing copies is fine for `i32`, `u32`, `f32` and their `Wrapping` versions.
+#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+#[inline(never)]
+fn median3_rec_gathered_4_byte<T, F>(v: &[T], is_less: &mut F) -> Option<usize>
+where
+    T: Copy,
+    F: FnMut(&T, &T) -> bool,
+{
+    use core::arch::x86_64;
+
+    const LANES: usize = 8;
+
+    assert!(mem::size_of::<T>() == 4);
+
+    let len = v.len();
+    if len < GATHER_PIVOT_MIN_LEN || len >= 8usize.pow(8) {
+        return None;
+    }
+
+    // Expand the sample positions breadth-first. Every position turns into the 3 positions
+    // `median3_rec` would look at one level down, which keeps them in the order it visits them.
+    let mut indices = [0i32; GATHER_PIVOT_MAX_SAMPLES];
+    let ([_, offset_b, offset_c], mut n) = median3_rec_sections(len);
+    indices[..3].copy_from_slice(&[0, offset_b as i32, offset_c as i32]);
+    let mut count = 3;
+
+    while n * 8 >= PSEUDO_MEDIAN_REC_THRESHOLD {
+        let ([_, offset_b, offset_c], n8) = median3_rec_sections(n);
+        let (offset_b, offset_c) = (offset_b as i32, offset_c as i32);
+        for i in (0..count).rev() {
+            let a = indices[i];
+            indices[i * 3..(i * 3 + 3)].copy_from_slice(&[a, a + offset_b, a + offset_c]);
+        }
+
+        count *= 3;
+        n = n8;
+    }
+
+    let mut samples = [MaybeUninit::<T>::uninit(); GATHER_PIVOT_MAX_SAMPLES];
+    let arr_ptr = v.as_ptr();
+    let samples_ptr = samples.as_mut_ptr() as *mut T;
+
+    // SAFETY: All indices are below `len` and fit into i32, because `len < 8^8`. `count` is at
+    // most `GATHER_PIVOT_MAX_SAMPLES`, so all writes stay inside `samples`. `T` is 4 bytes, which
+    // is the gather scale.
+    unsafe {
+        let mut i = 0;
+        while i + LANES <= count {
+            let vindex =
+                x86_64::_mm256_loadu_si256(indices.as_ptr().add(i) as *const x86_64::__m256i);
+            let gathered = x86_64::_mm256_i32gather_epi32::<4>(arr_ptr as *const i32, vindex);
+            x86_64::_mm256_storeu_si256(samples_ptr.add(i) as *mut x86_64::__m256i, gathered);
+            i += LANES;
+        }
+
+        while i < count {
+            ptr::copy_nonoverlapping(arr_ptr.add(indices[i] as usize), samples_ptr.add(i), 1);
+            i += 1;
+        }
+    }
+
+    // Reduce groups of 3 to their median, like `median3_rec` does on the way back up.
+    while count > 1 {
+        for group in 0..(count / 3) {
+            let first = group * 3;
+            // SAFETY: The group is in bounds and initialized, `median3` returns one of its
+            // arguments.
+            let median_offset = unsafe {
+                let first_ptr = samples_ptr.add(first);
+                let median_ptr = median3(first_ptr, first_ptr.add(1), first_ptr.add(2), is_less);
+                median_ptr.sub_ptr(first_ptr)
+            };
+
+            // SAFETY: See above.
+            unsafe {
+                *samples_ptr.add(group) = *samples_ptr.add(first + median_offset);
+            }
+            indices[group] = indices[first + median_offset];
+        }
+
+        count /= 3;
+    }
+
+    Some(indices[0] as usize)
+}
+
 // Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
 // performance impact.
 #[inline(never)]
-fn median7_approx<T, F>(v: &[T], is_less: &mut F) -> *const T
+pub fn median7_approx<T, F>(v: &[T], is_less: &mut F) -> *const T
 where
     F: FnMut(&T, &T) -> bool,
 {
@@ -934,6 +4878,8 @@ where
 /// recursing we have logarithmic recursion depth and overall sample from
 /// f(n) = 3*f(n/8) -> f(n) = O(n^(log(3)/log(8))) ~= O(n^0.528) elements.
 ///
+/// Sections of fewer than `rec_threshold / 8` elements are not split further, see `choose_pivot`.
+///
 /// SAFETY: a, b, c must point to the start of initialized regions of memory of
 /// at least n elements.
 #[inline(never)]
@@ -942,23 +4888,63 @@ unsafe fn median3_rec<T, F>(
     mut b: *const T,
     mut c: *const T,
     n: usize,
+    rec_threshold: usize,
     is_less: &mut F,
 ) -> *const T
 where
     F: FnMut(&T, &T) -> bool,
 {
-    // SAFETY: TODO
+    // SAFETY: The caller guarantees that `a`, `b` and `c` each start `n` initialized elements.
+    // `median3_rec_sections` places the sections of the next level inside those `n` elements, so
+    // every offset is below `n` and the recursive calls get the same guarantee. `n` is at most an
+    // eighth of the slice length, so `n * 8` doesn't overflow.
     unsafe {
-        if n * 8 >= PSEUDO_MEDIAN_REC_THRESHOLD {
-            let n8 = n / 8;
-            a = median3_rec(a, a.add(n8 * 4), a.add(n8 * 7), n8, is_less);
-            b = median3_rec(b, b.add(n8 * 4), b.add(n8 * 7), n8, is_less);
-            c = median3_rec(c, c.add(n8 * 4), c.add(n8 * 7), n8, is_less);
+        if n * 8 >= rec_threshold {
+            let ([_, offset_b, offset_c], n8) = median3_rec_sections(n);
+            a = median3_rec(
+                a,
+                a.add(offset_b),
+                a.add(offset_c),
+                n8,
+                rec_threshold,
+                is_less,
+            );
+            b = median3_rec(
+                b,
+                b.add(offset_b),
+                b.add(offset_c),
+                n8,
+                rec_threshold,
+                is_less,
+            );
+            c = median3_rec(
+                c,
+                c.add(offset_b),
+                c.add(offset_c),
+                n8,
+                rec_threshold,
+                is_less,
+            );
         }
         median3(a, b, c, is_less)
     }
 }
 
+/// Splits `n` elements into the three sections `median3_rec` samples from, returned as their start
+/// offsets and their common length `n / 8`.
+///
+/// The sections start at `0`, `4 * (n / 8)` and `7 * (n / 8)`, so the last one ends at
+/// `8 * (n / 8) <= n`. Dividing before multiplying means no intermediate value exceeds `n`, which
+/// holds for every `n` up to `usize::MAX`, and so for the longest slice of any `T`.
+pub fn median3_rec_sections(n: usize) -> ([usize; 3], usize) {
+    let n8 = n / 8;
+    let offsets = [0, n8 * 4, n8 * 7];
+
+    debug_assert!(offsets[2] + n8 <= n);
+
+    (offsets, n8)
+}
+
 /// Calculates the median of 3 elements.
 ///
 /// SAFETY: a, b, c must be valid initialized elements.
@@ -992,7 +4978,7 @@ where
 }
 
 impl<T: Freeze> UnstableSortTypeImpl for T {
-    fn small_sort<F>(v: &mut [Self], is_less: &mut F) -> bool
+    default fn small_sort<F>(v: &mut [Self], is_less: &mut F) -> bool
     where
         F: FnMut(&Self, &Self) -> bool,
     {
@@ -1015,18 +5001,83 @@ impl<T: Freeze> UnstableSortTypeImpl for T {
         }
     }
 
-    fn partition<F>(v: &mut [Self], pivot: &Self, is_less: &mut F) -> usize
+    default fn small_sort_with_scratch<F>(
+        v: &mut [Self],
+        scratch: &mut MaybeUninit<[Self; SMALL_SORT_SCRATCH_LEN]>,
+        is_less: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        let len = v.len();
+
+        if intrinsics::likely(len <= max_len_small_sort::<T>()) {
+            let scratch_ptr = scratch.as_mut_ptr() as *mut T;
+
+            // SAFETY: scratch is valid for SMALL_SORT_SCRATCH_LEN writes, which is at least
+            // max_len_small_sort::<T>(), and doesn't alias v.
+            unsafe {
+                if const { has_efficient_in_place_swap::<T>() } {
+                    small_sort_network_impl(v, Some(scratch_ptr), is_less);
+                } else {
+                    small_sort_general_impl(v, Some(scratch_ptr), is_less);
+                }
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    default fn partition<F>(
+        v: &mut [Self],
+        pivot: &Self,
+        is_less: &mut F,
+        fulcrum_enabled: bool,
+    ) -> usize
     where
         F: FnMut(&Self, &Self) -> bool,
     {
         // The code-gen properties that make in-place swapping inefficient for types larger than u64
         // also apply to fulcrum partitioning.
-        if const { FULCRUM_ENABLED && has_efficient_in_place_swap::<T>() } {
+        if const { has_efficient_in_place_swap::<T>() }
+            && fulcrum_enabled
+            && v.len() >= FULCRUM_MIN_LEN
+        {
             fulcrum_partition(v, pivot, is_less)
+        } else if const { has_efficient_lomuto_partition::<T>() }
+            && mem::size_of_val(v) <= LOMUTO_MAX_BYTES
+        {
+            partition_lomuto_branchless(v, pivot, is_less)
         } else {
             partition_in_blocks(v, pivot, is_less)
         }
     }
+
+    #[inline(always)]
+    default fn sort_exact_network<F>(v: &mut [Self], is_less: &mut F) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        if const { !has_efficient_in_place_swap::<T>() } {
+            return false;
+        }
+
+        match v.len() {
+            4 => sort4_optimal(v, is_less),
+            5 => sort5_optimal(v, is_less),
+            6 => sort6_optimal(v, is_less),
+            7 => sort7_optimal(v, is_less),
+            8 => sort8_optimal(v, is_less),
+            9 => sort9_optimal(v, is_less),
+            10 => sort10_optimal(v, is_less),
+            14 => sort14_optimal(v, is_less),
+            _ => return false,
+        }
+
+        true
+    }
 }
 
 // --- Insertion sorts ---
@@ -1047,6 +5098,71 @@ impl<T> Drop for InsertionHole<T> {
     }
 }
 
+/// Inserts `v[0]` into pre-sorted sequence `v[1..]` so that whole `v[..]` becomes sorted.
+///
+/// This is the integral subroutine of insertion sort.
+unsafe fn insert_head<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    debug_assert!(v.len() >= 2);
+
+    let arr_ptr = v.as_mut_ptr();
+    let len = v.len();
+
+    // SAFETY: caller must ensure v is at least len 2.
+    unsafe {
+        // There are three ways to implement insertion here:
+        //
+        // 1. Swap adjacent elements until the first one gets to its final destination.
+        //    However, this way we copy data around more than is necessary. If elements are big
+        //    structures (costly to copy), this method will be slow.
+        //
+        // 2. Iterate until the right place for the first element is found. Then shift the
+        //    elements succeeding it to make room for it and finally place it into the
+        //    remaining hole. This is a good method.
+        //
+        // 3. Copy the first element into a temporary variable. Iterate until the right place
+        //    for it is found. As we go along, copy every traversed element into the slot
+        //    preceding it. Finally, copy data from the temporary variable into the remaining
+        //    hole. This method is very good. Benchmarks demonstrated slightly better
+        //    performance than with the 2nd method.
+        //
+        // All methods were benchmarked, and the 3rd showed best results. So we chose that one.
+
+        // It's important that we use arr_ptr here, for the same reason as in insert_tail.
+        if is_less(&*arr_ptr.add(1), &*arr_ptr) {
+            let tmp = mem::ManuallyDrop::new(ptr::read(arr_ptr));
+            // Intermediate state of the insertion process is always tracked by `hole`, which
+            // serves two purposes:
+            // 1. Protects integrity of `v` from panics in `is_less`.
+            // 2. Fills the remaining hole in `v` in the end.
+            //
+            // Panic safety:
+            //
+            // If `is_less` panics at any point during the process, `hole` will get dropped and
+            // fill the hole in `v` with `tmp`, thus ensuring that `v` still holds every object it
+            // initially held exactly once.
+            let mut hole = InsertionHole {
+                src: &*tmp,
+                dest: arr_ptr.add(1),
+            };
+            ptr::copy_nonoverlapping(hole.dest, arr_ptr, 1);
+
+            for j in 2..len {
+                let j_ptr = arr_ptr.add(j);
+                if !is_less(&*j_ptr, &*tmp) {
+                    break;
+                }
+
+                ptr::copy_nonoverlapping(j_ptr, hole.dest, 1);
+                hole.dest = j_ptr;
+            }
+            // `hole` gets dropped and thus copies `tmp` into the remaining hole in `v`.
+        }
+    }
+}
+
 /// Inserts `v[v.len() - 1]` into pre-sorted sequence `v[..v.len() - 1]` so that whole `v[..]`
 /// becomes sorted.
 unsafe fn insert_tail<T, F>(v: &mut [T], is_less: &mut F)
@@ -1055,6 +5171,11 @@ where
 {
     debug_assert!(v.len() >= 2);
 
+    // SAFETY: caller must ensure v is at least len 2.
+    if unsafe { <T as InsertTailWordCopy>::insert_tail_word_copy(v, is_less) } {
+        return;
+    }
+
     let arr_ptr = v.as_mut_ptr();
     let i = v.len() - 1;
 
@@ -1102,6 +5223,114 @@ where
     }
 }
 
+/// Same as [`insert_tail`] for `Copy` types of 4 or 8 bytes without interior mutability, e.g.
+/// `u32` and `u64`.
+///
+/// The generic version copies every element through memory, and the compiler does not always keep
+/// `tmp` in a register across the loop. Here the inserted value and the element it is compared with
+/// are locals, and the loop is unrolled by two. Sorting random `u64` and `u32` in chunks of 12 to 20
+/// elements was 12-18% faster, see the `sort_small_word` benchmark. Does the same comparisons in
+/// the same order as [`insert_tail`].
+trait InsertTailWordCopy: Sized {
+    /// Returns `false` without touching `v` if `Self` doesn't qualify.
+    ///
+    /// # Safety
+    ///
+    /// `v.len() >= 2`.
+    unsafe fn insert_tail_word_copy<F>(v: &mut [Self], is_less: &mut F) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool;
+}
+
+impl<T> InsertTailWordCopy for T {
+    default unsafe fn insert_tail_word_copy<F>(_v: &mut [Self], _is_less: &mut F) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        false
+    }
+}
+
+impl<T: Copy + Freeze> InsertTailWordCopy for T {
+    unsafe fn insert_tail_word_copy<F>(v: &mut [Self], is_less: &mut F) -> bool
+    where
+        F: FnMut(&Self, &Self) -> bool,
+    {
+        // Writes `value` into the hole at `dest`, when the insertion is done or if `is_less`
+        // panics. Every other slot of `v` holds one of the original elements exactly once.
+        struct CopyHole<T: Copy> {
+            value: T,
+            dest: *mut T,
+        }
+
+        impl<T: Copy> Drop for CopyHole<T> {
+            #[inline(always)]
+            fn drop(&mut self) {
+                // SAFETY: `dest` always points to the hole in `v`.
+                unsafe {
+                    ptr::write(self.dest, self.value);
+                }
+            }
+        }
+
+        if !matches!(mem::size_of::<T>(), 4 | 8) {
+            return false;
+        }
+
+        debug_assert!(v.len() >= 2);
+
+        let arr_ptr = v.as_mut_ptr();
+        let i = v.len() - 1;
+
+        // SAFETY: caller must ensure v is at least len 2. `j >= 1` on every access below `j`, so
+        // all accesses are in bounds.
+        unsafe {
+            let i_ptr = arr_ptr.add(i);
+            let prev = ptr::read(i_ptr.sub(1));
+            if !is_less(&*i_ptr, &prev) {
+                return true;
+            }
+
+            let mut hole = CopyHole {
+                value: ptr::read(i_ptr),
+                dest: i_ptr.sub(1),
+            };
+            ptr::write(i_ptr, prev);
+
+            // The hole is at `j`.
+            let mut j = i - 1;
+            while j >= 2 {
+                let a = ptr::read(arr_ptr.add(j - 1));
+                if !is_less(&hole.value, &a) {
+                    return true;
+                }
+                ptr::write(arr_ptr.add(j), a);
+                hole.dest = arr_ptr.add(j - 1);
+
+                let b = ptr::read(arr_ptr.add(j - 2));
+                if !is_less(&hole.value, &b) {
+                    return true;
+                }
+                ptr::write(arr_ptr.add(j - 1), b);
+                hole.dest = arr_ptr.add(j - 2);
+
+                j -= 2;
+            }
+
+            if j == 1 {
+                let a = ptr::read(arr_ptr);
+                if is_less(&hole.value, &a) {
+                    ptr::write(arr_ptr.add(1), a);
+                    hole.dest = arr_ptr;
+                }
+            }
+            // `hole` gets dropped and thus writes the inserted value into the remaining hole.
+        }
+
+        true
+    }
+}
+
 /// Sort `v` assuming `v[..offset]` is already sorted.
 fn insertion_sort_shift_left<T, F>(v: &mut [T], offset: usize, is_less: &mut F)
 where
@@ -1122,6 +5351,28 @@ where
     }
 }
 
+/// Sort `v` assuming `v[offset..]` is already sorted.
+///
+/// The mirror image of `insertion_sort_shift_left`, for presorted checks that find a sorted suffix.
+pub fn insertion_sort_shift_right<T, F>(v: &mut [T], offset: usize, is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+
+    // Using assert here improves performance.
+    assert!(offset < len);
+
+    // Shift each element of the unsorted region v[..offset] as far right as is needed to make v
+    // sorted.
+    for i in (0..offset).rev() {
+        // SAFETY: we tested that `offset < len`, so `v[i..]` is at least len 2.
+        unsafe {
+            insert_head(&mut v[i..], is_less);
+        }
+    }
+}
+
 #[inline(always)]
 unsafe fn merge_up<T, F>(
     mut src_left: *const T,
@@ -1270,6 +5521,12 @@ where
     }
 }
 
+// The stack scratch of the small-sorts is limited to this many bytes, see `small_sort_general`.
+const MAX_STACK_SCRATCH_BYTES: usize = 8 * 1024;
+
+// Length of a scratch that fits every small-sort, see `sort_each`.
+const SMALL_SORT_SCRATCH_LEN: usize = max_len_small_sort::<i32>();
+
 // Slices of up to this length get sorted using optimized sorting for small slices.
 const fn max_len_small_sort<T>() -> usize {
     if <T as IsFreeze>::value() && has_efficient_in_place_swap::<T>() {
@@ -1312,11 +5569,24 @@ impl<T: Freeze> const IsFreeze for T {
     }
 }
 
+/// `Duration`, `SystemTime` and similar two-field types like `(u64, u32)` are 12 to 16 bytes and
+/// don't qualify, they take the same paths as `String`. Forcing the sorting networks on `Duration`
+/// with [`SmallSort::Network`], or comparing it as a packed `u128` key, showed no consistent
+/// difference on random inputs of 10k and 1M elements, all within the run-to-run noise, see the
+/// `sort_same_paths` benchmark. The two-field comparison isn't what limits them.
 #[must_use]
 const fn has_efficient_in_place_swap<T>() -> bool {
     mem::size_of::<T>() <= mem::size_of::<u64>()
 }
 
+/// The Lomuto loop does one comparison and one swap per element, with the swap position carried
+/// from one iteration to the next. For `u8` that was slower than the block partition from around
+/// 100 elements on, so only types from `u32` up to `u64` qualify.
+#[must_use]
+const fn has_efficient_lomuto_partition<T>() -> bool {
+    has_efficient_in_place_swap::<T>() && mem::size_of::<T>() >= mem::size_of::<u32>()
+}
+
 #[test]
 fn type_info() {
     assert!(has_efficient_in_place_swap::<i32>());
@@ -1379,7 +5649,213 @@ where
 // Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
 // performance impact.
 #[inline(never)]
-fn sort10_optimal<T, F>(v: &mut [T], is_less: &mut F)
+pub fn sort4_optimal<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: caller must ensure v.len() >= 4.
+    assert!(v.len() == 4);
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // Optimal sorting network with 5 comparators, see:
+    // https://bertdobbelaere.github.io/sorting_networks.html.
+
+    // We checked the len.
+    unsafe {
+        swap_if_less(arr_ptr, 0, 2, is_less);
+        swap_if_less(arr_ptr, 1, 3, is_less);
+        swap_if_less(arr_ptr, 0, 1, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+    }
+}
+
+// Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
+// performance impact.
+#[inline(never)]
+pub fn sort5_optimal<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: caller must ensure v.len() >= 5.
+    assert!(v.len() == 5);
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // Optimal sorting network with 9 comparators, see:
+    // https://bertdobbelaere.github.io/sorting_networks.html.
+
+    // We checked the len.
+    unsafe {
+        swap_if_less(arr_ptr, 0, 3, is_less);
+        swap_if_less(arr_ptr, 1, 4, is_less);
+        swap_if_less(arr_ptr, 0, 2, is_less);
+        swap_if_less(arr_ptr, 1, 3, is_less);
+        swap_if_less(arr_ptr, 0, 1, is_less);
+        swap_if_less(arr_ptr, 2, 4, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+    }
+}
+
+// Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
+// performance impact.
+#[inline(never)]
+pub fn sort6_optimal<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: caller must ensure v.len() >= 6.
+    assert!(v.len() == 6);
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // Optimal sorting network with 12 comparators, see:
+    // https://bertdobbelaere.github.io/sorting_networks.html.
+
+    // We checked the len.
+    unsafe {
+        swap_if_less(arr_ptr, 0, 5, is_less);
+        swap_if_less(arr_ptr, 1, 3, is_less);
+        swap_if_less(arr_ptr, 2, 4, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+        swap_if_less(arr_ptr, 0, 3, is_less);
+        swap_if_less(arr_ptr, 2, 5, is_less);
+        swap_if_less(arr_ptr, 0, 1, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+        swap_if_less(arr_ptr, 4, 5, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+    }
+}
+
+// Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
+// performance impact.
+#[inline(never)]
+pub fn sort7_optimal<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: caller must ensure v.len() >= 7.
+    assert!(v.len() == 7);
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // Optimal sorting network with 16 comparators, see:
+    // https://bertdobbelaere.github.io/sorting_networks.html.
+
+    // We checked the len.
+    unsafe {
+        swap_if_less(arr_ptr, 0, 6, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+        swap_if_less(arr_ptr, 4, 5, is_less);
+        swap_if_less(arr_ptr, 0, 2, is_less);
+        swap_if_less(arr_ptr, 1, 4, is_less);
+        swap_if_less(arr_ptr, 3, 6, is_less);
+        swap_if_less(arr_ptr, 0, 1, is_less);
+        swap_if_less(arr_ptr, 2, 5, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 4, 6, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+        swap_if_less(arr_ptr, 4, 5, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+        swap_if_less(arr_ptr, 5, 6, is_less);
+    }
+}
+
+// Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
+// performance impact.
+#[inline(never)]
+pub fn sort8_optimal<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: caller must ensure v.len() >= 8.
+    assert!(v.len() == 8);
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // Optimal sorting network with 19 comparators, see:
+    // https://bertdobbelaere.github.io/sorting_networks.html.
+
+    // We checked the len.
+    unsafe {
+        swap_if_less(arr_ptr, 0, 2, is_less);
+        swap_if_less(arr_ptr, 1, 3, is_less);
+        swap_if_less(arr_ptr, 4, 6, is_less);
+        swap_if_less(arr_ptr, 5, 7, is_less);
+        swap_if_less(arr_ptr, 0, 4, is_less);
+        swap_if_less(arr_ptr, 1, 5, is_less);
+        swap_if_less(arr_ptr, 2, 6, is_less);
+        swap_if_less(arr_ptr, 3, 7, is_less);
+        swap_if_less(arr_ptr, 0, 1, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+        swap_if_less(arr_ptr, 4, 5, is_less);
+        swap_if_less(arr_ptr, 6, 7, is_less);
+        swap_if_less(arr_ptr, 2, 4, is_less);
+        swap_if_less(arr_ptr, 3, 5, is_less);
+        swap_if_less(arr_ptr, 1, 4, is_less);
+        swap_if_less(arr_ptr, 3, 6, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+        swap_if_less(arr_ptr, 5, 6, is_less);
+    }
+}
+
+// Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
+// performance impact.
+#[inline(never)]
+pub fn sort9_optimal<T, F>(v: &mut [T], is_less: &mut F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: caller must ensure v.len() >= 9.
+    assert!(v.len() == 9);
+
+    let arr_ptr = v.as_mut_ptr();
+
+    // Optimal sorting network with 25 comparators, see:
+    // https://bertdobbelaere.github.io/sorting_networks.html.
+
+    // We checked the len.
+    unsafe {
+        swap_if_less(arr_ptr, 0, 3, is_less);
+        swap_if_less(arr_ptr, 1, 7, is_less);
+        swap_if_less(arr_ptr, 2, 5, is_less);
+        swap_if_less(arr_ptr, 4, 8, is_less);
+        swap_if_less(arr_ptr, 0, 7, is_less);
+        swap_if_less(arr_ptr, 2, 4, is_less);
+        swap_if_less(arr_ptr, 3, 8, is_less);
+        swap_if_less(arr_ptr, 5, 6, is_less);
+        swap_if_less(arr_ptr, 0, 2, is_less);
+        swap_if_less(arr_ptr, 1, 3, is_less);
+        swap_if_less(arr_ptr, 4, 5, is_less);
+        swap_if_less(arr_ptr, 7, 8, is_less);
+        swap_if_less(arr_ptr, 1, 4, is_less);
+        swap_if_less(arr_ptr, 3, 6, is_less);
+        swap_if_less(arr_ptr, 5, 7, is_less);
+        swap_if_less(arr_ptr, 0, 1, is_less);
+        swap_if_less(arr_ptr, 2, 4, is_less);
+        swap_if_less(arr_ptr, 3, 5, is_less);
+        swap_if_less(arr_ptr, 6, 8, is_less);
+        swap_if_less(arr_ptr, 2, 3, is_less);
+        swap_if_less(arr_ptr, 4, 5, is_less);
+        swap_if_less(arr_ptr, 6, 7, is_less);
+        swap_if_less(arr_ptr, 1, 2, is_less);
+        swap_if_less(arr_ptr, 3, 4, is_less);
+        swap_if_less(arr_ptr, 5, 6, is_less);
+    }
+}
+
+// Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
+// performance impact.
+#[inline(never)]
+pub fn sort10_optimal<T, F>(v: &mut [T], is_less: &mut F)
 where
     F: FnMut(&T, &T) -> bool,
 {
@@ -1428,7 +5904,7 @@ where
 // Never inline this function to avoid code bloat. It still optimizes nicely and has practically no
 // performance impact.
 #[inline(never)]
-fn sort14_optimal<T, F>(v: &mut [T], is_less: &mut F)
+pub fn sort14_optimal<T, F>(v: &mut [T], is_less: &mut F)
 where
     F: FnMut(&T, &T) -> bool,
 {
@@ -1499,14 +5975,28 @@ where
 
 #[cfg_attr(feature = "no_inline_sub_functions", inline(never))]
 fn sort14_plus<T, F>(v: &mut [T], is_less: &mut F)
+where
+    T: Freeze,
+    F: FnMut(&T, &T) -> bool,
+{
+    let mut swap = MaybeUninit::<[T; SMALL_SORT_SCRATCH_LEN]>::uninit();
+
+    // SAFETY: swap is valid for SMALL_SORT_SCRATCH_LEN writes and doesn't alias v.
+    unsafe {
+        sort14_plus_with_scratch(v, swap.as_mut_ptr() as *mut T, is_less);
+    }
+}
+
+/// `swap_ptr` must be valid for `v.len()` writes and must not alias `v`.
+#[inline(always)]
+unsafe fn sort14_plus_with_scratch<T, F>(v: &mut [T], swap_ptr: *mut T, is_less: &mut F)
 where
     T: Freeze,
     F: FnMut(&T, &T) -> bool,
 {
     let len = v.len();
-    const MAX_BRANCHLESS_SMALL_SORT: usize = max_len_small_sort::<i32>();
 
-    assert!(len >= 14 && len <= MAX_BRANCHLESS_SMALL_SORT);
+    assert!(len >= 14 && len <= SMALL_SORT_SCRATCH_LEN);
 
     if len < 20 {
         sort14_optimal(&mut v[0..14], is_less);
@@ -1533,12 +6023,9 @@ where
     insertion_sort_shift_left(&mut v[0..len_div_2], mid, is_less);
     insertion_sort_shift_left(&mut v[len_div_2..], mid, is_less);
 
-    let mut swap = MaybeUninit::<[T; MAX_BRANCHLESS_SMALL_SORT]>::uninit();
-    let swap_ptr = swap.as_mut_ptr() as *mut T;
-
     // SAFETY: We checked that T is Freeze and thus observation safe.
     // Should is_less panic v was not modified in parity_merge and retains it's original input.
-    // swap and v must not alias and swap has v.len() space.
+    // The caller guarantees that swap and v don't alias and that swap has v.len() space.
     unsafe {
         bi_directional_merge_even(&mut v[..even_len], swap_ptr, is_less);
         ptr::copy_nonoverlapping(swap_ptr, v.as_mut_ptr(), even_len);
@@ -1553,6 +6040,19 @@ where
 }
 
 fn small_sort_network<T, F>(v: &mut [T], is_less: &mut F)
+where
+    T: Freeze,
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: Without scratch there is nothing to uphold.
+    unsafe {
+        small_sort_network_impl(v, None, is_less);
+    }
+}
+
+/// `scratch_ptr`, if any, must be valid for `v.len()` writes and must not alias `v`.
+#[inline(always)]
+unsafe fn small_sort_network_impl<T, F>(v: &mut [T], scratch_ptr: Option<*mut T>, is_less: &mut F)
 where
     T: Freeze,
     F: FnMut(&T, &T) -> bool,
@@ -1561,25 +6061,52 @@ where
 
     let len = v.len();
 
-    // Always sort assuming somewhat random distribution.
+    // Apart from fully descending inputs, always sort assuming somewhat random distribution.
     // Patterns should have already been found by the other analysis steps.
     //
     // Small total slices are handled separately, see function quicksort.
-    if len >= 14 {
-        sort14_plus(v, is_less);
-    } else if len >= 2 {
-        let end = if len >= 10 {
-            sort10_optimal(&mut v[0..10], is_less);
-            10
-        } else {
-            1
-        };
+    if len >= 2 && reverse_if_descending(v, is_less) {
+        return;
+    }
 
-        insertion_sort_shift_left(v, end, is_less);
+    // Exact lengths 4-9 go straight to an optimal network, which avoids the unpredictable branches
+    // of insertion sort for the sizes that the quicksort recursion hands out most often.
+    match len {
+        0 | 1 => {}
+        2 | 3 => insertion_sort_shift_left(v, 1, is_less),
+        4 => sort4_optimal(v, is_less),
+        5 => sort5_optimal(v, is_less),
+        6 => sort6_optimal(v, is_less),
+        7 => sort7_optimal(v, is_less),
+        8 => sort8_optimal(v, is_less),
+        9 => sort9_optimal(v, is_less),
+        10..=13 => {
+            sort10_optimal(&mut v[0..10], is_less);
+            insertion_sort_shift_left(v, 10, is_less);
+        }
+        _ => match scratch_ptr {
+            // SAFETY: See the function safety contract.
+            Some(scratch_ptr) => unsafe { sort14_plus_with_scratch(v, scratch_ptr, is_less) },
+            None => sort14_plus(v, is_less),
+        },
     }
 }
 
 fn small_sort_general<T, F>(v: &mut [T], is_less: &mut F)
+where
+    T: Freeze,
+    F: FnMut(&T, &T) -> bool,
+{
+    // SAFETY: Without scratch there is nothing to uphold.
+    unsafe {
+        small_sort_general_impl(v, None, is_less);
+    }
+}
+
+/// `scratch_ptr`, if any, must be valid for `min(v.len(), max_len_small_sort::<String>())` writes
+/// and must not alias `v`.
+#[inline(always)]
+unsafe fn small_sort_general_impl<T, F>(v: &mut [T], scratch_ptr: Option<*mut T>, is_less: &mut F)
 where
     T: Freeze,
     F: FnMut(&T, &T) -> bool,
@@ -1590,8 +6117,57 @@ where
 
     let len = v.len();
 
-    let mut scratch = MaybeUninit::<[T; MAX_SIZE]>::uninit();
-    let scratch_ptr = scratch.as_mut_ptr() as *mut T;
+    if len >= 2 && reverse_if_descending(v, is_less) {
+        return;
+    }
+
+    if len < 8 {
+        if len >= 2 {
+            insertion_sort_shift_left(v, 1, is_less);
+        }
+        return;
+    }
+
+    // The scratch is sized for the largest input, independent of len. For large types that would
+    // be a stack frame of tens of KiB, even for slices just above 8 elements, which can overflow
+    // small thread stacks. This sort promises not to allocate, so the scratch can't move to the
+    // heap. Above this budget only the scratch of `sort8_indirect` is kept and insertion sort
+    // continues from there, without the merge. For 1 KiB elements that measured the same as the
+    // merge, within noise, while plain insertion sort was ~15% slower for 1k elements. Types too
+    // large for even 8 elements use plain insertion sort. The budget is `MAX_STACK_SCRATCH_BYTES`.
+    if let Some(scratch_ptr) = scratch_ptr {
+        small_sort_general_with_scratch::<T, F, MAX_SIZE>(v, scratch_ptr, is_less);
+    } else if const { mem::size_of::<T>() * MAX_SIZE <= MAX_STACK_SCRATCH_BYTES } {
+        let mut scratch = MaybeUninit::<[T; MAX_SIZE]>::uninit();
+        let scratch_ptr = scratch.as_mut_ptr() as *mut T;
+        small_sort_general_with_scratch::<T, F, MAX_SIZE>(v, scratch_ptr, is_less);
+    } else if const { mem::size_of::<T>() * 8 <= MAX_STACK_SCRATCH_BYTES } {
+        let mut scratch = MaybeUninit::<[T; 8]>::uninit();
+        let scratch_ptr = scratch.as_mut_ptr() as *mut T;
+        // SAFETY: scratch is valid for 8 writes and v is at least 8 long.
+        unsafe {
+            sort8_indirect(v.as_mut_ptr(), scratch_ptr, is_less);
+        }
+
+        insertion_sort_shift_left(v, 8, is_less);
+    } else {
+        insertion_sort_shift_left(v, 1, is_less);
+    }
+}
+
+/// `v.len()` must be at least 8 and `scratch_ptr` must be valid for `min(v.len(), MAX_SIZE)`
+/// writes.
+#[inline(always)]
+fn small_sort_general_with_scratch<T, F, const MAX_SIZE: usize>(
+    v: &mut [T],
+    scratch_ptr: *mut T,
+    is_less: &mut F,
+) where
+    T: Freeze,
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+    debug_assert!(len >= 8);
 
     if len >= 16 && len <= MAX_SIZE {
         let even_len = len - (len % 2);
@@ -1622,20 +6198,43 @@ where
                 insert_tail(v, is_less);
             }
         }
-    } else if len >= 2 {
-        let offset = if len >= 8 {
-            // SAFETY: scratch_ptr is valid and has enough space.
-            unsafe {
-                sort8_indirect(v.as_mut_ptr(), scratch_ptr, is_less);
-            }
+    } else {
+        // SAFETY: scratch_ptr is valid and has enough space.
+        unsafe {
+            sort8_indirect(v.as_mut_ptr(), scratch_ptr, is_less);
+        }
 
-            8
-        } else {
-            1
-        };
+        insertion_sort_shift_left(v, 8, is_less);
+    }
+}
+
+/// Reverses `v` if it is fully descending and returns `true` in that case.
+///
+/// The scan only starts if the last element is less than the first one, and stops at the first
+/// ascending pair, so for random inputs this costs one to three comparisons. For random `i32`
+/// arrays of 8 to 20 elements sorted with [`sort_array`] that was within ~3% of the small-sort
+/// without the check, and random inputs of 1k and 100k elements do ~0.5-1% more comparisons over
+/// all their small-sorts. Descending `i32` arrays of 16 to 32 elements sorted ~3-5x faster. `v`
+/// must have at least two elements.
+fn reverse_if_descending<T, F>(v: &mut [T], is_less: &mut F) -> bool
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
 
-        insertion_sort_shift_left(v, offset, is_less);
+    if !is_less(&v[len - 1], &v[0]) {
+        return false;
     }
+
+    // A not strictly descending input can be reversed as well, the order of equal elements is
+    // allowed to change.
+    if !v.windows(2).all(|w| !is_less(&w[0], &w[1])) {
+        return false;
+    }
+
+    v.reverse();
+
+    true
 }
 
 /// SAFETY: The caller MUST guarantee that `arr_ptr` is valid for 4 reads and `dest_ptr` is valid
@@ -1,3 +1,9 @@
+//! Binary-size optimized unstable sort from the tiny_sort crate.
+//!
+//! tiny_sort is fully generic, every type and comparison function accepted by the slice sort
+//! methods is supported. Unlike the FFI and radix sort modules, there is no per-type dispatch that
+//! could panic, and no fallback is needed.
+
 use std::cmp::Ordering;
 
 use tiny_sort;
@@ -1,5 +1,5290 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+
 use sort_test_tools::instantiate_sort_tests;
+use sort_test_tools::patterns;
+
+use sort_comp::unstable::rust_ipnsort;
 
 type TestSort = sort_comp::unstable::rust_ipnsort::SortImpl;
 
 instantiate_sort_tests!(TestSort);
+
+/// Returns the result of sorting `v` with `sort_fn` and the number of comparisons it took.
+fn sort_count_comparisons<T: Clone>(
+    v: &[T],
+    sort_fn: impl FnOnce(&mut [T], &mut dyn FnMut(&T, &T) -> Ordering),
+    mut compare: impl FnMut(&T, &T) -> Ordering,
+) -> (Vec<T>, u64) {
+    let count = Cell::new(0u64);
+    let mut result = v.to_vec();
+
+    sort_fn(&mut result, &mut |a, b| {
+        count.set(count.get() + 1);
+        compare(a, b)
+    });
+
+    (result, count.get())
+}
+
+thread_local! {
+    /// Makes every allocation on the current thread fail, to test the error paths of the `try_`
+    /// functions. Nothing else may allocate on the thread while it is set.
+    static FAIL_ALLOCATIONS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The system allocator, except that allocations fail on threads that set `FAIL_ALLOCATIONS`.
+struct FailingAllocator;
+
+unsafe impl std::alloc::GlobalAlloc for FailingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        if FAIL_ALLOCATIONS
+            .try_with(|fail| fail.get())
+            .unwrap_or(false)
+        {
+            return std::ptr::null_mut();
+        }
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: FailingAllocator = FailingAllocator;
+
+/// Deterministic Fisher-Yates shuffle, so that tests asserting comparison counts are reproducible.
+fn shuffle_with_seed<T>(v: &mut [T], seed: u64) {
+    let mut state = seed | 1;
+    for i in (1..v.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        v.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+#[test]
+fn pivot_hint_reduces_comparisons() {
+    // Skewed with respect to pivot sampling, `choose_pivot` only samples the regions at 0/8, 4/8
+    // and 7/8 of the slice. Place the smallest values there, so the sampled pivot is bad.
+    let len = 100_000;
+    let region_len = len / 8;
+    let is_sampled = |i: usize| [0, 4, 7].contains(&(i / region_len));
+
+    let mut small_vals = (0..(3 * region_len) as i32).collect::<Vec<_>>();
+    let mut large_vals = ((3 * region_len) as i32..len as i32).collect::<Vec<_>>();
+    shuffle_with_seed(&mut small_vals, 123);
+    shuffle_with_seed(&mut large_vals, 456);
+
+    let (mut small_iter, mut large_iter) = (small_vals.into_iter(), large_vals.into_iter());
+    let v = (0..len)
+        .map(|i| {
+            if is_sampled(i) {
+                small_iter.next().unwrap()
+            } else {
+                large_iter.next().unwrap()
+            }
+        })
+        .collect::<Vec<i32>>();
+
+    let median_idx = v.iter().position(|val| *val == (len / 2) as i32).unwrap();
+
+    let (without_hint, without_hint_count) =
+        sort_count_comparisons(&v, |v, compare| rust_ipnsort::sort_by(v, compare), i32::cmp);
+    let (with_hint, with_hint_count) = sort_count_comparisons(
+        &v,
+        |v, compare| {
+            rust_ipnsort::sort_by_with_hint(v, compare, rust_ipnsort::PivotHint(median_idx))
+        },
+        i32::cmp,
+    );
+
+    let expected = (0..len as i32).collect::<Vec<_>>();
+    assert_eq!(without_hint, expected);
+    assert_eq!(with_hint, expected);
+    assert!(with_hint_count < without_hint_count);
+}
+
+#[test]
+fn pivot_hint_out_of_bounds_is_ignored() {
+    for len in [0, 1, 2, 20, 21, 50, 1_000] {
+        let v = patterns::random(len);
+
+        let mut expected = v.clone();
+        expected.sort();
+
+        for hint in [len, len + 1, usize::MAX] {
+            let mut result = v.clone();
+            rust_ipnsort::sort_by_with_hint(&mut result, i32::cmp, rust_ipnsort::PivotHint(hint));
+            assert_eq!(result, expected);
+        }
+
+        for hint in 0..len.min(50) {
+            let mut result = v.clone();
+            rust_ipnsort::sort_by_with_hint(&mut result, i32::cmp, rust_ipnsort::PivotHint(hint));
+            assert_eq!(result, expected);
+        }
+    }
+}
+
+#[test]
+fn all_equal_with_outliers() {
+    for len in [21, 50, 100, 1_000, 10_000] {
+        let outlier_positions = [0, 1, len / 8, len / 2, len - 2, len - 1];
+
+        for outlier_pos in outlier_positions {
+            for outlier in [i32::MIN, -1, 1, i32::MAX] {
+                let mut v = vec![0; len];
+                v[outlier_pos] = outlier;
+
+                let mut expected = v.clone();
+                expected.sort();
+
+                rust_ipnsort::sort(&mut v);
+                assert_eq!(v, expected);
+            }
+        }
+
+        // Low-cardinality input, where sub-slices end up all-equal.
+        let mut v = patterns::random_uniform(len, 0..=2);
+        let mut expected = v.clone();
+        expected.sort();
+
+        rust_ipnsort::sort(&mut v);
+        assert_eq!(v, expected);
+    }
+}
+
+#[test]
+fn low_cardinality() {
+    for len in [21, 50, 100, 1_000, 10_000, 100_000] {
+        for distinct in [2, 3, 10, 100] {
+            let mut v = patterns::random_uniform(len, 0..distinct);
+            let mut v_str = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+
+            let mut expected = v.clone();
+            expected.sort();
+            let mut expected_str = v_str.clone();
+            expected_str.sort();
+
+            rust_ipnsort::sort(&mut v);
+            assert_eq!(v, expected);
+
+            rust_ipnsort::sort(&mut v_str);
+            assert_eq!(v_str, expected_str);
+        }
+    }
+}
+
+#[test]
+fn presorted_prefix_with_unsorted_rest() {
+    for len in [21, 50, 100, 1_000, 10_000] {
+        for sorted_percent in [50.0, 70.0, 75.0, 90.0, 99.0] {
+            for reversed in [false, true] {
+                let mut v = patterns::random_sorted(len, sorted_percent);
+                if reversed {
+                    let sorted_len = ((len as f64) * (sorted_percent / 100.0)).round() as usize;
+                    v[..sorted_len].reverse();
+                }
+
+                let mut expected = v.clone();
+                expected.sort();
+
+                let mut v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+                let mut expected_str = v_str.clone();
+                expected_str.sort();
+
+                rust_ipnsort::sort(&mut v);
+                assert_eq!(v, expected);
+
+                rust_ipnsort::sort(&mut v_str);
+                assert_eq!(v_str, expected_str);
+            }
+        }
+    }
+}
+
+#[test]
+fn few_presorted_runs() {
+    for len in [21, 50, 100, 1_000, 10_000] {
+        let moved_block = {
+            let mut v = patterns::ascending(len);
+            v[(len / 4)..((len * 3) / 4)].rotate_left(len / 10);
+            v
+        };
+
+        let three_runs = {
+            let mut v = patterns::random(len);
+            v[..len / 3].sort();
+            v[len / 3..(len * 2) / 3].sort_by(|a, b| b.cmp(a));
+            v[(len * 2) / 3..].sort();
+            v
+        };
+
+        // Four runs are only merged if they are long enough.
+        let four_runs = {
+            let mut v = patterns::random_uniform(len, 0..=(len as i32 / 8));
+            for chunk in v.chunks_mut(len / 4) {
+                chunk.sort();
+            }
+            v
+        };
+
+        for v in [moved_block, three_runs, four_runs] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+            let mut expected_str = v_str.clone();
+            expected_str.sort();
+
+            let mut v = v;
+            rust_ipnsort::sort(&mut v);
+            assert_eq!(v, expected);
+
+            rust_ipnsort::sort(&mut v_str);
+            assert_eq!(v_str, expected_str);
+        }
+    }
+
+    // A sorted slice with one moved block should be handled with a linear number of comparisons.
+    let len = 100_000;
+    let mut v = patterns::ascending(len);
+    v[(len / 4)..((len * 3) / 4)].rotate_left(len / 10);
+
+    let (result, comparisons) =
+        sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+    assert_eq!(result, patterns::ascending(len));
+    assert!(comparisons < 2 * len as u64, "{comparisons}");
+}
+
+#[test]
+fn already_partitioned_skips_sorted_side() {
+    // Too many runs for the presorted checks at the start, but every partition finds the slice
+    // already partitioned, and the side without swaps is sorted and skipped.
+    let len = 100_000;
+    for swap_stride in [50, 4_999] {
+        let mut v = patterns::ascending(len);
+        for i in 0..20 {
+            v.swap(i * swap_stride, i * swap_stride + 1);
+        }
+
+        let (result, comparisons) =
+            sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+        assert_eq!(result, patterns::ascending(len));
+        assert!(comparisons < 10 * len as u64, "{comparisons}");
+    }
+
+    for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+        let mut expected = v.clone();
+        expected.sort();
+
+        let mut result = v;
+        rust_ipnsort::sort(&mut result);
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn low_cardinality_comparisons() {
+    // Once the elements equal to a pivot are split off, the larger side never contains elements
+    // equal to it, so the ancestor pivot check is skipped there. The number of comparisons should
+    // grow with the number of distinct values, not with log(len).
+    let len = 100_000;
+    for (cardinality, max_comparisons_per_elem) in [(2, 2.1), (4, 3.4), (16, 5.1)] {
+        let mut v = (0..len).map(|i| i % cardinality).collect::<Vec<i32>>();
+        shuffle_with_seed(&mut v, 0x5EED);
+
+        let mut expected = v.clone();
+        expected.sort();
+
+        let (result, comparisons) =
+            sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+        assert_eq!(result, expected);
+        assert!(
+            (comparisons as f64) < max_comparisons_per_elem * len as f64,
+            "cardinality {cardinality}: {comparisons}"
+        );
+    }
+}
+
+type NetworkFn = fn(&mut [u8], &mut dyn FnMut(&u8, &u8) -> bool);
+
+/// Calls `network` with a counting comparator on every 0/1 input of length `N`, and checks that
+/// each call sorts the input and takes exactly `expected_comparisons` comparisons. By the 0/1
+/// principle, a comparator network that sorts all 0/1 inputs sorts all inputs.
+fn check_network_exhaustive<const N: usize>(network: NetworkFn, expected_comparisons: usize) {
+    for bits in 0..(1u32 << N) {
+        let mut v = (0..N).map(|i| ((bits >> i) & 1) as u8).collect::<Vec<_>>();
+        let mut expected = v.clone();
+        expected.sort();
+
+        let mut comparisons = 0;
+        network(&mut v, &mut |a, b| {
+            comparisons += 1;
+            a < b
+        });
+
+        assert_eq!(v, expected);
+        assert_eq!(comparisons, expected_comparisons);
+    }
+}
+
+#[test]
+fn sorting_network_comparisons() {
+    check_network_exhaustive::<10>(
+        |v, is_less| rust_ipnsort::sort10_optimal(v, &mut |a, b| is_less(a, b)),
+        29,
+    );
+    // The smallest known network for 14 inputs has 51 comparators, this one has 52.
+    check_network_exhaustive::<14>(
+        |v, is_less| rust_ipnsort::sort14_optimal(v, &mut |a, b| is_less(a, b)),
+        52,
+    );
+    check_network_exhaustive::<4>(
+        |v, is_less| {
+            let mut dest = [0; 4];
+            // SAFETY: `v` and `dest` both hold 4 elements.
+            unsafe {
+                rust_ipnsort::sort4_indirect(v.as_ptr(), dest.as_mut_ptr(), &mut |a, b| {
+                    is_less(a, b)
+                })
+            };
+            v.copy_from_slice(&dest);
+        },
+        5,
+    );
+    check_network_exhaustive::<4>(
+        |v, is_less| rust_ipnsort::sort4_optimal(v, &mut |a, b| is_less(a, b)),
+        5,
+    );
+    check_network_exhaustive::<5>(
+        |v, is_less| rust_ipnsort::sort5_optimal(v, &mut |a, b| is_less(a, b)),
+        9,
+    );
+    check_network_exhaustive::<6>(
+        |v, is_less| rust_ipnsort::sort6_optimal(v, &mut |a, b| is_less(a, b)),
+        12,
+    );
+    check_network_exhaustive::<7>(
+        |v, is_less| rust_ipnsort::sort7_optimal(v, &mut |a, b| is_less(a, b)),
+        16,
+    );
+    check_network_exhaustive::<8>(
+        |v, is_less| rust_ipnsort::sort8_optimal(v, &mut |a, b| is_less(a, b)),
+        19,
+    );
+    check_network_exhaustive::<9>(
+        |v, is_less| rust_ipnsort::sort9_optimal(v, &mut |a, b| is_less(a, b)),
+        25,
+    );
+}
+
+#[test]
+fn median7_approx_comparisons() {
+    // `median7_approx` is the median of three `median3` calls, each of which takes 2 or 3
+    // comparisons. Check all permutations of 7 distinct elements.
+    let mut v = [0, 1, 2, 3, 4, 5, 6];
+    let mut permutations = vec![v];
+    // Heap's algorithm.
+    let mut c = [0; 7];
+    let mut i = 1;
+    while i < v.len() {
+        if c[i] < i {
+            v.swap(if i % 2 == 0 { 0 } else { c[i] }, i);
+            permutations.push(v);
+            c[i] += 1;
+            i = 1;
+        } else {
+            c[i] = 0;
+            i += 1;
+        }
+    }
+    assert_eq!(permutations.len(), 5040);
+
+    let (mut min_comparisons, mut max_comparisons) = (usize::MAX, 0);
+    for v in permutations {
+        let mut comparisons = 0;
+        let median_ptr = rust_ipnsort::median7_approx(&v, &mut |a: &i32, b: &i32| {
+            comparisons += 1;
+            a < b
+        });
+
+        // SAFETY: `median7_approx` returns a pointer into `v`.
+        let median_idx = unsafe { median_ptr.offset_from(v.as_ptr()) } as usize;
+        assert!((2..=4).contains(&v[median_idx]), "{v:?}");
+
+        min_comparisons = min_comparisons.min(comparisons);
+        max_comparisons = max_comparisons.max(comparisons);
+    }
+
+    assert_eq!((min_comparisons, max_comparisons), (6, 9));
+}
+
+#[test]
+fn median3_rec_sections_in_bounds() {
+    use rust_ipnsort::median3_rec_sections;
+
+    // The longest slices of 1, 2 and 3 byte types, and lengths around powers of 8. The sections
+    // depend only on the length, so this needs no allocation.
+    let mut lens = vec![
+        usize::MAX,
+        isize::MAX as usize,
+        isize::MAX as usize / 2,
+        isize::MAX as usize / 3,
+    ];
+    for exp in 1..usize::BITS / 3 {
+        let pow = 8usize.pow(exp);
+        lens.extend([pow - 1, pow, pow + 1, pow * 7 + 7]);
+    }
+
+    for len in lens {
+        // Follow each of the three sections down to the last level, the offsets within a section
+        // don't depend on where it starts.
+        for path in 0..3 {
+            let (mut start, mut n) = (0usize, len);
+            // Same as `n * 8 >= 64` in `median3_rec`, without overflowing for the longest lengths.
+            while n >= 8 {
+                let (offsets, section_len) = median3_rec_sections(n);
+                assert_eq!(section_len, n / 8);
+                assert!(offsets.windows(2).all(|w| w[0] + section_len <= w[1]));
+                assert!(offsets[2].checked_add(section_len).unwrap() <= n);
+
+                start = start.checked_add(offsets[path]).unwrap();
+                n = section_len;
+                assert!(start.checked_add(n).unwrap() <= len, "len: {len}");
+            }
+        }
+    }
+}
+
+#[test]
+fn rotation_merge_correct() {
+    for len in [0usize, 1, 2, 3, 10, 100, 1_000] {
+        for mid in [0, 1, len / 4, len / 2, len.saturating_sub(1), len] {
+            let mid = mid.min(len);
+            let mut v = patterns::random_uniform(len, 0..=(len as i32 / 4));
+            v[..mid].sort();
+            v[mid..].sort();
+
+            let mut expected = v.clone();
+            expected.sort();
+
+            rust_ipnsort::rotation_merge(&mut v, mid, &mut |a, b| a < b);
+            assert_eq!(v, expected);
+        }
+    }
+}
+
+#[test]
+fn rotation_merge_panic_safe() {
+    let len = 1_000;
+    let mut v = patterns::random(len)
+        .iter()
+        .map(|val| val.to_string())
+        .collect::<Vec<_>>();
+    v[..len / 2].sort();
+    v[len / 2..].sort();
+
+    let mut expected = v.clone();
+    expected.sort();
+
+    for panic_after in [0, 1, 5, 20, 100] {
+        let mut result = v.clone();
+        let mut count = 0;
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::rotation_merge(&mut result, len / 2, &mut |a, b| {
+                if count == panic_after {
+                    panic!("comparison panic");
+                }
+                count += 1;
+                a < b
+            });
+        }));
+
+        // Every element must still be present exactly once, whether the merge finished or not.
+        if res.is_err() {
+            result.sort();
+        }
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn sort_boxed_slices() {
+    for len in [0, 1, 2, 20, 50, 1_000] {
+        let mut v = patterns::random_uniform(len, 0..=32)
+            .into_iter()
+            .zip(patterns::random(len))
+            .map(|(box_len, val)| {
+                (0..box_len)
+                    .map(|i| (val as u32 >> (i % 32)) as u8 % 4)
+                    .collect::<Box<[u8]>>()
+            })
+            .collect::<Vec<_>>();
+
+        let compare = |a: &[u8], b: &[u8]| a.len().cmp(&b.len()).then_with(|| a.cmp(b));
+
+        let mut expected = v.clone();
+        expected.sort_by(|a, b| compare(a, b));
+
+        rust_ipnsort::sort_boxed_by(&mut v, compare);
+        assert_eq!(v, expected);
+    }
+}
+
+/// Sorts `len` elements with McIlroy's "A Killer Adversary for Quicksort" comparator, which
+/// decides the order of elements lazily to make the chosen pivots as bad as possible. Returns the
+/// number of comparisons and checks that the result is sorted.
+fn antiqsort_count_comparisons(
+    len: usize,
+    sort_fn: impl FnOnce(&mut [usize], &mut dyn FnMut(&usize, &usize) -> Ordering),
+) -> u64 {
+    // Fix the first elements as three short descending runs. Otherwise the lazily decided order
+    // turns into long presorted runs, which the run detection in front of the quicksort catches.
+    antiqsort_count_comparisons_with_prefix(len, &[1, 0, 3, 2, 5, 4], sort_fn)
+}
+
+/// Same as [`antiqsort_count_comparisons`], but the order of the first elements is fixed up front.
+/// `prefix` must be a permutation of `0..prefix.len()`, all other elements are greater.
+fn antiqsort_count_comparisons_with_prefix(
+    len: usize,
+    prefix: &[usize],
+    sort_fn: impl FnOnce(&mut [usize], &mut dyn FnMut(&usize, &usize) -> Ordering),
+) -> u64 {
+    let gas = len;
+    let mut values = vec![gas; len];
+    values[..prefix.len()].copy_from_slice(prefix);
+    let values = std::cell::RefCell::new(values);
+    let next_solid = Cell::new(prefix.len());
+    let candidate = Cell::new(0);
+
+    let v = (0..len).collect::<Vec<_>>();
+    let (result, count) = sort_count_comparisons(&v, sort_fn, |&a: &usize, &b: &usize| {
+        let mut values = values.borrow_mut();
+
+        if values[a] == gas && values[b] == gas {
+            let frozen = if a == candidate.get() { a } else { b };
+            values[frozen] = next_solid.get();
+            next_solid.set(next_solid.get() + 1);
+        }
+
+        if values[a] == gas {
+            candidate.set(a);
+        } else if values[b] == gas {
+            candidate.set(b);
+        }
+
+        values[a].cmp(&values[b])
+    });
+
+    let values = values.into_inner();
+    assert!(result.windows(2).all(|w| values[w[0]] <= values[w[1]]));
+
+    count
+}
+
+#[test]
+fn guaranteed_pivot_adversarial() {
+    for len in [100, 1_000, 10_000, 100_000] {
+        // A presorted first half is merged with the rest by `sort_by`, after sorting the rest with
+        // the regular pivot choice.
+        let presorted_half = (0..len / 2).collect::<Vec<_>>();
+
+        for prefix in [&[1, 0, 3, 2, 5, 4][..], &presorted_half] {
+            let mut fallbacks = 0;
+            let count = antiqsort_count_comparisons_with_prefix(len, prefix, |v, compare| {
+                rust_ipnsort::sort_by_guaranteed_on_fallback(v, compare, || fallbacks += 1)
+            });
+
+            // Every partition is balanced, no matter how the adversary decides comparisons, so
+            // the recursion limit is never reached.
+            assert_eq!(fallbacks, 0);
+
+            let n_log_n = len as f64 * (len as f64).log2();
+            assert!((count as f64) < 4.0 * n_log_n);
+        }
+    }
+}
+
+#[test]
+fn fallback_strategies_adversarial() {
+    use rust_ipnsort::{Heapsort, MedianOfMedians};
+
+    for len in [1_000, 10_000, 100_000] {
+        let default = antiqsort_count_comparisons(len, |v, compare| {
+            rust_ipnsort::sort_by(v, compare);
+        });
+        let heapsort = antiqsort_count_comparisons(len, |v, compare| {
+            rust_ipnsort::sort_by_with_fallback::<_, _, Heapsort>(v, compare);
+        });
+        let median_of_medians = antiqsort_count_comparisons(len, |v, compare| {
+            rust_ipnsort::sort_by_with_fallback::<_, _, MedianOfMedians>(v, compare);
+        });
+
+        // The default fallback must not change behavior.
+        assert_eq!(heapsort, default);
+
+        // The adversary exhausts the imbalance limit, so the fallbacks do most of the work.
+        let n_log_n = len as f64 * (len as f64).log2();
+        assert!(heapsort as f64 > 2.0 * n_log_n, "{heapsort}");
+        assert!((heapsort as f64) < 6.0 * n_log_n, "{heapsort}");
+        assert!(
+            (median_of_medians as f64) < 6.0 * n_log_n,
+            "{median_of_medians}"
+        );
+    }
+}
+
+#[test]
+fn sort_deterministic_call_order() {
+    type SortFn = fn(&mut [i32], &mut dyn FnMut(&i32, &i32) -> Ordering);
+
+    // Records every call into external state, which makes the result depend on the call order.
+    fn record_calls(v: &[i32], sort_fn: SortFn) -> Vec<(i32, i32)> {
+        let mut calls = Vec::new();
+        let mut result = v.to_vec();
+        sort_fn(&mut result, &mut |a, b| {
+            calls.push((*a, *b));
+            a.cmp(b)
+        });
+        assert!(result.windows(2).all(|w| w[0] <= w[1]));
+
+        calls
+    }
+
+    let sort_deterministic: SortFn = |v, compare| rust_ipnsort::sort_deterministic(v, compare);
+    let sort_by: SortFn = |v, compare| rust_ipnsort::sort_by(v, compare);
+
+    for len in [0, 1, 2, 20, 1_000, 100_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::random_sorted(len, 95.0),
+        ] {
+            let expected = record_calls(&v, sort_deterministic);
+            assert_eq!(record_calls(&v, sort_deterministic), expected);
+            assert_eq!(record_calls(&v, sort_by), expected);
+
+            // The research overrides are thread-local, change them on a separate thread.
+            let (calls, calls_sort_by) = std::thread::scope(|s| {
+                s.spawn(|| {
+                    rust_ipnsort::set_small_sort_threshold(8);
+                    rust_ipnsort::set_fulcrum_enabled(false);
+                    (
+                        record_calls(&v, sort_deterministic),
+                        record_calls(&v, sort_by),
+                    )
+                })
+                .join()
+                .unwrap()
+            });
+            assert_eq!(calls, expected);
+            // With few distinct values, the partitions end in runs of equal elements before the
+            // small-sort threshold matters.
+            if len >= 1_000 && v.iter().any(|&val| val > 16) {
+                assert_ne!(calls_sort_by, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn sort_by_tuned_default_matches_sort() {
+    let record_calls = |v: &[i32], tuned: Option<rust_ipnsort::TuneParams>| {
+        let mut calls = Vec::new();
+        let mut result = v.to_vec();
+        let compare = |a: &i32, b: &i32| {
+            calls.push((*a, *b));
+            a.cmp(b)
+        };
+        match tuned {
+            Some(params) => rust_ipnsort::sort_by_tuned(&mut result, compare, params),
+            None => rust_ipnsort::sort_deterministic(&mut result, compare),
+        }
+        assert!(result.windows(2).all(|w| w[0] <= w[1]));
+
+        calls
+    };
+
+    let params = rust_ipnsort::TuneParams::default();
+    assert_eq!(params.small_sort_max, usize::MAX);
+    assert_eq!(params.insertion_always_max, 20);
+    assert_eq!(params.median_of_medians_min, 64);
+
+    for len in [0, 1, 2, 20, 21, 1_000, 100_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::random_sorted(len, 95.0),
+        ] {
+            let expected = record_calls(&v, None);
+            assert_eq!(record_calls(&v, Some(params)), expected);
+
+            // Each parameter is used. With few distinct values, the partitions end in runs of
+            // equal elements before the small-sort threshold matters.
+            if len >= 1_000 {
+                let small_sort_tuned = rust_ipnsort::TuneParams {
+                    small_sort_max: 8,
+                    ..params
+                };
+                if v.iter().any(|&val| val > 16) {
+                    assert_ne!(record_calls(&v, Some(small_sort_tuned)), expected);
+                }
+
+                let median_tuned = rust_ipnsort::TuneParams {
+                    median_of_medians_min: 8,
+                    ..params
+                };
+                assert_ne!(record_calls(&v, Some(median_tuned)), expected);
+            }
+        }
+    }
+
+    let v = patterns::random(30);
+    let tuned = rust_ipnsort::TuneParams {
+        insertion_always_max: 30,
+        ..params
+    };
+    assert_ne!(record_calls(&v, Some(tuned)), record_calls(&v, None));
+}
+
+#[test]
+fn sort_by_tuned_clamps_params() {
+    let values = [0, 1, 2, 7, 8, 9, 21, 64, 1_000, usize::MAX];
+
+    for len in [0, 1, 2, 9, 20, 21, 36, 37, 100, 10_000] {
+        let v = patterns::random(len);
+        let mut expected = v.clone();
+        expected.sort();
+
+        let strings = v.iter().map(|val| format!("{val:011}")).collect::<Vec<_>>();
+        let mut expected_strings = strings.clone();
+        expected_strings.sort();
+
+        for small_sort_max in values {
+            for median_of_medians_min in values {
+                // Large values make the sort quadratic, keep them to the small inputs.
+                for insertion_always_max in values.into_iter().filter(|&val| val <= len.max(64)) {
+                    let params = rust_ipnsort::TuneParams {
+                        small_sort_max,
+                        insertion_always_max,
+                        median_of_medians_min,
+                    };
+
+                    let mut result = v.clone();
+                    rust_ipnsort::sort_by_tuned(&mut result, |a, b| a.cmp(b), params);
+                    assert_eq!(result, expected, "{params:?}");
+
+                    let mut result_strings = strings.clone();
+                    rust_ipnsort::sort_by_tuned(&mut result_strings, |a, b| a.cmp(b), params);
+                    assert_eq!(result_strings, expected_strings, "{params:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn sort_by_with_small_sort_strategies() {
+    use rust_ipnsort::SmallSort;
+
+    let strategies = [
+        SmallSort::Insertion,
+        SmallSort::Network,
+        SmallSort::IndirectMerge,
+        SmallSort::Auto,
+    ];
+
+    for len in [0, 1, 2, 20, 50, 1_000, 100_000] {
+        for (is_random, v) in [
+            (true, patterns::random(len)),
+            (false, patterns::random_uniform(len, 0..=16)),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            let strings = v.iter().map(|val| format!("{val:011}")).collect::<Vec<_>>();
+            let mut expected_strings = strings.clone();
+            expected_strings.sort();
+
+            let mut comparison_counts = Vec::new();
+            for strategy in strategies {
+                let (result, comparisons) = sort_count_comparisons(
+                    &v,
+                    |v, cmp| rust_ipnsort::sort_by_with_small_sort(v, cmp, strategy),
+                    i32::cmp,
+                );
+                assert_eq!(result, expected, "{strategy:?}");
+                comparison_counts.push(comparisons);
+
+                let mut result_strings = strings.clone();
+                rust_ipnsort::sort_by_with_small_sort(
+                    &mut result_strings,
+                    |a, b| a.cmp(b),
+                    strategy,
+                );
+                assert_eq!(result_strings, expected_strings, "{strategy:?}");
+            }
+
+            // `Auto` is `Network` for i32, and the other strategies take effect. Low cardinality
+            // inputs are mostly handled by the equal element partitions instead.
+            assert_eq!(comparison_counts[1], comparison_counts[3]);
+            if is_random && len >= 1_000 {
+                assert_ne!(comparison_counts[0], comparison_counts[3]);
+                assert_ne!(comparison_counts[2], comparison_counts[3]);
+            }
+        }
+    }
+}
+
+#[test]
+fn move_counted_fulcrum_vs_block_partition() {
+    for len in [0, 1, 2, 20, 1_000, 100_000] {
+        let v = patterns::random(len);
+        let mut expected = v.clone();
+        expected.sort();
+
+        // The fulcrum override is thread-local, compare on separate threads.
+        let (v, expected) = (&v, &expected);
+        let (fulcrum_moves, block_moves) = std::thread::scope(|s| {
+            let sort_on_thread = |fulcrum_enabled| {
+                s.spawn(move || {
+                    rust_ipnsort::set_fulcrum_enabled(fulcrum_enabled);
+                    let mut result = v.clone();
+                    let moves = rust_ipnsort::sort_by_move_counted(&mut result, |a, b| a.cmp(b));
+                    assert_eq!(&result, expected);
+                    moves
+                })
+                .join()
+                .unwrap()
+            };
+
+            (sort_on_thread(true), sort_on_thread(false))
+        });
+
+        if len >= 1_000 {
+            // Fulcrum partitioning rotates every element through the swap buffer, the block
+            // partition only swaps the out-of-order ones. Measured ~1.4-1.6x.
+            assert!(block_moves < fulcrum_moves, "{block_moves} {fulcrum_moves}");
+            assert!(
+                fulcrum_moves < 2 * block_moves,
+                "{block_moves} {fulcrum_moves}"
+            );
+        }
+    }
+}
+
+#[test]
+fn sort_and_count_inversions_brute_force() {
+    fn brute_force(v: &[i32]) -> u64 {
+        let mut inversions = 0;
+        for i in 0..v.len() {
+            for j in (i + 1)..v.len() {
+                inversions += (v[i] > v[j]) as u64;
+            }
+        }
+        inversions
+    }
+
+    for len in (0..=70).chain([100, 500, 1_000]) {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=3),
+            patterns::ascending(len),
+            patterns::descending(len),
+        ] {
+            let mut result = v.clone();
+            let inversions = rust_ipnsort::sort_and_count_inversions(&mut result);
+
+            let mut expected = v.clone();
+            expected.sort();
+            assert_eq!(result, expected);
+            assert_eq!(inversions, brute_force(&v));
+        }
+    }
+
+    let mut v = patterns::descending(100_000);
+    assert_eq!(
+        rust_ipnsort::sort_and_count_inversions(&mut v),
+        100_000 * 99_999 / 2
+    );
+}
+
+#[test]
+fn describe_dispatch_types() {
+    let i32_info = rust_ipnsort::describe_dispatch::<i32>();
+    assert!(i32_info.uses_network);
+    // Fulcrum partitioning is disabled by default.
+    assert!(!i32_info.uses_fulcrum_eligible);
+    assert!(i32_info.uses_lomuto);
+    assert!(i32_info.efficient_swap);
+    assert_eq!(i32_info.small_sort_max, 36);
+
+    let string_info = rust_ipnsort::describe_dispatch::<String>();
+    assert!(!string_info.uses_network);
+    assert!(!string_info.uses_fulcrum_eligible);
+    assert!(!string_info.uses_lomuto);
+    assert!(!string_info.efficient_swap);
+    assert_eq!(string_info.small_sort_max, 20);
+
+    // Small enough, but the interior mutability rules out the networks.
+    let cell_info = rust_ipnsort::describe_dispatch::<Cell<u32>>();
+    assert!(!cell_info.uses_network);
+    assert!(!cell_info.uses_lomuto);
+    assert!(cell_info.efficient_swap);
+    assert_eq!(cell_info.small_sort_max, 20);
+
+    // The Lomuto loop only pays off from `u32` to `u64`.
+    assert!(rust_ipnsort::describe_dispatch::<u64>().uses_lomuto);
+    assert!(!rust_ipnsort::describe_dispatch::<u8>().uses_lomuto);
+    assert!(!rust_ipnsort::describe_dispatch::<u128>().uses_lomuto);
+
+    // The niche of `char` doesn't matter, it takes the same paths as `u32`.
+    assert_eq!(
+        rust_ipnsort::describe_dispatch::<char>(),
+        rust_ipnsort::describe_dispatch::<u32>()
+    );
+
+    // Same for the `NonZero*` integers.
+    assert_eq!(
+        rust_ipnsort::describe_dispatch::<std::num::NonZeroU64>(),
+        rust_ipnsort::describe_dispatch::<u64>()
+    );
+    assert_eq!(
+        rust_ipnsort::describe_dispatch::<std::num::NonZeroU128>(),
+        rust_ipnsort::describe_dispatch::<u128>()
+    );
+
+    // 16 bytes, so it takes the same paths as `String`.
+    assert_eq!(
+        rust_ipnsort::describe_dispatch::<std::time::Duration>(),
+        string_info
+    );
+
+    // `Wrapping` and other `repr(transparent)` newtypes without interior mutability take the same
+    // paths as the inner type.
+    assert_eq!(
+        rust_ipnsort::describe_dispatch::<std::num::Wrapping<u64>>(),
+        rust_ipnsort::describe_dispatch::<u64>()
+    );
+    assert_eq!(
+        rust_ipnsort::describe_dispatch::<std::num::Wrapping<i32>>(),
+        i32_info
+    );
+    assert!(!rust_ipnsort::describe_dispatch::<Cell<u64>>().uses_network);
+}
+
+#[test]
+fn sort_stats_adversarial_fallbacks() {
+    for len in [1_000, 10_000, 100_000] {
+        let v = patterns::random(len);
+        let mut expected = v.clone();
+        expected.sort();
+
+        let mut result = v.clone();
+        let random_stats = rust_ipnsort::sort_by_stats(&mut result, |a, b| a.cmp(b));
+        assert_eq!(result, expected);
+        assert_eq!(random_stats.heapsort_fallbacks, 0);
+        assert!(random_stats.total_partitions > 0);
+        assert!(random_stats.max_recursion_depth <= 2 * len.ilog2());
+
+        let (_, comparisons) =
+            sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+        assert_eq!(random_stats.comparisons, comparisons);
+
+        let mut adversarial_stats = None;
+        let adversarial_comparisons = antiqsort_count_comparisons(len, |v, compare| {
+            adversarial_stats = Some(rust_ipnsort::sort_by_stats(v, compare));
+        });
+        let adversarial_stats = adversarial_stats.unwrap();
+        assert!(adversarial_stats.heapsort_fallbacks > 0);
+        assert_eq!(adversarial_stats.comparisons, adversarial_comparisons);
+        assert!(adversarial_stats.max_recursion_depth > random_stats.max_recursion_depth);
+    }
+}
+
+#[test]
+fn sort_by_on_fallback_adversarial() {
+    for len in [0, 1, 20, 1_000, 10_000, 100_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::saw_mixed(len, (len / 20).min(8)),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut fallbacks = 0;
+            let mut result = v.clone();
+            rust_ipnsort::sort_by_on_fallback(&mut result, |a, b| a.cmp(b), || fallbacks += 1);
+            assert_eq!(result, expected);
+            assert_eq!(fallbacks, 0);
+        }
+
+        if len < 1_000 {
+            continue;
+        }
+
+        // Same number of fallbacks as reported by `sort_by_stats` for the same killer input.
+        let mut fallbacks = 0;
+        let mut stats = None;
+        antiqsort_count_comparisons(len, |v, compare| {
+            rust_ipnsort::sort_by_on_fallback(v, compare, || fallbacks += 1);
+        });
+        antiqsort_count_comparisons(len, |v, compare| {
+            stats = Some(rust_ipnsort::sort_by_stats(v, compare));
+        });
+        assert!(fallbacks > 0);
+        assert_eq!(fallbacks, stats.unwrap().heapsort_fallbacks);
+    }
+}
+
+#[test]
+fn sort_stats_medium_no_fallbacks() {
+    // Slices just past the always-insertion-sort length have the smallest limits, but need only
+    // a few partitions for non-adversarial inputs.
+    for len in (21..=300).chain([1_000, 5_000]) {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=(len as i32 / 4)),
+            patterns::saw_mixed(len, (len / 20).min(8)),
+        ] {
+            let mut result = v.clone();
+            let stats = rust_ipnsort::sort_by_stats(&mut result, |a, b| a.cmp(b));
+            assert_eq!(stats.heapsort_fallbacks, 0, "len: {len}");
+            assert!(result.windows(2).all(|w| w[0] <= w[1]));
+        }
+
+        // The adversary still runs into heapsort, which keeps the worst case in check.
+        let count = antiqsort_count_comparisons(len, |v, compare| {
+            rust_ipnsort::sort_by(v, compare);
+        });
+        let n_log_n = len as f64 * (len as f64).log2();
+        assert!((count as f64) < 6.0 * n_log_n, "len: {len} count: {count}");
+    }
+}
+
+#[test]
+fn sort_external_many_runs() {
+    for len in [0, 1, 2, 20, 1_000, 10_000] {
+        for chunk_len in [1, 2, 3, 7, 64, 1_000] {
+            let v = patterns::random(len);
+            let mut expected = v.clone();
+            expected.sort();
+
+            let chunks = v.chunks(chunk_len).map(|chunk| chunk.to_vec());
+            let mut result = Vec::new();
+            rust_ipnsort::sort_external(chunks, &mut result, |run| run);
+            assert_eq!(result, expected);
+        }
+    }
+
+    // Empty chunks and runs of very different lengths.
+    let chunks = vec![
+        vec![],
+        patterns::random(1_000),
+        vec![],
+        vec![5],
+        patterns::random(3),
+    ];
+    let mut expected = chunks.concat();
+    expected.sort_by(|a, b| b.cmp(a));
+
+    let mut result = Vec::new();
+    rust_ipnsort::sort_external_by(chunks, &mut result, |run| run, |a, b| b.cmp(a));
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn sort_external_spills_runs() {
+    let v = patterns::random(10_000);
+    let mut expected = v.clone();
+    expected.sort();
+
+    // Stores every run as bytes and drops the elements, reading them back only during the merge.
+    let mut spilled_len = 0;
+    let spill = |run: Vec<i32>| {
+        assert!(run.windows(2).all(|pair| pair[0] <= pair[1]));
+        spilled_len += run.len();
+
+        let bytes = run
+            .iter()
+            .flat_map(|val| val.to_le_bytes())
+            .collect::<Vec<u8>>();
+        (0..bytes.len() / 4)
+            .map(move |i| i32::from_le_bytes(bytes[i * 4..][..4].try_into().unwrap()))
+    };
+
+    let mut result = Vec::new();
+    rust_ipnsort::sort_external(
+        v.chunks(777).map(|chunk| chunk.to_vec()),
+        &mut result,
+        spill,
+    );
+    assert_eq!(result, expected);
+    assert_eq!(spilled_len, v.len());
+}
+
+#[test]
+fn merge_runs_lazily() {
+    let runs = (0..10)
+        .map(|run_idx| (0..100).map(move |i| (i * 10 + run_idx).to_string()))
+        .collect::<Vec<_>>();
+
+    let mut expected = runs.iter().cloned().flatten().collect::<Vec<_>>();
+    expected.sort_by_key(|val| val.parse::<i32>().unwrap());
+
+    let mut result = Vec::new();
+    rust_ipnsort::merge_runs_by(runs, &mut result, |a, b| {
+        a.parse::<i32>().unwrap().cmp(&b.parse::<i32>().unwrap())
+    });
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn sort_pairs_by_key_matches_sort_by() {
+    fn check<K: Ord + Copy + std::fmt::Debug, V: Ord + Copy + std::fmt::Debug>(mut v: Vec<(K, V)>) {
+        let mut expected = v.clone();
+        expected.sort_by_key(|pair| pair.0);
+
+        rust_ipnsort::sort_pairs_by_key(&mut v);
+
+        // Pairs with equal keys may be reordered, so only the keys have to match exactly.
+        let keys = |v: &[(K, V)]| v.iter().map(|pair| pair.0).collect::<Vec<_>>();
+        assert_eq!(keys(&v), keys(&expected));
+
+        v.sort();
+        expected.sort();
+        assert_eq!(v, expected);
+    }
+
+    for len in [0, 1, 2, 20, 21, 36, 37, 50, 1_000, 10_000] {
+        let v = patterns::random(len);
+        let v_dup = patterns::random_uniform(len, 0..=16);
+
+        check(v.iter().map(|&val| (val as u32, [val as u8; 28])).collect());
+        check(v_dup.iter().map(|&val| (val as u8, val as u64)).collect());
+        check(
+            v_dup
+                .iter()
+                .zip(&v)
+                .map(|(&key, &val)| ([key as u64; 3], val.to_le_bytes()))
+                .collect(),
+        );
+    }
+}
+
+#[test]
+fn sort_mixed_direction_matches_sort() {
+    use std::cmp::Reverse;
+
+    fn check<K1: Ord + Clone + std::fmt::Debug, K2: Ord + Clone + std::fmt::Debug>(
+        mut v: Vec<(Reverse<K1>, K2)>,
+    ) {
+        let mut expected = v.clone();
+        expected.sort();
+
+        rust_ipnsort::sort_mixed_direction(&mut v);
+        assert_eq!(v, expected);
+    }
+
+    for len in [0, 1, 2, 20, 21, 50, 1_000, 10_000] {
+        let v = patterns::random(len);
+        // Ties on the primary key, and the full range of both keys including the extremes.
+        let v_dup = patterns::random_uniform(len, 0..=16);
+        let v_ext = patterns::random_uniform(len, -1..=1)
+            .into_iter()
+            .map(|val| val.saturating_mul(i32::MAX))
+            .collect::<Vec<_>>();
+
+        for (primary, secondary) in [(&v_dup, &v), (&v, &v_dup), (&v_ext, &v), (&v_dup, &v_ext)] {
+            let pairs = || primary.iter().copied().zip(secondary.iter().copied());
+
+            check(
+                pairs()
+                    .map(|(a, b)| (Reverse(a as u32), b as u32))
+                    .collect(),
+            );
+            check(pairs().map(|(a, b)| (Reverse(a), b)).collect());
+            check(pairs().map(|(a, b)| (Reverse(a as i8), b as u16)).collect());
+            check(
+                pairs()
+                    .map(|(a, b)| (Reverse(a as u16), b as i16))
+                    .collect(),
+            );
+            // Not combined into one key.
+            check(
+                pairs()
+                    .map(|(a, b)| (Reverse(a as u64), b as u32))
+                    .collect(),
+            );
+            check(pairs().map(|(a, b)| (Reverse(a.to_string()), b)).collect());
+        }
+    }
+}
+
+#[cfg(feature = "comparison_trace")]
+#[test]
+fn record_and_replay() {
+    for len in [0, 1, 2, 20, 50, 1_000, 10_000] {
+        let v = patterns::random(len);
+
+        let mut recorded = v.clone();
+        let trace = rust_ipnsort::sort_by_recorded(&mut recorded, i32::cmp);
+
+        let mut replayed = v.clone();
+        rust_ipnsort::replay(&trace, &mut replayed);
+        assert_eq!(replayed, recorded);
+
+        // Comparison results that don't form a total order, as produced while fuzzing.
+        let random_results = patterns::random_uniform(len * 32 + 1, 0..=2);
+        let mut random_results_iter = random_results.iter().cycle();
+
+        let mut recorded = v.clone();
+        let trace = rust_ipnsort::sort_by_recorded(&mut recorded, |_, _| {
+            [Ordering::Less, Ordering::Equal, Ordering::Greater]
+                [*random_results_iter.next().unwrap() as usize]
+        });
+
+        // The sort may detect the Ord violation and panic, replaying has to do the same.
+        let mut replayed = v.clone();
+        let replay_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::replay(&trace, &mut replayed);
+        }));
+        assert_eq!(replay_result.is_err(), trace.panicked);
+        assert_eq!(replayed, recorded);
+    }
+}
+
+#[test]
+fn sort_from_matrix_relations() {
+    fn check<const N: usize>() {
+        // Consistent, the order of the values.
+        let values = patterns::random_uniform(N, 0..=(N as i32 / 2));
+        let less_matrix: [[bool; N]; N] =
+            std::array::from_fn(|a| std::array::from_fn(|b| values[a] < values[b]));
+
+        let mut perm: [u32; N] = std::array::from_fn(|i| i as u32);
+        rust_ipnsort::sort_from_matrix(&mut perm, &less_matrix);
+        assert!(perm
+            .windows(2)
+            .all(|w| values[w[0] as usize] <= values[w[1] as usize]));
+        assert_permutation(&perm);
+
+        // Cyclic, every index is less than the next few, wrapping around. Also random relations,
+        // which are neither transitive nor antisymmetric.
+        let cyclic: [[bool; N]; N] =
+            std::array::from_fn(|a| std::array::from_fn(|b| (b + N - a) % N <= N / 2 && a != b));
+        let random_bits = patterns::random(N * N);
+        let random: [[bool; N]; N] =
+            std::array::from_fn(|a| std::array::from_fn(|b| random_bits[a * N + b] % 2 == 0));
+
+        for less_matrix in [cyclic, random] {
+            let mut perm: [u32; N] = std::array::from_fn(|i| (N - 1 - i) as u32);
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                rust_ipnsort::sort_from_matrix(&mut perm, &less_matrix);
+            }));
+            assert_permutation(&perm);
+        }
+    }
+
+    fn assert_permutation(perm: &[u32]) {
+        let mut sorted = perm.to_vec();
+        sorted.sort();
+        assert!(sorted.iter().enumerate().all(|(i, &val)| i as u32 == val));
+    }
+
+    check::<0>();
+    check::<1>();
+    check::<2>();
+    check::<3>();
+    check::<8>();
+    check::<17>();
+    check::<20>();
+    check::<32>();
+    check::<36>();
+    check::<37>();
+    check::<100>();
+}
+
+#[test]
+fn sort_array_sizes() {
+    fn check<const N: usize>() {
+        let v = patterns::random(N);
+        let mut expected = v.clone();
+        expected.sort();
+
+        let arr: [i32; N] = v.clone().try_into().unwrap();
+        assert_eq!(
+            rust_ipnsort::sort_array(arr).as_slice(),
+            expected.as_slice()
+        );
+
+        let v_str = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+        let mut expected_str = v_str.clone();
+        expected_str.sort();
+
+        let arr: [String; N] = v_str.try_into().unwrap();
+        assert_eq!(
+            rust_ipnsort::sort_array(arr).as_slice(),
+            expected_str.as_slice()
+        );
+    }
+
+    check::<0>();
+    check::<1>();
+    check::<2>();
+    check::<8>();
+    check::<20>();
+    check::<36>();
+    check::<37>();
+    check::<100>();
+
+    assert_eq!(rust_ipnsort::sort_array([(); 3]), [(); 3]);
+}
+
+#[test]
+fn small_sort_near_descending() {
+    fn check<const N: usize>() {
+        let descending = patterns::descending(N);
+        let mut inputs = vec![descending.clone()];
+
+        // Descending with duplicates, which may be reversed.
+        inputs.push(descending.iter().map(|val| val / 2).collect());
+
+        // Almost descending, these must not be reversed.
+        for i in 0..N - 1 {
+            let mut v = descending.clone();
+            v.swap(i, i + 1);
+            inputs.push(v);
+        }
+        let mut v = descending.clone();
+        v[N / 2] = i32::MAX;
+        inputs.push(v);
+        let mut v = descending.clone();
+        v[1..].rotate_left(1);
+        inputs.push(v);
+
+        for v in inputs {
+            let mut expected = v.clone();
+            expected.sort();
+
+            let result = rust_ipnsort::sort_array::<_, N>(v.clone().try_into().unwrap());
+            assert_eq!(result.as_slice(), expected, "{v:?}");
+
+            let v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+            let mut expected_str = v_str.clone();
+            expected_str.sort();
+
+            let result_str = rust_ipnsort::sort_array::<_, N>(v_str.try_into().unwrap());
+            assert_eq!(result_str.as_slice(), expected_str);
+        }
+    }
+
+    check::<2>();
+    check::<3>();
+    check::<9>();
+    check::<10>();
+    check::<16>();
+    check::<20>();
+    check::<32>();
+}
+
+#[test]
+fn sort_by_cached_key_correct() {
+    for len in [0, 1, 2, 20, 255, 256, 1_000, 70_000] {
+        let v = patterns::random(len);
+
+        let mut expected = v.clone();
+        expected.sort_by_cached_key(|val| val.to_string());
+
+        let mut result = v.clone();
+        let mut key_calls = 0;
+        rust_ipnsort::sort_by_cached_key(&mut result, |val| {
+            key_calls += 1;
+            val.to_string()
+        });
+
+        assert_eq!(result, expected);
+        assert_eq!(key_calls, if len < 2 { 0 } else { len });
+    }
+}
+
+#[test]
+fn sort_heap_matches_sort() {
+    for len in [0, 1, 2, 20, 1_000, 100_000] {
+        for v in [
+            patterns::random(len),
+            patterns::ascending(len),
+            patterns::descending(len),
+            patterns::random_uniform(len, 0..=4),
+        ] {
+            let mut expected = v.clone();
+            rust_ipnsort::sort(&mut expected);
+
+            let mut result = v.clone();
+            rust_ipnsort::sort_heap(&mut result);
+            assert_eq!(result, expected);
+
+            // The comparison count doesn't depend on the pattern beyond this bound.
+            let (result, comparisons) =
+                sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_heap_by(v, cmp), i32::cmp);
+            assert_eq!(result, expected);
+            let max_comparisons = 2 * len as u64 * (len as u64 | 1).ilog2() as u64;
+            assert!(comparisons <= max_comparisons, "{comparisons}");
+        }
+    }
+}
+
+#[test]
+fn sort_heap_sift_down_edges() {
+    // Plain textbook heapsort, sift-down checks for the second child on every level.
+    fn reference_heapsort(v: &mut [i32], is_less: &mut impl FnMut(&i32, &i32) -> bool) {
+        let mut sift_down = |v: &mut [i32], mut node: usize| loop {
+            let mut child = 2 * node + 1;
+            if child >= v.len() {
+                break;
+            }
+            if child + 1 < v.len() && is_less(&v[child], &v[child + 1]) {
+                child += 1;
+            }
+            if !is_less(&v[node], &v[child]) {
+                break;
+            }
+            v.swap(node, child);
+            node = child;
+        };
+
+        for i in (0..v.len() / 2).rev() {
+            sift_down(v, i);
+        }
+        for i in (1..v.len()).rev() {
+            v.swap(0, i);
+            sift_down(&mut v[..i], 0);
+        }
+    }
+
+    // Lengths around full trees, where the last parent alternates between one and two children.
+    for len in [
+        2, 3, 4, 5, 6, 7, 8, 9, 15, 16, 17, 31, 32, 33, 255, 256, 257, 1_000,
+    ] {
+        for v in [
+            patterns::random(len),
+            patterns::descending(len),
+            patterns::random_uniform(len, 0..=2),
+        ] {
+            let mut expected = v.clone();
+            let mut expected_calls = Vec::new();
+            reference_heapsort(&mut expected, &mut |a, b| {
+                expected_calls.push((*a, *b));
+                a < b
+            });
+
+            let mut result = v.clone();
+            let mut calls = Vec::new();
+            rust_ipnsort::heapsort(&mut result, &mut |a, b| {
+                calls.push((*a, *b));
+                a < b
+            });
+
+            // The same comparisons in the same order mean the heap is built and maintained the
+            // same way, so the heap invariant holds after every sift-down.
+            assert_eq!(calls, expected_calls);
+            assert_eq!(result, expected);
+            assert!(result.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+}
+
+#[test]
+fn sort_by_rank_custom_order() {
+    // Declared in a different order than the logical one.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    enum Priority {
+        Low,
+        Critical,
+        Medium,
+        High,
+    }
+
+    fn rank(priority: &Priority) -> u32 {
+        match priority {
+            Priority::Critical => 0,
+            Priority::High => 1,
+            Priority::Medium => 2,
+            Priority::Low => 3,
+        }
+    }
+
+    let all = [
+        Priority::Low,
+        Priority::Critical,
+        Priority::Medium,
+        Priority::High,
+    ];
+
+    for len in [0, 1, 2, 20, 1_000, 100_000] {
+        let v = patterns::random(len)
+            .iter()
+            .map(|val| all[val.rem_euclid(4) as usize])
+            .collect::<Vec<_>>();
+
+        let mut expected = v.clone();
+        expected.sort_by_key(rank);
+
+        let mut result = v;
+        rust_ipnsort::sort_by_rank(&mut result, rank);
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn sort_by_priority_grouped() {
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    enum Task {
+        Urgent { deadline: u64 },
+        High { deadline: u64, name: String },
+        Normal { id: i32 },
+        Idle,
+    }
+
+    fn priority(task: &Task) -> u8 {
+        match task {
+            Task::Urgent { .. } => 0,
+            Task::High { .. } => 1,
+            Task::Normal { .. } => 2,
+            Task::Idle => 3,
+        }
+    }
+
+    // Only orders tasks of the same variant.
+    fn compare_payload(a: &Task, b: &Task) -> Ordering {
+        match (a, b) {
+            (Task::Urgent { deadline: x }, Task::Urgent { deadline: y }) => x.cmp(y),
+            (
+                Task::High {
+                    deadline: x,
+                    name: name_x,
+                },
+                Task::High {
+                    deadline: y,
+                    name: name_y,
+                },
+            ) => x.cmp(y).then_with(|| name_x.cmp(name_y)),
+            (Task::Normal { id: x }, Task::Normal { id: y }) => x.cmp(y),
+            (Task::Idle, Task::Idle) => Ordering::Equal,
+            _ => panic!("compared tasks of different priority: {a:?} {b:?}"),
+        }
+    }
+
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        // Only few distinct payloads, so there are many ties within each variant.
+        let v = patterns::random_uniform(len, 0..=63)
+            .into_iter()
+            .map(|val| match val % 4 {
+                0 => Task::Urgent {
+                    deadline: (val / 4) as u64,
+                },
+                1 => Task::High {
+                    deadline: (val / 32) as u64,
+                    name: (val / 4).to_string(),
+                },
+                2 => Task::Normal { id: -val },
+                _ => Task::Idle,
+            })
+            .collect::<Vec<_>>();
+
+        // The variants are declared in priority order, so the derived order is the expected one.
+        let mut expected = v.clone();
+        expected.sort();
+
+        let mut result = v;
+        rust_ipnsort::sort_by_priority(&mut result, priority, compare_payload);
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn sort_by_c_cmp_sign() {
+    // `a - b` semantics, computed in `i64` so that it can't overflow, and clamped to `i32`.
+    fn c_cmp(a: &i32, b: &i32) -> i32 {
+        (*a as i64 - *b as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        let extremes = [i32::MIN, i32::MIN + 1, -1, 0, 1, i32::MAX - 1, i32::MAX];
+        for mut v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::random(len)
+                .iter()
+                .map(|&val| extremes[val.rem_euclid(extremes.len() as i32) as usize])
+                .collect(),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            rust_ipnsort::sort_by_c_cmp(&mut v, c_cmp);
+            assert_eq!(v, expected);
+        }
+    }
+
+    // Any negative or positive value works, not only -1 and 1.
+    let mut v = patterns::random(1_000);
+    let mut expected = v.clone();
+    expected.sort_by(|a, b| b.cmp(a));
+    rust_ipnsort::sort_by_c_cmp(&mut v, |a, b| match b.cmp(a) {
+        Ordering::Less => -1_000,
+        Ordering::Equal => 0,
+        Ordering::Greater => i32::MAX,
+    });
+    assert_eq!(v, expected);
+}
+
+#[cfg(feature = "bench_barriers")]
+#[test]
+fn bench_barriers_results_unchanged() {
+    for len in [0, 1, 2, 8, 20, 35, 1_000, 100_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::ascending(len),
+            patterns::descending(len),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut result = v.clone();
+            rust_ipnsort::sort(&mut result);
+            assert_eq!(result, expected);
+
+            let mut result = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+            rust_ipnsort::sort_by(&mut result, |a, b| b.cmp(a));
+            let mut expected = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+            expected.sort_by(|a, b| b.cmp(a));
+            assert_eq!(result, expected);
+        }
+    }
+}
+
+#[test]
+fn sort_by_bool_key_stable() {
+    for len in [0, 1, 2, 20, 35, 1_000, 10_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=1),
+            patterns::ascending(len),
+            patterns::descending(len),
+        ] {
+            let mut pairs = v
+                .iter()
+                .enumerate()
+                .map(|(i, &val)| (val % 3 == 0, i))
+                .collect::<Vec<_>>();
+            let mut expected = pairs.clone();
+            expected.sort();
+
+            let mut key_calls = 0;
+            rust_ipnsort::sort_by_bool_key(&mut pairs, |&(predicate, _)| {
+                key_calls += 1;
+                predicate
+            });
+            assert_eq!(pairs, expected);
+            assert!(key_calls <= len);
+        }
+    }
+}
+
+#[test]
+fn sort_by_bool_key_panic_safe() {
+    let v = patterns::random(500);
+    for panic_after in [0, 1, 250, 499] {
+        let mut strings = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+        let mut key_calls = 0;
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::sort_by_bool_key(&mut strings, |val| {
+                if key_calls == panic_after {
+                    panic!("key limit reached");
+                }
+                key_calls += 1;
+                val.len() % 2 == 0
+            });
+        }));
+        assert!(res.is_err());
+
+        // The elements seen so far are partitioned stably, the rest is untouched.
+        let (seen, rest) = v.split_at(panic_after);
+        let mut expected = seen.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+        expected.sort_by_key(|val| val.len() % 2 == 0);
+        expected.extend(rest.iter().map(|val| val.to_string()));
+        assert_eq!(strings, expected);
+    }
+}
+
+#[test]
+fn sort_each_groups() {
+    // At most 180k elements in groups, the rest of `v` is one large group.
+    let v = patterns::random(200_000);
+    let lens = patterns::random_uniform(3_000, 0..=60);
+
+    let mut result = v.clone();
+    let mut groups = Vec::new();
+    let mut rest = &mut result[..];
+    for &len in &lens {
+        let (group, tail) = rest.split_at_mut(len as usize);
+        groups.push(group);
+        rest = tail;
+    }
+    groups.push(rest);
+    rust_ipnsort::sort_each(&mut groups);
+
+    let mut expected = v.clone();
+    let mut start = 0usize;
+    for len in lens.iter().map(|&len| len as usize).chain([usize::MAX]) {
+        let end = start.saturating_add(len).min(v.len());
+        expected[start..end].sort();
+        start = end;
+    }
+    assert_eq!(result, expected);
+
+    let mut groups = result.chunks_mut(16).collect::<Vec<_>>();
+    rust_ipnsort::sort_each_by(&mut groups, |a, b| b.cmp(a));
+    assert!(groups
+        .iter()
+        .all(|group| group.windows(2).all(|w| w[0] >= w[1])));
+}
+
+#[test]
+fn sort_each_shared_scratch() {
+    // Groups of all small-sort lengths of the merging small-sort, which go through the shared
+    // scratch one after the other.
+    let lens = (0..=24).cycle().take(500).collect::<Vec<usize>>();
+    let v = patterns::random(lens.iter().sum())
+        .into_iter()
+        .map(|val| [val as u64, 0, (val as u64).wrapping_mul(3), 7])
+        .collect::<Vec<_>>();
+
+    let mut result = v.clone();
+    let mut groups = Vec::new();
+    let mut rest = &mut result[..];
+    for &len in &lens {
+        let (group, tail) = rest.split_at_mut(len);
+        groups.push(group);
+        rest = tail;
+    }
+    rust_ipnsort::sort_each(&mut groups);
+
+    let mut expected = v;
+    let mut start = 0;
+    for &len in &lens {
+        expected[start..start + len].sort();
+        start += len;
+    }
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn sort_large_elements_small_stack() {
+    // With a small-sort scratch for 20 elements on the stack, sorting the 4 KiB type needs more
+    // than 100 KiB of stack in release builds. Without it, 64 KiB are enough. Debug builds keep many
+    // more copies of the elements on the stack, independent of the small-sort.
+    const STACK_SIZE: usize = if cfg!(debug_assertions) {
+        256 * 1024
+    } else {
+        64 * 1024
+    };
+
+    fn check<const N: usize>(v: &[i32]) {
+        let input = v
+            .iter()
+            .map(|&val| {
+                let mut bytes = [0u8; N];
+                bytes[N - 4..].copy_from_slice(&val.to_be_bytes());
+                bytes
+            })
+            .collect::<Vec<_>>();
+
+        let mut expected = input.clone();
+        expected.sort();
+
+        let result = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let mut result = input;
+                rust_ipnsort::sort(&mut result);
+                result
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert!(result == expected);
+    }
+
+    for len in [8, 16, 20, 30, 100] {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=3)] {
+            check::<1024>(&v);
+            check::<4096>(&v);
+        }
+    }
+}
+
+#[test]
+fn insertion_sort_word_copy() {
+    // Same size and no interior mutability, but not `Copy`, so it takes the generic insertion.
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct NotCopy<T>(T);
+
+    fn sort_recorded<T: Ord + Clone>(v: &[T]) -> (Vec<T>, Vec<(T, T)>) {
+        let mut seen = Vec::new();
+        let mut result = v.to_vec();
+        rust_ipnsort::sort_by(&mut result, |a, b| {
+            seen.push((a.clone(), b.clone()));
+            a.cmp(b)
+        });
+
+        (result, seen)
+    }
+
+    fn check<T: Ord + Clone + std::fmt::Debug>(v: Vec<T>) {
+        let v_not_copy = v.iter().cloned().map(NotCopy).collect::<Vec<_>>();
+
+        // Same comparisons in the same order as the generic insertion.
+        let (result, seen) = sort_recorded(&v);
+        let (result_not_copy, seen_not_copy) = sort_recorded(&v_not_copy);
+        let unwrap_pair = |(a, b): &(NotCopy<T>, NotCopy<T>)| (a.0.clone(), b.0.clone());
+        assert_eq!(
+            seen,
+            seen_not_copy.iter().map(unwrap_pair).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            result,
+            result_not_copy
+                .into_iter()
+                .map(|val| val.0)
+                .collect::<Vec<_>>()
+        );
+
+        let mut expected = v.clone();
+        expected.sort();
+        assert_eq!(result, expected);
+
+        // A panic leaves a permutation of the input, without duplicating the inserted element.
+        for panic_after in [0, seen.len() / 2, seen.len() - 1] {
+            let mut result = v.clone();
+            let mut count = 0;
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                rust_ipnsort::sort_by(&mut result, |a, b| {
+                    if count == panic_after {
+                        panic!("comparison limit reached");
+                    }
+                    count += 1;
+                    a.cmp(b)
+                });
+            }));
+            assert!(res.is_err());
+
+            result.sort();
+            assert_eq!(result, expected);
+        }
+    }
+
+    // Up to 20 elements are sorted with insertion sort alone.
+    for len in 2..=20 {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=3),
+            patterns::descending(len),
+        ] {
+            check(v.iter().map(|&val| val as u64).collect());
+            check(v.iter().map(|&val| val as u32).collect());
+        }
+    }
+}
+
+#[test]
+fn exact_network_lengths() {
+    for len in 0..=21 {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=2),
+            patterns::descending(len),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            let (result, comparisons) =
+                sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+            assert_eq!(result, expected);
+
+            // The optimal networks for 4 and 8 elements always use 5 and 19 comparators.
+            match len {
+                4 => assert_eq!(comparisons, 5),
+                8 => assert_eq!(comparisons, 19),
+                _ => {}
+            }
+
+            // Types without efficient in-place swaps keep using insertion sort.
+            let mut v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+            let mut expected_str = v_str.clone();
+            expected_str.sort();
+            rust_ipnsort::sort(&mut v_str);
+            assert_eq!(v_str, expected_str);
+        }
+    }
+}
+
+/// Sorts `v`, and the same values wrapped with `wrap`, and checks that both sort to the same order
+/// with [`rust_ipnsort::sort_by`] and [`rust_ipnsort::sort`]. `sort_by` must also do the same number
+/// of comparisons, so the wrapper takes the same small-sort and partition paths as `Inner`.
+fn matches_inner<Wrapper, Inner>(
+    v: &[Inner],
+    wrap: impl Fn(Inner) -> Wrapper,
+    unwrap: impl Fn(&Wrapper) -> Inner,
+) where
+    Wrapper: Ord + Clone,
+    Inner: Ord + Copy,
+{
+    let v_wrapper = v.iter().copied().map(wrap).collect::<Vec<_>>();
+
+    let (result_inner, comparisons_inner) =
+        sort_count_comparisons(v, |v, cmp| rust_ipnsort::sort_by(v, cmp), Inner::cmp);
+    let (result_wrapper, comparisons_wrapper) = sort_count_comparisons(
+        &v_wrapper,
+        |v, cmp| rust_ipnsort::sort_by(v, cmp),
+        Wrapper::cmp,
+    );
+
+    assert!(result_wrapper
+        .iter()
+        .map(&unwrap)
+        .eq(result_inner.iter().copied()));
+    assert_eq!(comparisons_wrapper, comparisons_inner, "len: {}", v.len());
+
+    let mut result_wrapper = v_wrapper;
+    rust_ipnsort::sort(&mut result_wrapper);
+    assert!(result_wrapper
+        .iter()
+        .map(&unwrap)
+        .eq(result_inner.iter().copied()));
+}
+
+#[test]
+fn char_matches_u32() {
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+            // Scalar values below the surrogate range, so every value is a valid `char`.
+            let v_u32 = v
+                .iter()
+                .map(|&val| val.rem_euclid(0xD800) as u32)
+                .collect::<Vec<_>>();
+            matches_inner(&v_u32, |val| char::from_u32(val).unwrap(), |&c| c as u32);
+        }
+    }
+}
+
+#[test]
+fn sort_duration_and_system_time() {
+    use std::time::{Duration, SystemTime};
+
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        // Few distinct seconds, so many values only differ in the sub-second part, and values just
+        // below and at a full second, where the order crosses from nanos to secs.
+        let mut v = patterns::random(len)
+            .into_iter()
+            .map(|val| {
+                let val = val as u32;
+                Duration::new((val % 4) as u64, (val / 4) % 1_000_000_000)
+            })
+            .collect::<Vec<_>>();
+        for (i, secs) in (0..len).step_by(7).zip([0, 1, 2, 1, 0]) {
+            v[i] = Duration::new(secs, 999_999_999);
+            if i + 1 < len {
+                v[i + 1] = Duration::new(secs + 1, 0);
+            }
+        }
+
+        let as_tuple = |val: &Duration| (val.as_secs(), val.subsec_nanos());
+
+        let mut expected = v.iter().map(as_tuple).collect::<Vec<_>>();
+        expected.sort();
+
+        let mut result = v.clone();
+        rust_ipnsort::sort(&mut result);
+        assert!(result.iter().map(as_tuple).eq(expected.iter().copied()));
+
+        let times = v
+            .iter()
+            .map(|&val| SystemTime::UNIX_EPOCH + val)
+            .collect::<Vec<_>>();
+        let mut result_times = times.clone();
+        rust_ipnsort::sort(&mut result_times);
+        assert!(result_times
+            .iter()
+            .map(|time| as_tuple(&time.duration_since(SystemTime::UNIX_EPOCH).unwrap()))
+            .eq(expected.iter().copied()));
+    }
+}
+
+#[test]
+fn non_zero_matches_inner() {
+    use std::num::{NonZeroI32, NonZeroU64};
+
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        // Dense values around the edges of the value range, plenty of adjacent values.
+        let dense = patterns::random_uniform(len, 0..=64)
+            .into_iter()
+            .map(|val| {
+                if val < 32 {
+                    val as u64 + 1
+                } else {
+                    u64::MAX - 64 + val as u64
+                }
+            })
+            .collect::<Vec<_>>();
+        let random = patterns::random(len)
+            .into_iter()
+            .map(|val| (val as u32 as u64) << 16 | 1)
+            .collect::<Vec<_>>();
+
+        for v_u64 in [dense, random] {
+            matches_inner(&v_u64, |val| NonZeroU64::new(val).unwrap(), |val| val.get());
+        }
+
+        // Signed, negative values sort before positive ones.
+        let v_i32 = patterns::random_uniform(len, -16..=16)
+            .into_iter()
+            .map(|val| if val == 0 { 1 } else { val })
+            .collect::<Vec<_>>();
+        matches_inner(&v_i32, |val| NonZeroI32::new(val).unwrap(), |val| val.get());
+    }
+}
+
+#[test]
+fn sort_anti_stable_reverses_equal_elements() {
+    for len in [0, 1, 2, 20, 35, 1_000, 10_000] {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+            let mut v_indexed = v.iter().copied().zip(0..).collect::<Vec<(i32, usize)>>();
+            rust_ipnsort::sort_anti_stable_by(&mut v_indexed, |a, b| a.0.cmp(&b.0));
+
+            let mut expected = v.iter().copied().zip(0..).collect::<Vec<(i32, usize)>>();
+            expected.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+            assert_eq!(v_indexed, expected);
+        }
+    }
+}
+
+#[test]
+fn sort_deque_wrapped() {
+    use std::collections::VecDeque;
+
+    for len in [0, 1, 2, 20, 35, 1_000, 10_000] {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            // Push the second half to the back and the first half to the front, so the elements
+            // wrap around the end of the ring buffer.
+            let mut dq = VecDeque::with_capacity(len);
+            for &val in &v[len / 2..] {
+                dq.push_back(val);
+            }
+            for &val in v[..len / 2].iter().rev() {
+                dq.push_front(val);
+            }
+            assert!(dq.iter().eq(v.iter()));
+            if len >= 2 {
+                assert!(!dq.as_slices().1.is_empty());
+            }
+
+            let mut dq_by = dq.clone();
+            rust_ipnsort::sort_deque(&mut dq);
+            assert!(dq.iter().eq(expected.iter()));
+
+            rust_ipnsort::sort_deque_by(&mut dq_by, |a, b| b.cmp(a));
+            assert!(dq_by.iter().eq(expected.iter().rev()));
+        }
+    }
+}
+
+#[test]
+fn sort_coupled_keys_and_values() {
+    for len in [0, 1, 2, 20, 35, 1_000, 10_000] {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+            let mut keys = v.iter().map(|&val| val as u32).collect::<Vec<_>>();
+            // The values tell the key and the original position of each pair.
+            let mut values = keys
+                .iter()
+                .enumerate()
+                .map(|(i, &key)| [key as f32, i as f32, -(i as f32)])
+                .collect::<Vec<_>>();
+
+            let mut expected = keys.clone();
+            expected.sort();
+
+            rust_ipnsort::sort_coupled(&mut keys, &mut values);
+            assert_eq!(keys, expected);
+
+            let mut positions = vec![false; len];
+            for (key, value) in keys.iter().zip(&values) {
+                let i = value[1] as usize;
+                assert_eq!(value[0], *key as f32);
+                assert_eq!(value[2], -(i as f32));
+                assert_eq!(v[i] as u32, *key);
+                assert!(!positions[i]);
+                positions[i] = true;
+            }
+        }
+    }
+}
+
+#[test]
+fn sort_coupled_panic_safe() {
+    thread_local! {
+        static COMPARISONS_LEFT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    struct PanickingKey(String);
+
+    impl PartialOrd for PanickingKey {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for PanickingKey {
+        fn cmp(&self, other: &Self) -> Ordering {
+            let left = COMPARISONS_LEFT.with(|left| left.get());
+            if left == 0 {
+                panic!("comparison limit reached");
+            }
+            COMPARISONS_LEFT.with(|comparisons_left| comparisons_left.set(left - 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let v = patterns::random(500);
+    for panic_after in [0, 1, 100, 1_000, usize::MAX] {
+        let mut keys = v
+            .iter()
+            .map(|val| PanickingKey(val.to_string()))
+            .collect::<Vec<_>>();
+        let mut values = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+
+        COMPARISONS_LEFT.with(|left| left.set(panic_after));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::sort_coupled(&mut keys, &mut values);
+        }));
+        assert_eq!(res.is_ok(), panic_after == usize::MAX);
+
+        // Pairs stay together, and every pair is still there exactly once.
+        assert!(keys.iter().zip(&values).all(|(key, value)| key.0 == *value));
+        let mut result = values
+            .iter()
+            .map(|val| val.parse().unwrap())
+            .collect::<Vec<i32>>();
+        let mut expected = v.clone();
+        result.sort();
+        expected.sort();
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+#[should_panic(expected = "keys and values differ in length")]
+fn sort_coupled_length_mismatch() {
+    let mut keys = patterns::random(10);
+    let mut values = vec![[0.0f32; 3]; 9];
+    rust_ipnsort::sort_coupled(&mut keys, &mut values);
+}
+
+#[test]
+fn sort_partial_floats() {
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        let v = patterns::random(len)
+            .into_iter()
+            .map(|val| val as f64 / 7.0)
+            .chain([-0.0, 0.0, f64::INFINITY, f64::NEG_INFINITY])
+            .collect::<Vec<_>>();
+        let mut expected = v.clone();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mut result = v.clone();
+        rust_ipnsort::sort_partial(&mut result);
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn sort_floats_with_nan_policy() {
+    use rust_ipnsort::{NanFound, NanPolicy, SortableFloat};
+
+    fn check<T: SortableFloat + std::fmt::Debug>(
+        v: &[T],
+        bits: impl Fn(&T) -> u64,
+        total_cmp: impl Fn(&T, &T) -> Ordering,
+    ) {
+        let nan_count = v.iter().filter(|val| val.is_nan()).count();
+        let mut sorted_bits = v.iter().map(&bits).collect::<Vec<_>>();
+        sorted_bits.sort();
+
+        for policy in [NanPolicy::First, NanPolicy::Last, NanPolicy::Error] {
+            let mut result = v.to_vec();
+            let res = rust_ipnsort::sort_floats_with(&mut result, policy);
+
+            if policy == NanPolicy::Error && nan_count != 0 {
+                assert_eq!(res, Err(NanFound));
+                assert_eq!(
+                    result.iter().map(&bits).collect::<Vec<_>>(),
+                    v.iter().map(&bits).collect::<Vec<_>>()
+                );
+                continue;
+            }
+            assert_eq!(res, Ok(()));
+
+            let (nans, numbers) = if policy == NanPolicy::First {
+                result.split_at(nan_count)
+            } else {
+                let (numbers, nans) = result.split_at(result.len() - nan_count);
+                (nans, numbers)
+            };
+            assert!(nans.iter().all(|val| val.is_nan()));
+
+            // The numbers are in ascending order, with `-0.0` before `0.0`.
+            assert!(numbers
+                .windows(2)
+                .all(|w| total_cmp(&w[0], &w[1]) != Ordering::Greater));
+
+            // Every value is still present with its exact bits, including the NaN payloads.
+            let mut result_bits = result.iter().map(&bits).collect::<Vec<_>>();
+            result_bits.sort();
+            assert_eq!(result_bits, sorted_bits);
+        }
+    }
+
+    let special_f64 = [
+        f64::NAN,
+        -f64::NAN,
+        // Signaling NaN, and a negative one.
+        f64::from_bits(0x7FF0_0000_0000_0001),
+        f64::from_bits(0xFFF0_0000_0000_0001),
+        -0.0,
+        0.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ];
+    let special_f32 = [
+        f32::NAN,
+        -f32::NAN,
+        f32::from_bits(0x7F80_0001),
+        f32::from_bits(0xFF80_0001),
+        -0.0,
+        0.0,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+    ];
+
+    for len in [0, 1, 2, 20, 35, 1_000, 100_000] {
+        let v = patterns::random(len);
+        let picks = patterns::random_uniform(len, 0..=15);
+
+        let v_f64 = v
+            .iter()
+            .zip(&picks)
+            .map(|(&val, &pick)| {
+                special_f64
+                    .get(pick as usize)
+                    .copied()
+                    .unwrap_or(val as f64 / 7.0)
+            })
+            .collect::<Vec<_>>();
+        let v_f64_no_nan = v_f64.iter().filter(|val| !val.is_nan()).copied();
+        check(&v_f64, |val| val.to_bits(), f64::total_cmp);
+        check(
+            &v_f64_no_nan.collect::<Vec<_>>(),
+            |val| val.to_bits(),
+            f64::total_cmp,
+        );
+
+        let v_f32 = v
+            .iter()
+            .zip(&picks)
+            .map(|(&val, &pick)| {
+                special_f32
+                    .get(pick as usize)
+                    .copied()
+                    .unwrap_or(val as f32 / 7.0)
+            })
+            .collect::<Vec<_>>();
+        check(&v_f32, |val| val.to_bits() as u64, f32::total_cmp);
+    }
+}
+
+#[test]
+fn sort_partial_nan_panics() {
+    for len in [2, 20, 35, 1_000] {
+        let mut v = patterns::random(len)
+            .into_iter()
+            .map(|val| val as f32)
+            .collect::<Vec<_>>();
+        v[len / 2] = f32::NAN;
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::sort_partial(&mut v);
+        }));
+        let payload = res.unwrap_err();
+        let msg = payload.downcast_ref::<String>().map(String::as_str);
+        assert_eq!(msg, Some("incomparable elements"));
+
+        // Nothing is lost, the NaN is still there once.
+        assert_eq!(v.iter().filter(|val| val.is_nan()).count(), 1);
+        assert_eq!(v.len(), len);
+    }
+}
+
+#[test]
+fn wrapping_matches_inner() {
+    use std::num::Wrapping;
+
+    /// A user defined `repr(transparent)` newtype, with the derived `Ord` of the inner value.
+    #[repr(transparent)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Id(u64);
+
+    for len in [0, 1, 2, 20, 35, 36, 37, 1_000, 100_000] {
+        for v_u64 in [
+            patterns::random(len)
+                .into_iter()
+                .map(|val| (val as u32 as u64) << 16)
+                .collect::<Vec<_>>(),
+            patterns::random_uniform(len, 0..=16)
+                .into_iter()
+                .map(|val| u64::MAX - val as u64)
+                .collect::<Vec<_>>(),
+        ] {
+            matches_inner(&v_u64, Wrapping, |val| val.0);
+            matches_inner(&v_u64, Id, |val| val.0);
+        }
+
+        // Signed, `Wrapping` compares the inner value and not the wrapped-around bits.
+        matches_inner(&patterns::random(len), Wrapping, |val| val.0);
+    }
+}
+
+#[test]
+fn sort_by_projection_borrowed_key() {
+    for len in [0, 1, 2, 20, 35, 1_000, 10_000] {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+            let mut records = v
+                .iter()
+                // Offset to non-negative values, so the string order matches the numeric order.
+                .map(|&val| (format!("{:010}", val as i64 - i32::MIN as i64), val))
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort();
+
+            rust_ipnsort::sort_by_projection(&mut records, |record| record.0.as_str(), str::cmp);
+            assert!(records
+                .iter()
+                .map(|record| record.1)
+                .eq(expected.iter().copied()));
+
+            // Reverse order through the comparator, the projection is unchanged.
+            rust_ipnsort::sort_by_projection(&mut records, |record| &record.1, |a, b| b.cmp(a));
+            assert!(records
+                .iter()
+                .map(|record| record.1)
+                .eq(expected.iter().rev().copied()));
+        }
+    }
+}
+
+#[test]
+fn try_sort_allocation_failure() {
+    let v = patterns::random(1_000);
+    let mut expected = v.clone();
+    expected.sort();
+
+    let mut stable = v.clone();
+    let mut cached = v.clone();
+    let mut short = v[..20].to_vec();
+    let mut key_calls = 0;
+
+    FAIL_ALLOCATIONS.with(|fail| fail.set(true));
+    let stable_result = rust_ipnsort::try_sort_stable(&mut stable);
+    let cached_result = rust_ipnsort::try_sort_by_cached_key(&mut cached, |&val| {
+        key_calls += 1;
+        val
+    });
+    // Short enough for insertion sort, which doesn't allocate.
+    let short_result = rust_ipnsort::try_sort_stable(&mut short);
+    FAIL_ALLOCATIONS.with(|fail| fail.set(false));
+
+    assert!(stable_result.is_err());
+    assert!(cached_result.is_err());
+    assert_eq!(stable, v);
+    assert_eq!(cached, v);
+    assert_eq!(key_calls, 0);
+
+    assert!(short_result.is_ok());
+    let mut short_expected = v[..20].to_vec();
+    short_expected.sort();
+    assert_eq!(short, short_expected);
+
+    assert!(rust_ipnsort::try_sort_stable(&mut stable).is_ok());
+    assert!(rust_ipnsort::try_sort_by_cached_key(&mut cached, |&val| val).is_ok());
+    assert_eq!(stable, expected);
+    assert_eq!(cached, expected);
+}
+
+#[test]
+fn sort_collect_filtered() {
+    for len in [0, 1, 2, 20, 1_000, 100_000] {
+        let v = patterns::random(len);
+
+        let mut expected = v.iter().copied().filter(|x| x % 3 == 0).collect::<Vec<_>>();
+        expected.sort();
+
+        let result = rust_ipnsort::sort_collect(v.iter().copied().filter(|x| x % 3 == 0));
+        assert_eq!(result, expected);
+
+        expected.reverse();
+        let result =
+            rust_ipnsort::sort_collect_by(v.iter().copied().filter(|x| x % 3 == 0), |a, b| {
+                b.cmp(a)
+            });
+        assert_eq!(result, expected);
+    }
+
+    // The upper bound of the size hint is `u64::MAX`, far more than can be allocated.
+    let result =
+        rust_ipnsort::sort_collect((0..u64::MAX).take_while(|&x| x < 1_000).map(|x| 999 - x));
+    assert!(result.iter().copied().eq(0..1_000));
+}
+
+#[test]
+fn try_sort_by_key_error() {
+    for len in [0, 1, 2, 20, 1_000] {
+        let v = patterns::random(len)
+            .iter()
+            .map(|val| val.to_string())
+            .collect::<Vec<_>>();
+
+        let mut expected = v.clone();
+        expected.sort_by_key(|val| val.parse::<i32>().unwrap());
+
+        let mut result = v.clone();
+        assert_eq!(
+            rust_ipnsort::try_sort_by_key(&mut result, |val| val.parse::<i32>()),
+            Ok(())
+        );
+        assert_eq!(result, expected);
+
+        if len == 0 {
+            continue;
+        }
+
+        // Only the element in the middle fails to parse, the slice must not be touched.
+        let mut invalid = v.clone();
+        invalid[len / 2] = "not a number".into();
+        let original = invalid.clone();
+        let mut key_count = 0;
+        let result = rust_ipnsort::try_sort_by_key(&mut invalid, |val| {
+            key_count += 1;
+            val.parse::<i32>()
+        });
+        assert!(result.is_err());
+        assert_eq!(invalid, original);
+        // Extraction stops at the first error.
+        assert_eq!(key_count, len / 2 + 1);
+    }
+}
+
+#[test]
+fn cached_key_index_width() {
+    use rust_ipnsort::{cached_key_index_width, CachedKeyIndexWidth};
+
+    let max_u32 = u32::MAX as usize;
+
+    assert_eq!(cached_key_index_width::<u8>(255), CachedKeyIndexWidth::U8);
+    assert_eq!(cached_key_index_width::<u8>(256), CachedKeyIndexWidth::U16);
+    assert_eq!(
+        cached_key_index_width::<u16>(65_535),
+        CachedKeyIndexWidth::U16
+    );
+    assert_eq!(
+        cached_key_index_width::<u16>(65_536),
+        CachedKeyIndexWidth::U32
+    );
+
+    // The index is only narrowed if that makes the pair smaller.
+    assert_eq!(cached_key_index_width::<u32>(10), CachedKeyIndexWidth::U32);
+    assert_eq!(
+        cached_key_index_width::<u64>(10),
+        CachedKeyIndexWidth::Usize
+    );
+    assert_eq!(
+        cached_key_index_width::<String>(10),
+        CachedKeyIndexWidth::Usize
+    );
+
+    // Slices that can't be indexed with u32 must fall back to usize.
+    assert_eq!(
+        cached_key_index_width::<u32>(max_u32),
+        CachedKeyIndexWidth::U32
+    );
+    assert_eq!(
+        cached_key_index_width::<u32>(max_u32 + 1),
+        CachedKeyIndexWidth::Usize
+    );
+    assert_eq!(
+        cached_key_index_width::<u8>(max_u32 + 1),
+        CachedKeyIndexWidth::Usize
+    );
+    assert_eq!(
+        cached_key_index_width::<()>(usize::MAX),
+        CachedKeyIndexWidth::Usize
+    );
+}
+
+#[test]
+fn run_detection_cost() {
+    // The run detection stops at the first pair that breaks the streak, random inputs only pay for
+    // a handful of comparisons, not a scan of the whole slice.
+    let len = 1_000_000;
+    let v = patterns::random(len);
+    let count = Cell::new(0);
+    let (streak_end, _) = rust_ipnsort::find_streak_by(&v, &mut |a, b| {
+        count.set(count.get() + 1);
+        a < b
+    });
+    assert!(streak_end < 32, "{streak_end}");
+    assert!(count.get() < 34, "{}", count.get());
+
+    // Fully presorted inputs in either direction are detected with one scan, and sorted without any
+    // other comparison.
+    for (v, was_reversed) in [
+        (patterns::ascending(len), false),
+        (patterns::descending(len), true),
+    ] {
+        let mut expected = v.clone();
+        expected.sort();
+
+        let count = Cell::new(0);
+        let streak = rust_ipnsort::find_streak_by(&v, &mut |a, b| {
+            count.set(count.get() + 1);
+            a < b
+        });
+        assert_eq!(streak, (len, was_reversed));
+        assert!(count.get() <= len);
+
+        let (result, comparisons) =
+            sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+        assert_eq!(result, expected);
+        assert_eq!(comparisons, count.get() as u64);
+    }
+}
+
+#[test]
+fn find_streak_equal_runs() {
+    let find_streak = |v: &[i32]| rust_ipnsort::find_streak_by(v, &mut |a, b| a < b);
+
+    assert_eq!(find_streak(&[]), (0, false));
+    assert_eq!(find_streak(&[2, 2, 2]), (3, false));
+
+    // Leading equal elements.
+    assert_eq!(find_streak(&[3, 3, 2, 1]), (4, true));
+    assert_eq!(find_streak(&[3, 3, 2, 1, 5]), (4, true));
+    assert_eq!(find_streak(&[1, 1, 2, 3]), (4, false));
+    assert_eq!(find_streak(&[1, 1, 2, 3, 0]), (4, false));
+
+    // Trailing and inner equal elements, only ascending streaks include them.
+    assert_eq!(find_streak(&[3, 2, 1, 1]), (3, true));
+    assert_eq!(find_streak(&[3, 3, 2, 2, 1]), (3, true));
+    assert_eq!(find_streak(&[1, 2, 3, 3]), (4, false));
+    assert_eq!(find_streak(&[1, 1, 2, 2, 0]), (4, false));
+
+    // All-equal inputs and equal prefixes cost one comparison per element.
+    for len in [2, 20, 21, 1_000] {
+        let count = Cell::new(0);
+        let find_streak_counted = |v: &[i32]| {
+            count.set(0);
+            let streak = rust_ipnsort::find_streak_by(v, &mut |a, b| {
+                count.set(count.get() + 1);
+                a < b
+            });
+            (streak, count.get())
+        };
+
+        assert_eq!(find_streak_counted(&vec![7; len]), ((len, false), len - 1));
+
+        let mut v = vec![7; len];
+        v.extend([6, 5]);
+        assert_eq!(find_streak_counted(&v), ((len + 2, true), len + 2));
+
+        let (result, comparisons) = sort_count_comparisons(
+            &vec![7; len],
+            |v, cmp| rust_ipnsort::sort_by(v, cmp),
+            i32::cmp,
+        );
+        assert_eq!(result, vec![7; len]);
+        assert_eq!(comparisons, len as u64 - 1);
+    }
+
+    // Descending with equal runs, with and without a trailing unsorted part.
+    for len in [20, 21, 100, 10_000] {
+        for tail in [0, 5] {
+            let mut v = (0..len).rev().map(|val| val / 3).collect::<Vec<i32>>();
+            v.extend(patterns::random(tail));
+            let mut expected = v.clone();
+            expected.sort();
+
+            rust_ipnsort::sort(&mut v);
+            assert_eq!(v, expected);
+        }
+    }
+}
+
+#[test]
+fn costly_comparisons_favor_fewer_comparisons() {
+    use sort_test_tools::cost_model::measure_sort;
+    use sort_test_tools::Sort;
+
+    // Needs quadratically many comparisons, but moves few elements for small inputs.
+    struct InsertionSort;
+
+    impl Sort for InsertionSort {
+        fn name() -> String {
+            "insertion_sort".into()
+        }
+
+        fn sort<T: Ord>(arr: &mut [T]) {
+            Self::sort_by(arr, T::cmp);
+        }
+
+        fn sort_by<T, F>(arr: &mut [T], mut compare: F)
+        where
+            F: FnMut(&T, &T) -> Ordering,
+        {
+            for i in 1..arr.len() {
+                let mut j = i;
+                while j > 0 && compare(&arr[j], &arr[j - 1]) == Ordering::Less {
+                    arr.swap(j, j - 1);
+                    j -= 1;
+                }
+            }
+        }
+    }
+
+    let v = patterns::random(500);
+    let mut expected = v.clone();
+    expected.sort();
+
+    let (mut ipnsort_best, mut insertion_best) =
+        (std::time::Duration::MAX, std::time::Duration::MAX);
+    for _ in 0..3 {
+        let (result, ipnsort) = measure_sort::<rust_ipnsort::SortImpl, _>(&v, 2_000);
+        assert_eq!(result, expected);
+        let (result, insertion) = measure_sort::<InsertionSort, _>(&v, 2_000);
+        assert_eq!(result, expected);
+
+        assert!(ipnsort.comparisons * 4 < insertion.comparisons);
+        ipnsort_best = ipnsort_best.min(ipnsort.elapsed);
+        insertion_best = insertion_best.min(insertion.elapsed);
+    }
+
+    assert!(
+        ipnsort_best < insertion_best,
+        "ipnsort: {ipnsort_best:?} insertion sort: {insertion_best:?}"
+    );
+}
+
+#[test]
+fn gathered_pivot_matches_scalar() {
+    // With AVX2, `i32` pivot samples are gathered with SIMD, `i64` always takes the scalar path.
+    // Both must pick the same pivots, which makes the comparisons identical. The gathered path
+    // does the pivot comparisons in a different order, so only the multisets are compared.
+    //
+    // The switch from Lomuto to block partitioning depends on the size in bytes, so the two would
+    // partition some slices differently. Fulcrum partitioning doesn't, use it for both. The override
+    // is thread-local, so it doesn't affect other tests running in parallel.
+    rust_ipnsort::set_fulcrum_enabled(true);
+
+    for len in [4_096, 10_000, 100_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::saw_mixed(len, 8),
+        ] {
+            let v_i64 = v.iter().map(|&val| val as i64).collect::<Vec<_>>();
+
+            let mut trace = Vec::new();
+            let (result, _) = sort_count_comparisons(
+                &v,
+                |v, cmp| rust_ipnsort::sort_by(v, cmp),
+                |a: &i32, b: &i32| {
+                    trace.push((*a, *b));
+                    a.cmp(b)
+                },
+            );
+
+            let mut trace_i64 = Vec::new();
+            let (result_i64, _) = sort_count_comparisons(
+                &v_i64,
+                |v, cmp| rust_ipnsort::sort_by(v, cmp),
+                |a: &i64, b: &i64| {
+                    trace_i64.push((*a as i32, *b as i32));
+                    a.cmp(b)
+                },
+            );
+
+            assert_eq!(
+                result.iter().map(|&val| val as i64).collect::<Vec<_>>(),
+                result_i64
+            );
+            trace.sort();
+            trace_i64.sort();
+            assert!(trace == trace_i64, "comparisons diverged for len {len}");
+        }
+    }
+
+    rust_ipnsort::set_fulcrum_enabled(false);
+}
+
+#[test]
+fn sort_suffix_correct() {
+    for len in [0, 1, 2, 20, 1_000, 100_000] {
+        let v = patterns::random(len);
+        for sorted_prefix_len in [0, 1, len / 2, len - len / 10, len.saturating_sub(1), len] {
+            let sorted_prefix_len = sorted_prefix_len.min(len);
+            let mut input = v.clone();
+            input[..sorted_prefix_len].sort();
+
+            let mut expected = input.clone();
+            expected.sort();
+
+            rust_ipnsort::sort_suffix(&mut input, sorted_prefix_len);
+            assert_eq!(input, expected);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "sorted prefix out of bounds")]
+fn sort_suffix_out_of_bounds() {
+    let mut v = patterns::random(10);
+    rust_ipnsort::sort_suffix(&mut v, 11);
+}
+
+#[test]
+fn sort_with_proof_rejects_corruption() {
+    for len in [0, 1, 2, 20, 1_000] {
+        let v = patterns::random_uniform(len, 0..=50);
+        let mut result = v.clone();
+        let proof = rust_ipnsort::sort_with_proof(&mut result);
+
+        assert!(proof.is_valid());
+        assert!(proof.verify(&result));
+        assert_eq!(proof.len, len);
+        assert_eq!(proof, rust_ipnsort::sort_with_proof(&mut v.clone()));
+
+        if len < 2 {
+            continue;
+        }
+
+        // Sorted, but one element replaced.
+        let mut replaced = result.clone();
+        replaced[len - 1] += 1;
+        assert!(!proof.verify(&replaced));
+
+        // Sorted, but one element duplicated and another dropped.
+        let mut duplicated = result.clone();
+        duplicated[0] = duplicated[len - 1];
+        duplicated.sort();
+        assert!(duplicated == result || !proof.verify(&duplicated));
+
+        // Right multiset, wrong order.
+        let mut unsorted = result.clone();
+        unsorted.reverse();
+        assert!(unsorted == result || !proof.verify(&unsorted));
+
+        assert!(!proof.verify(&result[1..]));
+    }
+}
+
+#[test]
+fn argsort_permutation() {
+    for len in [0, 1, 2, 20, 1_000, 70_000] {
+        let v = patterns::random(len);
+        let mut expected = v.clone();
+        expected.sort();
+
+        let p = rust_ipnsort::argsort(&v);
+        assert!(matches!(p, rust_ipnsort::ArgsortIndices::U32(_)));
+        assert_eq!(p.len(), len);
+        let applied = p.iter().map(|i| v[i]).collect::<Vec<i32>>();
+        assert_eq!(applied, expected);
+
+        let mut seen = p.iter().collect::<Vec<usize>>();
+        seen.sort();
+        assert!(seen.iter().enumerate().all(|(i, &idx)| idx == i));
+
+        expected.reverse();
+        let p = rust_ipnsort::argsort_by(&v, |a, b| b.cmp(a));
+        let applied = p.iter().map(|i| v[i]).collect::<Vec<i32>>();
+        assert_eq!(applied, expected);
+
+        let p = rust_ipnsort::argsort_usize_by(&v, |a, b| b.cmp(a));
+        let applied = p.iter().map(|&i| v[i]).collect::<Vec<i32>>();
+        assert_eq!(applied, expected);
+    }
+}
+
+#[test]
+fn sort_min_writes_minimal() {
+    for len in [0, 1, 2, 3, 20, 100, 500] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=4),
+            patterns::ascending(len),
+            patterns::descending(len),
+            patterns::random_sorted(len, 90.0),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            // Writing exactly the elements that are not in their final position is the minimum.
+            let min_writes = v.iter().zip(&expected).filter(|(a, b)| a != b).count();
+
+            let mut result = v.clone();
+            let writes = rust_ipnsort::cycle_sort(&mut result, &mut |a, b| a < b);
+            assert_eq!(result, expected);
+            assert_eq!(writes, min_writes);
+
+            let mut v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+            let mut expected_str = v_str.clone();
+            expected_str.sort();
+
+            rust_ipnsort::sort_min_writes(&mut v_str);
+            assert_eq!(v_str, expected_str);
+        }
+    }
+}
+
+#[test]
+fn sort_min_writes_panic_safe() {
+    thread_local! {
+        static DROP_COUNT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct DropCounted(i32);
+
+    impl Drop for DropCounted {
+        fn drop(&mut self) {
+            DROP_COUNT.with(|count| count.set(count.get() + 1));
+        }
+    }
+
+    let len = 200;
+    for panic_after in [0, 1, 50, 1_000, 10_000] {
+        DROP_COUNT.with(|count| count.set(0));
+
+        let mut v = patterns::random(len)
+            .into_iter()
+            .map(DropCounted)
+            .collect::<Vec<_>>();
+        let mut comparisons = 0;
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::cycle_sort(&mut v, &mut |a, b| {
+                comparisons += 1;
+                if comparisons > panic_after {
+                    panic!("comparison limit reached");
+                }
+                a < b
+            });
+        }));
+        assert!(res.is_err());
+
+        // Every element must still be in the slice exactly once.
+        assert_eq!(DROP_COUNT.with(|count| count.get()), 0);
+        drop(v);
+        assert_eq!(DROP_COUNT.with(|count| count.get()), len);
+    }
+}
+
+#[test]
+fn sort_large_elements_panic_safe() {
+    thread_local! {
+        static DROP_COUNT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // 256 bytes, the payload is derived from the key so that torn or duplicated moves show up.
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Large {
+        key: i32,
+        payload: [u32; 63],
+    }
+
+    fn payload(key: i32) -> [u32; 63] {
+        std::array::from_fn(|i| (key as u32).rotate_left(i as u32))
+    }
+
+    impl Drop for Large {
+        fn drop(&mut self) {
+            DROP_COUNT.with(|count| count.set(count.get() + 1));
+        }
+    }
+
+    for len in [100, 1_000] {
+        let values = patterns::random_uniform(len, 0..=(len as i32 / 4));
+        let mut expected = values.clone();
+        expected.sort();
+
+        for panic_after in [0, 1, 10, 100, 1_000, 5_000, usize::MAX] {
+            let mut v = values
+                .iter()
+                .map(|&key| Large {
+                    key,
+                    payload: payload(key),
+                })
+                .collect::<Vec<_>>();
+            DROP_COUNT.with(|count| count.set(0));
+
+            let mut comparisons = 0;
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                rust_ipnsort::sort_by(&mut v, |a, b| {
+                    if comparisons == panic_after {
+                        panic!("comparison limit reached");
+                    }
+                    comparisons += 1;
+                    a.cmp(b)
+                });
+            }));
+
+            assert!(v.iter().all(|elem| elem.payload == payload(elem.key)));
+            let mut keys = v.iter().map(|elem| elem.key).collect::<Vec<_>>();
+            if res.is_ok() {
+                assert_eq!(keys, expected);
+            }
+            keys.sort();
+            assert_eq!(keys, expected);
+
+            assert_eq!(DROP_COUNT.with(|count| count.get()), 0);
+            drop(v);
+            assert_eq!(DROP_COUNT.with(|count| count.get()), len);
+        }
+    }
+}
+
+#[test]
+fn insertion_sort_shift_right_sorted_suffix() {
+    for len in [1, 2, 3, 20, 35, 100] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=4),
+            patterns::descending(len),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            for offset in [0, 1, len / 2, len - 1] {
+                if offset >= len {
+                    continue;
+                }
+
+                let mut v = v.clone();
+                v[offset..].sort();
+
+                rust_ipnsort::insertion_sort_shift_right(&mut v, offset, &mut |a, b| a < b);
+                assert_eq!(v, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn insertion_sort_shift_right_panic_safe() {
+    thread_local! {
+        static DROP_COUNT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct DropCounted(i32);
+
+    impl Drop for DropCounted {
+        fn drop(&mut self) {
+            DROP_COUNT.with(|count| count.set(count.get() + 1));
+        }
+    }
+
+    let len = 100;
+    for panic_after in [0, 1, 50, 500] {
+        DROP_COUNT.with(|count| count.set(0));
+
+        let mut values = patterns::random(len);
+        values[len / 2..].sort();
+        let mut v = values.iter().copied().map(DropCounted).collect::<Vec<_>>();
+        let mut comparisons = 0;
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rust_ipnsort::insertion_sort_shift_right(&mut v, len / 2, &mut |a, b| {
+                comparisons += 1;
+                if comparisons > panic_after {
+                    panic!("comparison limit reached");
+                }
+                a < b
+            });
+        }));
+        assert!(res.is_err());
+
+        // Every element must still be in the slice exactly once.
+        let mut result = v.iter().map(|val| val.0).collect::<Vec<_>>();
+        result.sort();
+        values.sort();
+        assert_eq!(result, values);
+
+        assert_eq!(DROP_COUNT.with(|count| count.get()), 0);
+        drop(v);
+        assert_eq!(DROP_COUNT.with(|count| count.get()), len);
+    }
+}
+
+#[test]
+fn interior_mutable_types() {
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+
+    // Types with interior mutability must not take the paths that compare copies of elements. Any
+    // write through the comparison function to a copy would be lost, or for owned heap data lead
+    // to a double free or leak. Every write is counted, so a lost one shows up as a mismatch.
+    let lens: &[usize] = if cfg!(miri) {
+        &[0, 1, 2, 8, 20, 21, 50, 200]
+    } else {
+        &[0, 1, 2, 8, 20, 21, 50, 200, 1_000, 10_000]
+    };
+
+    for &len in lens {
+        for v in [patterns::random(len), patterns::random_uniform(len, 0..=8)] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            // 8 bytes, small enough for every in-place swap path if it weren't for the `Cell`. The
+            // key is in the upper half, the lower half counts the writes.
+            let mut v_cell = v
+                .iter()
+                .map(|&val| Cell::new(((val as u32 ^ (1 << 31)) as u64) << 32))
+                .collect::<Vec<_>>();
+            let mut comparisons = 0;
+            rust_ipnsort::sort_by(&mut v_cell, |a, b| {
+                comparisons += 1;
+                a.set(a.get() + 1);
+                b.set(b.get() + 1);
+                (a.get() >> 32).cmp(&(b.get() >> 32))
+            });
+            assert!(v_cell
+                .iter()
+                .map(|val| (val.get() >> 32) as u32 ^ (1 << 31))
+                .eq(expected.iter().map(|&val| val as u32)));
+            let seen = v_cell
+                .iter()
+                .map(|val| val.get() & u32::MAX as u64)
+                .sum::<u64>();
+            assert_eq!(seen, 2 * comparisons);
+
+            // Observed writes are counted in the elements themselves.
+            struct Counted {
+                key: i32,
+                seen: Cell<u32>,
+            }
+
+            let mut v_counted = v
+                .iter()
+                .map(|&key| Counted {
+                    key,
+                    seen: Cell::new(0),
+                })
+                .collect::<Vec<_>>();
+            let mut comparisons = 0;
+            rust_ipnsort::sort_by(&mut v_counted, |a, b| {
+                comparisons += 1;
+                a.seen.set(a.seen.get() + 1);
+                b.seen.set(b.seen.get() + 1);
+                a.key.cmp(&b.key)
+            });
+            assert!(v_counted
+                .iter()
+                .map(|val| val.key)
+                .eq(expected.iter().copied()));
+            let seen = v_counted
+                .iter()
+                .map(|val| val.seen.get() as u64)
+                .sum::<u64>();
+            assert_eq!(seen, 2 * comparisons);
+
+            // Owned heap data behind a `RefCell`, every comparison allocates into both elements.
+            let mut v_ref_cell = v
+                .iter()
+                .map(|&key| (key, RefCell::new(Vec::<Box<str>>::new())))
+                .collect::<Vec<_>>();
+            let mut comparisons = 0;
+            rust_ipnsort::sort_by(&mut v_ref_cell, |a, b| {
+                comparisons += 1;
+                a.1.borrow_mut().push(format!("{}", b.0).into_boxed_str());
+                b.1.borrow_mut().push(format!("{}", a.0).into_boxed_str());
+                a.0.cmp(&b.0)
+            });
+            assert!(v_ref_cell
+                .iter()
+                .map(|val| val.0)
+                .eq(expected.iter().copied()));
+            let pushed = v_ref_cell
+                .iter()
+                .map(|val| val.1.borrow().len() as u64)
+                .sum::<u64>();
+            assert_eq!(pushed, 2 * comparisons);
+
+            // Same with a `Mutex<Option<Box<str>>>`, which replaces its content on every comparison.
+            let mut v_mutex = v
+                .iter()
+                .map(|&key| (key, Mutex::new(None::<Box<str>>)))
+                .collect::<Vec<_>>();
+            rust_ipnsort::sort_by(&mut v_mutex, |a, b| {
+                *a.1.lock().unwrap() = Some(format!("{}", b.0).into_boxed_str());
+                *b.1.lock().unwrap() = Some(format!("{}", a.0).into_boxed_str());
+                a.0.cmp(&b.0)
+            });
+            assert!(v_mutex.iter().map(|val| val.0).eq(expected.iter().copied()));
+            if len >= 2 {
+                assert!(v_mutex.iter().all(|val| val.1.lock().unwrap().is_some()));
+            }
+        }
+    }
+}
+
+#[test]
+fn runtime_config_overrides() {
+    let lens = [0, 1, 2, 9, 20, 21, 36, 37, 50, 100, 1_000, 10_000];
+
+    let check = || {
+        for len in lens {
+            for v in [
+                patterns::random(len),
+                patterns::random_uniform(len, 0..=4),
+                patterns::saw_mixed_range(len, 20..70),
+            ] {
+                let mut expected = v.clone();
+                expected.sort();
+
+                let mut v_u64 = v.iter().map(|&val| val as u64).collect::<Vec<_>>();
+                let mut expected_u64 = v_u64.clone();
+                expected_u64.sort();
+
+                let mut v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+                let mut expected_str = v_str.clone();
+                expected_str.sort();
+
+                let mut v = v;
+                rust_ipnsort::sort(&mut v);
+                assert_eq!(v, expected);
+
+                rust_ipnsort::sort(&mut v_u64);
+                assert_eq!(v_u64, expected_u64);
+
+                rust_ipnsort::sort(&mut v_str);
+                assert_eq!(v_str, expected_str);
+            }
+        }
+    };
+
+    // The overrides are thread-local, so they don't affect other tests running in parallel.
+    for threshold in [0, 8, 12, 20, 36, usize::MAX] {
+        for fulcrum_enabled in [false, true] {
+            rust_ipnsort::set_small_sort_threshold(threshold);
+            rust_ipnsort::set_fulcrum_enabled(fulcrum_enabled);
+            check();
+        }
+    }
+
+    rust_ipnsort::set_small_sort_threshold(usize::MAX);
+    rust_ipnsort::set_fulcrum_enabled(false);
+}
+
+#[test]
+fn fulcrum_partition_correct() {
+    for len in [32, 33, 63, 64, 65, 100, 255, 256, 257, 1_000, 10_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=4),
+            patterns::ascending(len),
+            patterns::descending(len),
+        ] {
+            for pivot in [i32::MIN, 0, 2, v[len / 2], i32::MAX] {
+                let mut result = v.clone();
+                let mid =
+                    rust_ipnsort::partition_fulcrum_by(&mut result, &pivot, &mut |a, b| a < b);
+
+                assert!(result[..mid].iter().all(|&val| val < pivot));
+                assert!(result[mid..].iter().all(|&val| val >= pivot));
+
+                let mut expected = v.clone();
+                expected.sort();
+                result.sort();
+                assert_eq!(result, expected);
+            }
+        }
+    }
+}
+
+/// Partitions clones of `v` with the fulcrum and the block partition, and checks that both return
+/// the same number of elements smaller than `pivot`, produce a valid partition, and put the same
+/// elements on each side. The order within each side may differ.
+fn assert_partitions_equivalent<T: Ord + Clone + std::fmt::Debug>(v: &[T], pivot: &T) {
+    let mut fulcrum = v.to_vec();
+    let fulcrum_mid = rust_ipnsort::partition_fulcrum_by(&mut fulcrum, pivot, &mut |a, b| a < b);
+
+    let mut blocks = v.to_vec();
+    let blocks_mid = rust_ipnsort::partition_blocks_by(&mut blocks, pivot, &mut |a, b| a < b);
+
+    assert_eq!(fulcrum_mid, blocks_mid, "len: {} pivot: {pivot:?}", v.len());
+
+    for result in [&mut fulcrum, &mut blocks] {
+        assert!(result[..blocks_mid].iter().all(|val| val < pivot));
+        assert!(result[blocks_mid..].iter().all(|val| val >= pivot));
+
+        result[..blocks_mid].sort();
+        result[blocks_mid..].sort();
+    }
+    assert_eq!(fulcrum, blocks, "len: {} pivot: {pivot:?}", v.len());
+}
+
+#[test]
+fn fulcrum_and_block_partition_equivalent() {
+    // Around the minimum fulcrum length, the switch from 16 to 32 rotation elements at 256, and
+    // multiples of the block partition block size.
+    let mut lens = vec![
+        0, 1, 2, 15, 16, 17, 31, 32, 33, 47, 48, 63, 64, 65, 127, 128, 129,
+    ];
+    lens.extend([254, 255, 256, 257, 258, 511, 512, 513, 1_000, 4_096, 10_000]);
+    lens.extend(
+        patterns::random_uniform(20, 32..=2_000)
+            .into_iter()
+            .map(|len| len as usize),
+    );
+
+    for len in lens {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=4),
+            patterns::ascending(len),
+            patterns::descending(len),
+            patterns::saw_mixed(len, ((len as f64).log2().round() as usize).max(1)),
+        ] {
+            let mut pivots = vec![i32::MIN, i32::MAX];
+            pivots.extend(v.first());
+            pivots.extend(v.get(len / 2));
+            pivots.extend(v.last());
+
+            for pivot in pivots {
+                assert_partitions_equivalent(&v, &pivot);
+
+                // Types with fulcrum partitioning of different widths, and one without.
+                let v_u64 = v.iter().map(|&val| val as i64 as u64).collect::<Vec<_>>();
+                assert_partitions_equivalent(&v_u64, &(pivot as i64 as u64));
+
+                let v_u8 = v.iter().map(|&val| val as u8).collect::<Vec<_>>();
+                assert_partitions_equivalent(&v_u8, &(pivot as u8));
+
+                if len <= 1_000 {
+                    let v_str = v.iter().map(|val| format!("{val:011}")).collect::<Vec<_>>();
+                    assert_partitions_equivalent(&v_str, &format!("{pivot:011}"));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn fulcrum_partition_panic_safe() {
+    for len in [32, 64, 100, 255, 256, 1_000] {
+        let v = patterns::random(len);
+        let pivot = v[len / 3];
+        let mut expected = v.clone();
+        expected.sort();
+
+        // Panic in every phase, including while the swap buffer is drained at the end.
+        for panic_after in (0..len).step_by(7).chain([len - 1]) {
+            let mut result = v.clone();
+            let mut count = 0;
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                rust_ipnsort::partition_fulcrum_by(&mut result, &pivot, &mut |a, b| {
+                    if count == panic_after {
+                        panic!("comparison failed");
+                    }
+                    count += 1;
+                    a < b
+                });
+            }));
+
+            assert!(res.is_err());
+            result.sort();
+            assert_eq!(result, expected);
+        }
+    }
+}
+
+#[test]
+fn lomuto_partition_correct() {
+    for len in [0, 1, 2, 7, 31, 32, 100, 255, 256, 511, 512, 1_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=4),
+            patterns::ascending(len),
+            patterns::descending(len),
+        ] {
+            let pivots = if len == 0 {
+                vec![0]
+            } else {
+                vec![i32::MIN, 0, 2, v[len / 2], i32::MAX]
+            };
+
+            for pivot in pivots {
+                let mut result = v.clone();
+                let mid = rust_ipnsort::partition_lomuto_by(&mut result, &pivot, &mut |a, b| a < b);
+
+                assert!(result[..mid].iter().all(|&val| val < pivot));
+                assert!(result[mid..].iter().all(|&val| val >= pivot));
+
+                // Same split point as the block partition, only the order within the sides may
+                // differ.
+                let mut expected = v.clone();
+                let expected_mid =
+                    rust_ipnsort::partition_blocks_by(&mut expected, &pivot, &mut |a, b| a < b);
+                assert_eq!(mid, expected_mid);
+
+                result[..mid].sort();
+                result[mid..].sort();
+                expected[..mid].sort();
+                expected[mid..].sort();
+                assert_eq!(result, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn lomuto_partition_panic_safe() {
+    for len in [1, 32, 100, 512] {
+        let v = patterns::random(len);
+        let pivot = v[len / 3];
+        let mut expected = v.clone();
+        expected.sort();
+
+        for panic_after in (0..len).step_by(7).chain([len - 1]) {
+            let mut result = v.clone();
+            let mut count = 0;
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                rust_ipnsort::partition_lomuto_by(&mut result, &pivot, &mut |a, b| {
+                    if count == panic_after {
+                        panic!("comparison failed");
+                    }
+                    count += 1;
+                    a < b
+                });
+            }));
+
+            assert!(res.is_err());
+            result.sort();
+            assert_eq!(result, expected);
+        }
+    }
+}
+
+/// Polls `future` to completion on the current thread, and returns its output and the number of
+/// times it returned `Pending`.
+fn block_on_count_pending<Fut: std::future::Future>(future: Fut) -> (Fut::Output, usize) {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct NoopWake;
+
+    impl Wake for NoopWake {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWake));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+
+    let mut pending_count = 0;
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return (output, pending_count),
+            Poll::Pending => pending_count += 1,
+        }
+    }
+}
+
+#[test]
+fn sort_by_yielding_correct() {
+    for len in [0, 1, 2, 20, 1_000, 100_000, 1_000_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=16),
+            patterns::saw_mixed_range(len, 20..70),
+        ] {
+            let mut expected = v.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            let mut result = v.clone();
+            let ((), yield_count) =
+                block_on_count_pending(rust_ipnsort::sort_by_yielding(&mut result, |a, b| {
+                    b.cmp(a)
+                }));
+            assert_eq!(result, expected);
+
+            if len <= 20 {
+                assert_eq!(yield_count, 0);
+            }
+            if len >= 1_000_000 {
+                assert!(yield_count >= 1, "{yield_count}");
+            }
+        }
+    }
+}
+
+#[test]
+fn sort_by_cancellable_cancel_mid_sort() {
+    use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+    for len in [1_000, 100_000, 1_000_000] {
+        let v = patterns::random(len);
+        let mut expected = v.clone();
+        expected.sort();
+
+        let cancel = AtomicBool::new(false);
+        let mut result = v.clone();
+        assert_eq!(
+            rust_ipnsort::sort_by_cancellable(&mut result, |a, b| a.cmp(b), &cancel),
+            Ok(())
+        );
+        assert_eq!(result, expected);
+
+        // Cancel after roughly one partition of the whole input.
+        let mut comparisons = 0;
+        let mut result = v.clone();
+        let res = rust_ipnsort::sort_by_cancellable(
+            &mut result,
+            |a, b| {
+                comparisons += 1;
+                if comparisons == len {
+                    cancel.store(true, AtomicOrdering::Relaxed);
+                }
+                a.cmp(b)
+            },
+            &cancel,
+        );
+
+        if len >= 1_000_000 {
+            assert_eq!(res, Err(rust_ipnsort::Cancelled));
+            assert!(comparisons < len * 4, "{comparisons}");
+        }
+        result.sort();
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn sort_by_budget_limits_comparisons() {
+    for len in [0, 1, 20, 1_000, 100_000] {
+        let v = patterns::random(len);
+        let mut expected = v.clone();
+        expected.sort();
+
+        // 4 * n * log2(n) is well above what the sort needs for random inputs.
+        let generous = 4 * (len as u64) * (len.max(2).ilog2() as u64 + 1);
+        let mut result = v.clone();
+        assert_eq!(
+            rust_ipnsort::sort_by_budget(&mut result, |a, b| a.cmp(b), generous),
+            Ok(())
+        );
+        assert_eq!(result, expected);
+
+        // Inputs of up to 20 elements are sorted before the budget is first checked.
+        if len <= 20 {
+            continue;
+        }
+
+        // The budget is checked between work items, the first one partitions the whole input.
+        let mut comparisons = 0u64;
+        let mut result = v.clone();
+        let res = rust_ipnsort::sort_by_budget(
+            &mut result,
+            |a, b| {
+                comparisons += 1;
+                a.cmp(b)
+            },
+            10,
+        );
+        assert_eq!(res, Err(rust_ipnsort::BudgetExceeded));
+        assert!(comparisons >= 10 && comparisons < 2 * len as u64);
+        result.sort();
+        assert_eq!(result, expected);
+    }
+
+    // A panic of the comparison function is not mistaken for an exceeded budget.
+    let mut v = patterns::random(100);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = rust_ipnsort::sort_by_budget(&mut v, |_, _| panic!("compare"), 1_000_000);
+    }));
+    assert!(res.is_err());
+}
+
+#[test]
+fn sort_session_matches_sort() {
+    use rust_ipnsort::{SortProgress, SortSession};
+
+    for len in [0, 1, 2, 20, 35, 1_000, 10_000] {
+        for v in [
+            patterns::random(len),
+            patterns::random_uniform(len, 0..=(len as i32 / 8)),
+            patterns::ascending(len),
+            patterns::descending(len),
+            patterns::saw_mixed(len, len.min(8)),
+        ] {
+            let mut expected = v.clone();
+            expected.sort();
+
+            for budget in [0, 1, 100, 1_000, usize::MAX] {
+                let mut result = v.clone();
+                let mut steps = 0;
+                let mut session = SortSession::new(&mut result, |a: &i32, b: &i32| a.cmp(b));
+
+                while session.step(budget) == SortProgress::InProgress {
+                    steps += 1;
+
+                    // Everything outside the unsorted ranges is final, and the unsorted ranges
+                    // hold the right elements.
+                    let ranges = session.unsorted_ranges().collect::<Vec<_>>();
+                    let mut current = session.as_slice().to_vec();
+                    let mut pos = 0;
+                    let mut sorted_ranges = ranges.clone();
+                    sorted_ranges.sort_by_key(|range| range.start);
+                    for range in sorted_ranges {
+                        assert_eq!(current[pos..range.start], expected[pos..range.start]);
+                        current[range.clone()].sort();
+                        assert_eq!(current[range.clone()], expected[range.clone()]);
+                        pos = range.end;
+                    }
+                    assert_eq!(current[pos..], expected[pos..]);
+                }
+
+                assert_eq!(session.unsorted_ranges().count(), 0);
+                assert_eq!(result, expected);
+                if budget == usize::MAX {
+                    assert_eq!(steps, 0);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn sort_strided_columns() {
+    /// Sorts column `col` of the row-major `matrix` by copying it out and back.
+    fn sort_column_copy<T: Ord + Clone>(matrix: &[T], col: usize, columns: usize) -> Vec<T> {
+        let mut result = matrix.to_vec();
+        let mut column = result[col..]
+            .iter()
+            .step_by(columns)
+            .cloned()
+            .collect::<Vec<_>>();
+        column.sort();
+        for (dest, val) in result[col..].iter_mut().step_by(columns).zip(column) {
+            *dest = val;
+        }
+
+        result
+    }
+
+    rust_ipnsort::sort_strided(&mut Vec::<i32>::new(), 0, 0, 3);
+
+    for (rows, columns) in [(1, 3), (2, 1), (2, 5), (20, 3), (100, 7), (1_000, 16)] {
+        let matrix = patterns::random(rows * columns);
+        let matrix_str = matrix
+            .iter()
+            .map(|val| format!("{val:010}"))
+            .collect::<Vec<_>>();
+
+        for col in 0..columns {
+            let mut result = matrix.clone();
+            rust_ipnsort::sort_strided(&mut result, col, rows, columns);
+            assert_eq!(result, sort_column_copy(&matrix, col, columns));
+
+            let mut result_str = matrix_str.clone();
+            rust_ipnsort::sort_strided(&mut result_str, col, rows, columns);
+            assert_eq!(result_str, sort_column_copy(&matrix_str, col, columns));
+        }
+    }
+
+    // Views that don't cover the whole buffer, the other elements must stay untouched.
+    let v = patterns::random(100);
+    let mut result = v.clone();
+    rust_ipnsort::sort_strided(&mut result, 5, 10, 9);
+    for (i, (a, b)) in result.iter().zip(&v).enumerate() {
+        if !(5..=5 + 9 * 9).contains(&i) || (i - 5) % 9 != 0 {
+            assert_eq!(a, b);
+        }
+    }
+    let mut view = result[5..]
+        .iter()
+        .copied()
+        .step_by(9)
+        .take(10)
+        .collect::<Vec<_>>();
+    assert!(view.windows(2).all(|w| w[0] <= w[1]));
+    view.sort();
+    let mut expected_view = v[5..]
+        .iter()
+        .copied()
+        .step_by(9)
+        .take(10)
+        .collect::<Vec<_>>();
+    expected_view.sort();
+    assert_eq!(view, expected_view);
+}
+
+#[test]
+fn sort_strided_panic_safe() {
+    let mut v = (0..300)
+        .map(|val| vec![val % 7, val])
+        .rev()
+        .collect::<Vec<_>>();
+    let mut expected = v.clone();
+    expected.sort();
+
+    thread_local! {
+        static PANIC_AFTER: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq)]
+    struct PanicOrd(Vec<i32>);
+
+    impl PartialOrd for PanicOrd {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for PanicOrd {
+        fn cmp(&self, other: &Self) -> Ordering {
+            if PANIC_AFTER.with(|val| val.replace(val.get().saturating_sub(1))) == 1 {
+                panic!("comparison limit reached");
+            }
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let mut wrapped = v.drain(..).map(PanicOrd).collect::<Vec<_>>();
+    PANIC_AFTER.with(|val| val.set(50));
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        rust_ipnsort::sort_strided(&mut wrapped, 1, 100, 3);
+    }));
+    assert!(res.is_err());
+
+    // All elements must still be present exactly once.
+    let mut unwrapped = wrapped.into_iter().map(|val| val.0).collect::<Vec<_>>();
+    unwrapped.sort();
+    assert_eq!(unwrapped, expected);
+}
+
+#[test]
+#[should_panic(expected = "strided view out of bounds")]
+fn sort_strided_out_of_bounds() {
+    let mut v = patterns::random(10);
+    rust_ipnsort::sort_strided(&mut v, 1, 4, 3);
+}
+
+#[test]
+fn sort_segments_unequal() {
+    let v = patterns::random(20_000);
+    let lens = patterns::random_uniform(500, 0..=80);
+
+    fn split<'a>(v: &'a mut [String], lens: &[i32]) -> Vec<&'a mut [String]> {
+        let mut segments = Vec::new();
+        let mut rest = v;
+        for &len in lens {
+            let (segment, tail) = rest.split_at_mut((len as usize).min(rest.len()));
+            segments.push(segment);
+            rest = tail;
+        }
+        segments.push(rest);
+        segments
+    }
+
+    let mut expected = v.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+    let mut result = expected.clone();
+    expected.sort();
+
+    // Empty segments at the start, in between and at the end.
+    let mut segments = split(&mut result, &lens);
+    segments.insert(0, &mut []);
+    segments.push(&mut []);
+    rust_ipnsort::sort_segments(&mut segments);
+    assert_eq!(result, expected);
+
+    // A single non-empty segment among empty ones.
+    let mut result = v.iter().rev().take(100).copied().collect::<Vec<_>>();
+    let mut expected = result.clone();
+    expected.sort();
+    let mut segments: Vec<&mut [i32]> = vec![&mut [], &mut result[..], &mut []];
+    rust_ipnsort::sort_segments(&mut segments);
+    assert_eq!(result, expected);
+
+    rust_ipnsort::sort_segments::<i32>(&mut []);
+    rust_ipnsort::sort_segments::<i32>(&mut [&mut [], &mut []]);
+}
+
+#[test]
+fn sort_segments_panic_safe() {
+    let v = (0..300).map(|val| vec![val % 7, val]).collect::<Vec<_>>();
+    let mut expected = v.clone();
+    expected.sort();
+
+    thread_local! {
+        static PANIC_AFTER: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq)]
+    struct PanicOrd(Vec<i32>);
+
+    impl PartialOrd for PanicOrd {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for PanicOrd {
+        fn cmp(&self, other: &Self) -> Ordering {
+            if PANIC_AFTER.with(|val| val.replace(val.get().saturating_sub(1))) == 1 {
+                panic!("comparison limit reached");
+            }
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let mut wrapped = v.into_iter().rev().map(PanicOrd).collect::<Vec<_>>();
+    let (a, rest) = wrapped.split_at_mut(17);
+    let (b, c) = rest.split_at_mut(200);
+    PANIC_AFTER.with(|val| val.set(50));
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        rust_ipnsort::sort_segments(&mut [a, &mut [], b, c]);
+    }));
+    assert!(res.is_err());
+
+    // All elements must still be present exactly once.
+    let mut unwrapped = wrapped.into_iter().map(|val| val.0).collect::<Vec<_>>();
+    unwrapped.sort();
+    assert_eq!(unwrapped, expected);
+}
+
+#[test]
+fn sort_with_comparator_object() {
+    struct Ascending;
+    struct Descending;
+
+    impl rust_ipnsort::Comparator<i32> for Ascending {
+        fn compare(&self, a: &i32, b: &i32) -> Ordering {
+            a.cmp(b)
+        }
+    }
+
+    impl rust_ipnsort::Comparator<i32> for Descending {
+        fn compare(&self, a: &i32, b: &i32) -> Ordering {
+            b.cmp(a)
+        }
+    }
+
+    for len in [0, 1, 2, 20, 50, 1_000, 10_000] {
+        let v = patterns::random(len);
+
+        let mut expected = v.clone();
+        expected.sort();
+
+        let mut result = v.clone();
+        rust_ipnsort::sort_with_comparator(&mut result, &Ascending);
+        assert_eq!(result, expected);
+
+        expected.reverse();
+        rust_ipnsort::sort_with_comparator(&mut result, &Descending);
+        assert_eq!(result, expected);
+    }
+}
+
+/// Runs the shared test suite for [`rust_ipnsort::sort_by_guaranteed`], which doesn't share the
+/// main recursion with [`rust_ipnsort::sort_by`].
+mod guaranteed {
+    use std::cmp::Ordering;
+
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::instantiate_sort_tests;
+
+    struct GuaranteedSortImpl;
+
+    impl sort_test_tools::Sort for GuaranteedSortImpl {
+        fn name() -> String {
+            "rust_ipnsort_guaranteed_unstable".into()
+        }
+
+        fn sort<T>(arr: &mut [T])
+        where
+            T: Ord,
+        {
+            rust_ipnsort::sort_by_guaranteed(arr, T::cmp);
+        }
+
+        fn sort_by<T, F>(arr: &mut [T], compare: F)
+        where
+            F: FnMut(&T, &T) -> Ordering,
+        {
+            rust_ipnsort::sort_by_guaranteed(arr, compare);
+        }
+    }
+
+    instantiate_sort_tests!(GuaranteedSortImpl);
+}
+
+/// Checks that the sorted postcondition injected by `sort_impl!` with the `assert_sorted` feature
+/// catches backends that silently produce wrong results.
+#[cfg(feature = "assert_sorted")]
+mod sorted_postcondition {
+    use sort_test_tools::Sort;
+
+    mod broken_backend {
+        use std::cmp::Ordering;
+
+        fn sort<T: Ord>(arr: &mut [T]) {
+            sort_by(arr, T::cmp);
+        }
+
+        // Deliberately broken, only swaps the first two elements.
+        fn sort_by<T, F: FnMut(&T, &T) -> Ordering>(arr: &mut [T], _compare: F) {
+            if arr.len() >= 2 {
+                arr.swap(0, 1);
+            }
+        }
+
+        sort_comp::sort_impl!("broken_backend");
+    }
+
+    use broken_backend::SortImpl as BrokenSortImpl;
+
+    #[test]
+    #[should_panic(expected = "broken_backend produced unsorted output at index 0")]
+    fn sort_catches_broken_backend() {
+        BrokenSortImpl::sort(&mut [1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "broken_backend produced unsorted output at index 1")]
+    fn sort_by_catches_broken_backend() {
+        BrokenSortImpl::sort_by(&mut [1, 2, 3], |a, b| b.cmp(a));
+    }
+
+    #[test]
+    fn sort_by_uses_comparator_direction() {
+        // Happens to produce the right result for the descending comparator.
+        let mut v = [1, 2];
+        BrokenSortImpl::sort_by(&mut v, |a, b| b.cmp(a));
+        assert_eq!(v, [2, 1]);
+    }
+}
+
+mod powersort {
+    use sort_comp::stable::rust_powersort;
+    use sort_test_tools::instantiate_sort_tests;
+
+    instantiate_sort_tests!(rust_powersort::SortImpl);
+
+    #[test]
+    fn merge_cost_within_entropy_bound() {
+        // Concatenated sorted runs with random lengths. Powersort guarantees a merge cost of at
+        // most `n * (H + 2)`, where `H` is the entropy of the run lengths. Runs that happen to join
+        // only lower the actual entropy.
+        let lens = sort_test_tools::patterns::random_uniform(500, 10..=5_000);
+        let mut v = Vec::new();
+        for &run_len in &lens {
+            let mut run = sort_test_tools::patterns::random(run_len as usize);
+            run.sort();
+            v.extend(run);
+        }
+
+        let len = v.len() as f64;
+        let entropy = lens
+            .iter()
+            .map(|&run_len| {
+                let p = run_len as f64 / len;
+                -p * p.log2()
+            })
+            .sum::<f64>();
+
+        let mut expected = v.clone();
+        expected.sort();
+
+        let merge_cost = rust_powersort::sort_by_merge_cost(&mut v, |a, b| a.cmp(b));
+        assert_eq!(v, expected);
+        assert!(
+            (merge_cost as f64) <= len * (entropy + 2.0),
+            "merge cost: {merge_cost} bound: {}",
+            len * (entropy + 2.0)
+        );
+
+        // A single run needs no merges at all.
+        assert_eq!(
+            rust_powersort::sort_by_merge_cost(&mut v, |a, b| a.cmp(b)),
+            0
+        );
+    }
+}
+
+#[cfg(feature = "rust_lsd_radix")]
+mod lsd_radix {
+    use sort_comp::other::rust_lsd_radix::{self, Radixable};
+    use sort_test_tools::patterns;
+
+    fn assert_key_order<T: Radixable + std::fmt::Debug>(
+        mut values: Vec<T>,
+        cmp: impl Fn(&T, &T) -> std::cmp::Ordering,
+    ) {
+        values.sort_by(&cmp);
+        for w in values.windows(2) {
+            assert_eq!(
+                w[0].radix_key().cmp(&w[1].radix_key()),
+                cmp(&w[0], &w[1]),
+                "{:?} {:?}",
+                w[0],
+                w[1]
+            );
+        }
+        // The bytes above `KEY_BYTES` are zero.
+        if T::KEY_BYTES < 8 {
+            assert!(values
+                .iter()
+                .all(|val| val.radix_key() >> (8 * T::KEY_BYTES) == 0));
+        }
+    }
+
+    #[test]
+    fn key_preserves_order() {
+        assert_key_order(
+            vec![
+                i32::MIN,
+                i32::MIN + 1,
+                -1_000,
+                -1,
+                0,
+                1,
+                1_000,
+                i32::MAX - 1,
+                i32::MAX,
+            ],
+            i32::cmp,
+        );
+        assert_key_order(vec![i8::MIN, -1, 0, 1, i8::MAX], i8::cmp);
+        assert_key_order(vec![i64::MIN, -1, 0, 1, i64::MAX], i64::cmp);
+        assert_key_order(
+            vec![0, 1, u32::MAX / 2, u32::MAX / 2 + 1, u32::MAX],
+            u32::cmp,
+        );
+
+        let floats = vec![
+            f32::NEG_INFINITY,
+            f32::MIN,
+            -1.5,
+            -f32::MIN_POSITIVE,
+            -1e-45,
+            -0.0,
+            0.0,
+            1e-45,
+            f32::MIN_POSITIVE,
+            1.5,
+            f32::MAX,
+            f32::INFINITY,
+            f32::NAN,
+            -f32::NAN,
+        ];
+        assert_key_order(floats.clone(), f32::total_cmp);
+        assert_key_order(floats.into_iter().map(f64::from).collect(), f64::total_cmp);
+
+        assert!((-0.0f32).radix_key() < 0.0f32.radix_key());
+        assert!((-1i32).radix_key() < 0i32.radix_key());
+    }
+
+    #[test]
+    fn sort_radix_matches_sort() {
+        for len in [0, 1, 2, 20, 32, 33, 100, 1_000, 10_000] {
+            for v in [
+                patterns::random(len),
+                patterns::random_uniform(len, 0..=16),
+                patterns::ascending(len),
+                patterns::descending(len),
+                patterns::all_equal(len),
+            ] {
+                let mut expected = v.clone();
+                expected.sort();
+                let mut result = v.clone();
+                rust_lsd_radix::sort_radix(&mut result);
+                assert_eq!(result, expected);
+
+                let v_u64 = v.iter().map(|&val| (val as u64) << 20).collect::<Vec<_>>();
+                let mut expected = v_u64.clone();
+                expected.sort();
+                let mut result = v_u64;
+                rust_lsd_radix::sort_radix(&mut result);
+                assert_eq!(result, expected);
+
+                let v_f64 = v
+                    .iter()
+                    .map(|&val| val as f64 / 7.0)
+                    .chain(
+                        [f64::NAN, -0.0, f64::NEG_INFINITY]
+                            .into_iter()
+                            .take(len.min(3)),
+                    )
+                    .collect::<Vec<_>>();
+                let mut expected = v_f64.clone();
+                expected.sort_by(f64::total_cmp);
+                let mut result = v_f64;
+                rust_lsd_radix::sort_radix(&mut result);
+                assert!(result
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| a.to_bits() == b.to_bits()));
+            }
+        }
+    }
+
+    #[test]
+    fn sort_radix_is_stable_by_key() {
+        // Only the first field is part of the key, the second one records the input position.
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        struct Keyed(u16, u32);
+
+        impl Radixable for Keyed {
+            const KEY_BYTES: usize = 2;
+
+            fn radix_key(self) -> u64 {
+                self.0 as u64
+            }
+        }
+
+        let keys = patterns::random_uniform(1_000, 0..=50);
+        let v = keys
+            .iter()
+            .enumerate()
+            .map(|(i, &key)| Keyed(key as u16, i as u32))
+            .collect::<Vec<_>>();
+
+        let mut expected = v.clone();
+        expected.sort_by_key(|val| val.0);
+        let mut result = v;
+        rust_lsd_radix::sort_radix(&mut result);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn sort_impl_sorts_integers() {
+        use sort_test_tools::Sort;
+
+        let mut v = patterns::random(10_000);
+        let mut expected = v.clone();
+        expected.sort();
+        rust_lsd_radix::SortImpl::sort(&mut v);
+        assert_eq!(v, expected);
+    }
+}
+
+#[cfg(feature = "cpp_boost_spreadsort")]
+mod boost_spreadsort {
+    use sort_comp::other::cpp_boost_spreadsort;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn round_trip_i32() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let mut v = patterns::random(len);
+            let mut expected = v.clone();
+            expected.sort();
+
+            cpp_boost_spreadsort::sort(&mut v);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn round_trip_u64() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let mut v = patterns::random(len)
+                .into_iter()
+                .map(|val| (val as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort();
+
+            cpp_boost_spreadsort::sort(&mut v);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn round_trip_f32() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let mut v = patterns::random(len)
+                .into_iter()
+                .map(|val| val as f32 / 7.0)
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort_by(f32::total_cmp);
+
+            cpp_boost_spreadsort::sort_float(&mut v);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn round_trip_f64() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let mut v = patterns::random(len)
+                .into_iter()
+                .map(|val| val as f64 / 7.0)
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort_by(f64::total_cmp);
+
+            cpp_boost_spreadsort::sort_float(&mut v);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Type not supported by boost spreadsort")]
+    fn unsupported_type_panics() {
+        let mut v = vec![String::from("b"), String::from("a")];
+        cpp_boost_spreadsort::sort(&mut v);
+    }
+
+    #[test]
+    #[should_panic(expected = "sort_by not supported by boost spreadsort")]
+    fn sort_by_panics() {
+        let mut v = vec![2, 1];
+        cpp_boost_spreadsort::sort_by(&mut v, i32::cmp);
+    }
+}
+
+#[cfg(feature = "cpp_gerbens_qsort")]
+mod gerbens_qsort {
+    use sort_comp::unstable::cpp_gerbens_qsort;
+    use sort_test_tools::ffi_types::FFIString;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn sort_by_reverse() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let v = patterns::random(len);
+            let mut expected = v.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            let mut result = v.clone();
+            cpp_gerbens_qsort::sort_by(&mut result, |a, b| b.cmp(a));
+            assert_eq!(result, expected);
+
+            let mut result_u64 = v.iter().map(|&val| val as u64).collect::<Vec<_>>();
+            cpp_gerbens_qsort::sort_by(&mut result_u64, |a, b| b.cmp(a));
+            assert!(result_u64.windows(2).all(|w| w[0] >= w[1]));
+
+            let mut result_str = v
+                .iter()
+                .map(|val| FFIString::new(format!("{val:010}")))
+                .collect::<Vec<_>>();
+            cpp_gerbens_qsort::sort_by(&mut result_str, |a, b| b.cmp(a));
+            assert!(result_str.windows(2).all(|w| w[0] >= w[1]));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Panic in comparison function")]
+    fn sort_by_propagates_panic() {
+        let mut v = patterns::random(1_000);
+        cpp_gerbens_qsort::sort_by(&mut v, |_, _| panic!("comparison failed"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Type not supported")]
+    fn unsupported_type_panics() {
+        let mut v = vec![String::from("b"), String::from("a")];
+        cpp_gerbens_qsort::sort_by(&mut v, |a, b| a.cmp(b));
+    }
+}
+
+#[cfg(feature = "cpp_ips4o")]
+mod ips4o {
+    use sort_comp::unstable::cpp_ips4o;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn sort_and_sort_by_i32() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let v = patterns::random(len);
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut result = v.clone();
+            cpp_ips4o::sort(&mut result);
+            assert_eq!(result, expected);
+
+            expected.reverse();
+            let mut result = v.clone();
+            cpp_ips4o::sort_by(&mut result, |a, b| b.cmp(a));
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Type not supported: cpp_ips4o_unstable only sorts")]
+    fn unsupported_type_panics() {
+        let mut v = vec![String::from("b"), String::from("a")];
+        cpp_ips4o::sort_by(&mut v, |a, b| a.cmp(b));
+    }
+}
+
+/// Cross-checks every enabled general purpose backend against `slice::sort`. The inputs are
+/// generated from the shared seed, set `OVERRIDE_SEED` to reproduce a failure.
+mod golden {
+    use sort_test_tools::{patterns, Sort};
+
+    /// Packs `key` into the upper and the position into the lower half, so elements with equal
+    /// keys are still distinguishable after sorting by key.
+    fn with_index(v: &[i32]) -> Vec<u64> {
+        v.iter()
+            .enumerate()
+            .map(|(i, &key)| (((key as u32) ^ (1 << 31)) as u64) << 32 | i as u64)
+            .collect()
+    }
+
+    fn check_backend<S: Sort>(inputs: &[(&str, Vec<i32>)], seed: u64) {
+        let name = S::name();
+
+        for (pattern_name, v) in inputs {
+            let context = format!(
+                "backend: {name} pattern: {pattern_name} len: {} seed: {seed}",
+                v.len()
+            );
+
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut result = v.clone();
+            S::sort(&mut result);
+            assert_eq!(result, expected, "sort {context}");
+
+            let mut result = v.clone();
+            S::sort_by(&mut result, |a, b| b.cmp(a));
+            assert!(result.iter().eq(expected.iter().rev()), "sort_by {context}");
+
+            // Sorting by the key only must produce the same key order, and keep every element.
+            // Distinct keys imply an identical order.
+            let v_indexed = with_index(v);
+            let mut result = v_indexed.clone();
+            S::sort_by(&mut result, |a, b| (a >> 32).cmp(&(b >> 32)));
+            assert!(
+                result
+                    .iter()
+                    .map(|x| x >> 32)
+                    .eq(expected.iter().map(|&x| ((x as u32) ^ (1 << 31)) as u64)),
+                "sort_by key order {context}"
+            );
+            result.sort();
+            let mut expected_indexed = v_indexed;
+            expected_indexed.sort();
+            assert_eq!(result, expected_indexed, "sort_by multiset {context}");
+        }
+    }
+
+    #[test]
+    fn all_backends_agree() {
+        let seed = patterns::random_init_seed();
+
+        let mut inputs = Vec::new();
+        for len in [0, 1, 2, 3, 7, 16, 20, 21, 35, 64, 100, 1_000, 10_000] {
+            inputs.push(("random", patterns::random(len)));
+            inputs.push(("random_d16", patterns::random_uniform(len, 0..=16)));
+            inputs.push((
+                "saw_mixed",
+                patterns::saw_mixed(len, ((len as f64).log2().round() as usize).max(1)),
+            ));
+            inputs.push(("ascending", patterns::ascending(len)));
+            inputs.push(("descending", patterns::descending(len)));
+        }
+
+        check_backend::<sort_comp::unstable::rust_ipnsort::SortImpl>(&inputs, seed);
+        check_backend::<sort_comp::unstable::rust_std::SortImpl>(&inputs, seed);
+        check_backend::<sort_comp::stable::rust_std::SortImpl>(&inputs, seed);
+        check_backend::<sort_comp::stable::rust_powersort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "ipnsort_variants")]
+        check_backend::<sort_comp::other::ipnsort_variants::guaranteed::SortImpl>(&inputs, seed);
+        #[cfg(feature = "rust_dmsort")]
+        check_backend::<sort_comp::unstable::rust_dmsort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "rust_crumsort_rs")]
+        check_backend::<sort_comp::unstable::rust_crumsort_rs::SortImpl>(&inputs, seed);
+        #[cfg(feature = "rust_tinysort")]
+        check_backend::<sort_comp::unstable::rust_tinysort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "rust_tinysort")]
+        check_backend::<sort_comp::stable::rust_tinysort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "rust_wpwoodjr")]
+        check_backend::<sort_comp::stable::rust_wpwoodjr::SortImpl>(&inputs, seed);
+        #[cfg(feature = "rust_glidesort")]
+        check_backend::<sort_comp::stable::rust_glidesort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "evolution")]
+        check_backend::<sort_comp::other::sort_evolution::stable::timsort_evo0::SortImpl>(
+            &inputs, seed,
+        );
+        #[cfg(feature = "evolution")]
+        check_backend::<sort_comp::other::sort_evolution::stable::timsort_evo1::SortImpl>(
+            &inputs, seed,
+        );
+        #[cfg(feature = "evolution")]
+        check_backend::<sort_comp::other::sort_evolution::stable::timsort_evo2::SortImpl>(
+            &inputs, seed,
+        );
+        #[cfg(feature = "evolution")]
+        check_backend::<sort_comp::other::sort_evolution::stable::timsort_evo3::SortImpl>(
+            &inputs, seed,
+        );
+        #[cfg(feature = "evolution")]
+        check_backend::<sort_comp::other::sort_evolution::stable::timsort_evo4::SortImpl>(
+            &inputs, seed,
+        );
+        #[cfg(feature = "cpp_pdqsort")]
+        check_backend::<sort_comp::unstable::cpp_pdqsort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_ips4o")]
+        check_backend::<sort_comp::unstable::cpp_ips4o::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_blockquicksort")]
+        check_backend::<sort_comp::unstable::cpp_blockquicksort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_gerbens_qsort")]
+        check_backend::<sort_comp::unstable::cpp_gerbens_qsort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "c_crumsort")]
+        check_backend::<sort_comp::unstable::c_crumsort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "c_fluxsort")]
+        check_backend::<sort_comp::stable::c_fluxsort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_std_sys")]
+        check_backend::<sort_comp::unstable::cpp_std_sys::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_std_sys")]
+        check_backend::<sort_comp::stable::cpp_std_sys::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_std_libcxx")]
+        check_backend::<sort_comp::unstable::cpp_std_libcxx::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_std_libcxx")]
+        check_backend::<sort_comp::stable::cpp_std_libcxx::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_std_gcc4_3")]
+        check_backend::<sort_comp::unstable::cpp_std_gcc4_3::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_std_gcc4_3")]
+        check_backend::<sort_comp::stable::cpp_std_gcc4_3::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_powersort")]
+        check_backend::<sort_comp::stable::cpp_powersort::SortImpl>(&inputs, seed);
+        #[cfg(feature = "cpp_powersort")]
+        check_backend::<sort_comp::stable::cpp_powersort_4way::SortImpl>(&inputs, seed);
+    }
+}
+
+/// Property tests of `rust_ipnsort::sort_by` against `slice::sort_unstable_by` as the model, for
+/// generated `Vec<i64>` inputs and comparators. Failures are shrunk to a minimal input before they
+/// are reported, set `OVERRIDE_SEED` to the reported seed to reproduce one.
+mod property {
+    use std::cmp::Ordering;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::patterns;
+
+    const CASES: usize = 500;
+
+    /// A consistent comparator, orders by `key`. `modulus` maps many values onto the same key, so
+    /// that the order has ties.
+    #[derive(Copy, Clone, Debug)]
+    struct Comparator {
+        mask: u64,
+        rotate: u32,
+        modulus: Option<u64>,
+        reverse: bool,
+    }
+
+    impl Comparator {
+        fn generate(rng: &mut StdRng) -> Self {
+            Self {
+                mask: if rng.gen_bool(0.5) { 0 } else { rng.gen() },
+                rotate: rng.gen_range(0..64),
+                modulus: if rng.gen_bool(0.5) {
+                    None
+                } else {
+                    Some(rng.gen_range(1..=64))
+                },
+                reverse: rng.gen_bool(0.3),
+            }
+        }
+
+        fn key(&self, val: i64) -> u64 {
+            let key = ((val as u64) ^ self.mask).rotate_left(self.rotate);
+            match self.modulus {
+                Some(modulus) => key % modulus,
+                None => key,
+            }
+        }
+
+        fn compare(&self, a: &i64, b: &i64) -> Ordering {
+            let ord = self.key(*a).cmp(&self.key(*b));
+            if self.reverse {
+                ord.reverse()
+            } else {
+                ord
+            }
+        }
+    }
+
+    fn generate_input(rng: &mut StdRng) -> Vec<i64> {
+        let len = if rng.gen_bool(0.9) {
+            rng.gen_range(0..=300)
+        } else {
+            rng.gen_range(300..=5_000)
+        };
+
+        match rng.gen_range(0..5) {
+            0 => (0..len).map(|_| rng.gen()).collect(),
+            // Duplicate heavy.
+            1 => {
+                let distinct = rng.gen_range(1..=8);
+                (0..len).map(|_| rng.gen_range(0..distinct)).collect()
+            }
+            // Mostly presorted, with a few random swaps.
+            2 | 3 => {
+                let mut v = (0..len as i64).collect::<Vec<_>>();
+                if rng.gen_bool(0.5) {
+                    v.reverse();
+                }
+                for _ in 0..rng.gen_range(0..=3) {
+                    if len >= 2 {
+                        v.swap(rng.gen_range(0..len), rng.gen_range(0..len));
+                    }
+                }
+                v
+            }
+            _ => patterns::saw_mixed(len, rng.gen_range(1..=8).min(len.max(1)))
+                .into_iter()
+                .map(i64::from)
+                .collect(),
+        }
+    }
+
+    /// Returns a description of the first violated property, if any.
+    fn check(input: &[i64], comparator: Comparator) -> Result<(), String> {
+        let mut result = input.to_vec();
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            rust_ipnsort::sort_by(&mut result, |a, b| comparator.compare(a, b))
+        }))
+        .map_err(|_| "sort_by panicked".to_string())?;
+
+        if let Some(i) = (1..result.len())
+            .find(|&i| comparator.compare(&result[i - 1], &result[i]) == Ordering::Greater)
+        {
+            return Err(format!("not sorted at index {i}"));
+        }
+
+        let mut expected = input.to_vec();
+        expected.sort_unstable_by(|a, b| comparator.compare(a, b));
+
+        // Elements that compare equal may be in any order, but the sequence of keys must match
+        // the model exactly.
+        if !result
+            .iter()
+            .map(|&val| comparator.key(val))
+            .eq(expected.iter().map(|&val| comparator.key(val)))
+        {
+            return Err("key order differs from sort_unstable_by".to_string());
+        }
+
+        result.sort();
+        expected.sort();
+        if result != expected {
+            return Err("not a permutation of the input".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Greedily removes chunks of elements and halves values towards zero, as long as the input
+    /// keeps failing.
+    fn shrink(mut input: Vec<i64>, is_failing: impl Fn(&[i64]) -> bool) -> Vec<i64> {
+        let mut chunk_len = input.len() / 2;
+        while chunk_len >= 1 {
+            let mut start = 0;
+            while start + chunk_len <= input.len() {
+                let mut candidate = input.clone();
+                candidate.drain(start..start + chunk_len);
+                if is_failing(&candidate) {
+                    input = candidate;
+                } else {
+                    start += chunk_len;
+                }
+            }
+            chunk_len /= 2;
+        }
+
+        for i in 0..input.len() {
+            while input[i] != 0 {
+                let mut candidate = input.clone();
+                candidate[i] /= 2;
+                if is_failing(&candidate) {
+                    input = candidate;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        input
+    }
+
+    fn run(cases: usize, mut generate: impl FnMut(&mut StdRng) -> (Vec<i64>, Comparator)) {
+        let seed = patterns::random_init_seed();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        // The hook would print every panic during shrinking.
+        let prev_hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+
+        let failure = (0..cases).find_map(|_| {
+            let (input, comparator) = generate(&mut rng);
+            check(&input, comparator).err().map(|_| {
+                let input = shrink(input, |v| check(v, comparator).is_err());
+                let err = check(&input, comparator).unwrap_err();
+                (input, comparator, err)
+            })
+        });
+
+        panic::set_hook(prev_hook);
+
+        if let Some((input, comparator, err)) = failure {
+            panic!("{err}, seed: {seed} comparator: {comparator:?} minimal input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn sort_by_matches_model() {
+        run(CASES, |rng| {
+            (generate_input(rng), Comparator::generate(rng))
+        });
+    }
+
+    #[test]
+    fn sort_by_matches_model_total_order() {
+        // Without ties the result must be identical to the model.
+        run(CASES, |rng| {
+            let comparator = Comparator {
+                modulus: None,
+                ..Comparator::generate(rng)
+            };
+            (generate_input(rng), comparator)
+        });
+    }
+
+    #[test]
+    fn shrink_finds_minimal_input() {
+        let input = vec![0, 17, -5, 0, 1_000, 3];
+        let minimal = shrink(input, |v| v.iter().any(|&val| val >= 10));
+        assert_eq!(minimal, [15]);
+    }
+}
+
+/// Small inputs that reach the unsafe pointer code, block partitioning, the merges and the panic
+/// paths, for every category of element type. Meant to be run under Miri as well, which is why the
+/// inputs are only as large as the branches they target require. Under Miri the three tests take
+/// about 20 minutes on one core, most of it in `partition_in_blocks_branches`:
+///
+/// `cargo +nightly miri test --test main miri::`
+mod miri {
+    use std::cell::Cell;
+    use std::fmt::Debug;
+
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::patterns;
+
+    /// Number of elements in a block of `partition_in_blocks`.
+    const BLOCK: usize = 256;
+
+    /// Neither `Copy` nor `Freeze`, takes the generic code paths.
+    #[derive(Clone, Debug)]
+    struct Tagged {
+        key: i32,
+        seen: Cell<u32>,
+    }
+
+    impl Tagged {
+        fn new(key: i32) -> Self {
+            Self {
+                key,
+                seen: Cell::new(0),
+            }
+        }
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.seen.set(self.seen.get() + 1);
+            other.seen.set(other.seen.get() + 1);
+            self.key.cmp(&other.key)
+        }
+    }
+
+    trait FromI32 {
+        fn from_i32(x: i32) -> Self;
+    }
+
+    impl FromI32 for i32 {
+        fn from_i32(x: i32) -> Self {
+            x
+        }
+    }
+
+    impl FromI32 for u128 {
+        fn from_i32(x: i32) -> Self {
+            ((x as u32 ^ (1 << 31)) as u128) << 64 | x as u32 as u128
+        }
+    }
+
+    impl FromI32 for String {
+        fn from_i32(x: i32) -> Self {
+            format!("{:010}", x as i64 - i32::MIN as i64)
+        }
+    }
+
+    impl FromI32 for Tagged {
+        fn from_i32(x: i32) -> Self {
+            Tagged::new(x)
+        }
+    }
+
+    /// Calls the generic `check` function with a small `Copy` type, a large `Copy` type, a heap
+    /// allocated type and a type with interior mutability.
+    macro_rules! for_each_type {
+        ($check:ident($($arg:expr),*)) => {{
+            $check::<i32>($($arg),*);
+            $check::<u128>($($arg),*);
+            $check::<String>($($arg),*);
+            $check::<Tagged>($($arg),*);
+        }};
+    }
+
+    fn check_partition<T: FromI32 + Ord + Debug>(values: &[i32], pivot: i32) {
+        let mut v = values.iter().map(|&x| T::from_i32(x)).collect::<Vec<_>>();
+        let pivot = T::from_i32(pivot);
+
+        let mid = rust_ipnsort::partition_blocks_by(&mut v, &pivot, &mut |a, b| a < b);
+        assert!(v[..mid].iter().all(|elem| elem < &pivot));
+        assert!(v[mid..].iter().all(|elem| elem >= &pivot));
+
+        let mut expected = values.iter().map(|&x| T::from_i32(x)).collect::<Vec<_>>();
+        expected.sort();
+        v.sort();
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn partition_in_blocks_branches() {
+        // Up to two full blocks take the `is_done` path right away, anything longer first goes
+        // through the logic for the remaining left or right block.
+        for len in [
+            0,
+            1,
+            2,
+            2 * BLOCK - 1,
+            2 * BLOCK,
+            2 * BLOCK + 1,
+            3 * BLOCK + 17,
+        ] {
+            let random = patterns::random(len);
+            let mut sorted = random.clone();
+            sorted.sort();
+
+            // Pivot below and above all values, so that every block has either none or all of
+            // its elements out of order.
+            for_each_type!(check_partition(&random, i32::MIN));
+            for_each_type!(check_partition(&random, i32::MAX));
+
+            // Balanced, left- and right-heavy splits, each block ends up with a different count.
+            for pivot_pos in [len / 10, len / 2, len - len / 10] {
+                if let Some(&pivot) = sorted.get(pivot_pos) {
+                    for_each_type!(check_partition(&random, pivot));
+                }
+            }
+
+            // Exactly one out-of-order element in every block, on the left and on the right.
+            let one_per_block = (0..len)
+                .map(|i| if i % BLOCK == BLOCK / 2 { 2 } else { 0 })
+                .collect::<Vec<_>>();
+            for_each_type!(check_partition(&one_per_block, 1));
+            let inverse = one_per_block.iter().map(|&x| 2 - x).collect::<Vec<_>>();
+            for_each_type!(check_partition(&inverse, 1));
+
+            for_each_type!(check_partition(&patterns::ascending(len), len as i32 / 2));
+            for_each_type!(check_partition(&patterns::descending(len), len as i32 / 2));
+        }
+    }
+
+    fn check_sort<T: FromI32 + Ord + Debug>(values: &[i32]) {
+        let mut v = values.iter().map(|&x| T::from_i32(x)).collect::<Vec<_>>();
+        rust_ipnsort::sort(&mut v);
+
+        let mut expected = values.to_vec();
+        expected.sort();
+        let expected = expected.into_iter().map(T::from_i32).collect::<Vec<_>>();
+        assert_eq!(v, expected);
+    }
+
+    #[test]
+    fn sort_small_and_merges() {
+        // Every small-sort length, which covers the sorting networks, the bidirectional merges
+        // and the insertion sort tails.
+        for len in 0..=40 {
+            for_each_type!(check_sort(&patterns::random(len)));
+            for_each_type!(check_sort(&patterns::random_uniform(len, 0..=3)));
+        }
+
+        // Few presorted runs are merged in place with the rotation merge, the rest goes through
+        // quicksort.
+        let len = if cfg!(miri) { 300 } else { 3_000 };
+        let mut three_runs = patterns::random(len);
+        three_runs[..len / 3].sort();
+        three_runs[len / 3..len / 2].sort();
+        three_runs[len / 2..].sort();
+        for_each_type!(check_sort(&three_runs));
+        for_each_type!(check_sort(&patterns::random(len)));
+        for_each_type!(check_sort(&patterns::saw_mixed(len, 8)));
+    }
+
+    thread_local! {
+        static DROP_COUNT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct DropCounted(String);
+
+    impl Drop for DropCounted {
+        fn drop(&mut self) {
+            DROP_COUNT.with(|count| count.set(count.get() + 1));
+        }
+    }
+
+    /// Panics in the comparison after `panic_after` successful ones, and checks that every
+    /// element is still owned by `v` exactly once.
+    fn check_panic(
+        values: &[i32],
+        panic_after: usize,
+        f: impl FnOnce(&mut [DropCounted], &mut dyn FnMut(&DropCounted, &DropCounted) -> bool),
+    ) {
+        let mut v = values
+            .iter()
+            .map(|&x| DropCounted(String::from_i32(x)))
+            .collect::<Vec<_>>();
+        DROP_COUNT.with(|count| count.set(0));
+
+        let mut comparisons = 0;
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            f(&mut v, &mut |a, b| {
+                if comparisons == panic_after {
+                    panic!("comparison limit reached");
+                }
+                comparisons += 1;
+                a < b
+            });
+        }));
+        assert!(res.is_err() || comparisons <= panic_after);
+
+        let mut result = v.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
+        assert_eq!(DROP_COUNT.with(|count| count.get()), 0);
+        drop(v);
+        assert_eq!(DROP_COUNT.with(|count| count.get()), values.len());
+
+        let mut expected = values
+            .iter()
+            .map(|&x| String::from_i32(x))
+            .collect::<Vec<_>>();
+        expected.sort();
+        result.sort();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn panic_positions() {
+        for len in [2, 20, 40, 2 * BLOCK + 1] {
+            let v = patterns::random(len);
+            let pivot = DropCounted(String::from_i32(v[len / 2]));
+
+            for panic_after in [0, 1, len / 2, len, 4 * len] {
+                check_panic(&v, panic_after, |v, is_less| {
+                    rust_ipnsort::sort_by(v, |a, b| {
+                        if is_less(a, b) {
+                            std::cmp::Ordering::Less
+                        } else {
+                            std::cmp::Ordering::Greater
+                        }
+                    })
+                });
+                check_panic(&v, panic_after, |v, is_less| {
+                    rust_ipnsort::partition_blocks_by(v, &pivot, &mut |a, b| is_less(a, b));
+                });
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pivot_trace")]
+mod pivot_trace {
+    use std::ops::Range;
+
+    use sort_comp::unstable::rust_ipnsort::{self, PivotTree};
+    use sort_test_tools::patterns;
+
+    /// Appends the ranges of the leaves below `node` to `out`, in order.
+    fn collect_leaves<T>(tree: &PivotTree<T>, node: usize, out: &mut Vec<Range<usize>>) {
+        let node = &tree.nodes[node];
+        if node.children.is_empty() {
+            out.push(node.range.clone());
+        }
+        for &child in &node.children {
+            collect_leaves(tree, child, out);
+        }
+    }
+
+    #[test]
+    fn leaves_tile_slice() {
+        for len in [0, 1, 20, 100, 1_000, 10_000] {
+            for v in [
+                patterns::random(len),
+                patterns::random_uniform(len, 0..=16),
+                patterns::saw_mixed(len, len.min(8)),
+                patterns::ascending(len),
+                patterns::descending(len),
+            ] {
+                let mut sorted = v.clone();
+                let tree = rust_ipnsort::sort_by_traced(&mut sorted, i32::cmp);
+
+                let mut expected = v.clone();
+                expected.sort();
+                assert_eq!(sorted, expected);
+
+                assert_eq!(tree.nodes[0].range, 0..len);
+                let mut leaves = Vec::new();
+                collect_leaves(&tree, 0, &mut leaves);
+                assert_eq!(leaves.first().unwrap().start, 0);
+                assert_eq!(leaves.last().unwrap().end, len);
+                assert!(leaves.windows(2).all(|pair| pair[0].end == pair[1].start));
+                assert!(leaves.iter().all(|leaf| !leaf.is_empty() || len == 0));
+
+                for node in &tree.nodes {
+                    if node.children.is_empty() {
+                        assert!(node.pivot.is_none() && node.split_ratio.is_none());
+                        continue;
+                    }
+
+                    let ratio = node.split_ratio.unwrap();
+                    assert!((0.0..=1.0).contains(&ratio));
+
+                    // After sorting, the elements in front of the pivot are exactly the smaller
+                    // ones in the sub-slice.
+                    let pivot = node.pivot.unwrap();
+                    let smaller = sorted[node.range.clone()]
+                        .iter()
+                        .filter(|&&elem| elem < pivot)
+                        .count();
+                    assert_eq!(smaller, (ratio * node.range.len() as f64).round() as usize);
+                }
+            }
+        }
+
+        // Random inputs go through the main loop, and are not only a root.
+        let mut v = patterns::random(10_000);
+        let tree = rust_ipnsort::sort_by_traced(&mut v, i32::cmp);
+        assert!(tree.nodes.len() > 1);
+    }
+}
+
+#[cfg(feature = "pod_bytes")]
+mod pod_bytes {
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn matches_sort_for_byte_ordered_types() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+                let mut keys = v
+                    .iter()
+                    .map(|&val| {
+                        let mut key = [0xAB; 16];
+                        key[12..].copy_from_slice(&(val as u32).to_be_bytes());
+                        key
+                    })
+                    .collect::<Vec<_>>();
+                let mut expected = keys.clone();
+                expected.sort();
+                rust_ipnsort::sort_pod_bytes(&mut keys);
+                assert_eq!(keys, expected);
+
+                // Not a power of two in size, sorted with the byte-wise comparison.
+                let mut keys = v
+                    .iter()
+                    .map(|&val| {
+                        let [a, b, c, _] = (val as u32).to_be_bytes();
+                        [a, b, c]
+                    })
+                    .collect::<Vec<_>>();
+                let mut expected = keys.clone();
+                expected.sort();
+                rust_ipnsort::sort_pod_bytes(&mut keys);
+                assert_eq!(keys, expected);
+
+                let mut keys = v
+                    .iter()
+                    .map(|&val| (val as u32).to_be())
+                    .collect::<Vec<_>>();
+                rust_ipnsort::sort_pod_bytes(&mut keys);
+                assert!(keys
+                    .windows(2)
+                    .all(|w| u32::from_be(w[0]) <= u32::from_be(w[1])));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "run_merge")]
+mod run_merge {
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::patterns;
+
+    use crate::sort_count_comparisons;
+
+    #[test]
+    fn concatenated_sorted_sequences() {
+        let len = 100_000;
+        for saw_count in [4, 16, 32, 33, 200] {
+            let v = patterns::saw_mixed(len, saw_count);
+            let mut expected = v.clone();
+            expected.sort();
+
+            let (result, comparisons) =
+                sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), i32::cmp);
+            assert_eq!(result, expected);
+
+            // One scan to find the runs, and one comparison per element and level of merging.
+            if saw_count <= 32 {
+                let max_comparisons = len as u64 * (1 + saw_count.ilog2() as u64);
+                assert!(comparisons <= max_comparisons, "{comparisons}");
+            }
+
+            let mut v_str = v.iter().map(|val| format!("{val:010}")).collect::<Vec<_>>();
+            let mut expected_str = v_str.clone();
+            expected_str.sort();
+            rust_ipnsort::sort(&mut v_str);
+            assert_eq!(v_str, expected_str);
+        }
+    }
+}
+
+#[cfg(feature = "partition")]
+mod fulcrum_partition {
+    use sort_comp::other::partition::{fulcrum_partition_revised, Partition};
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn matches_revised() {
+        for len in [65, 100, 255, 256, 257, 1_000, 10_000, 100_000] {
+            for v in [patterns::random(len), patterns::random_uniform(len, 0..=16)] {
+                let pivot = v[len / 2];
+
+                let mut result = v.clone();
+                let mid =
+                    rust_ipnsort::partition_fulcrum_by(&mut result, &pivot, &mut |a, b| a < b);
+
+                let mut expected = v.clone();
+                let expected_mid =
+                    <fulcrum_partition_revised::PartitionImpl as Partition>::partition(
+                        &mut expected,
+                        &pivot,
+                    );
+
+                assert_eq!(mid, expected_mid);
+                if len >= 256 {
+                    // Same rotation buffer size, so the layout must be identical too.
+                    assert_eq!(result, expected);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "paranoid")]
+mod paranoid {
+    use sort_comp::unstable::rust_ipnsort;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn block_partition_invariants() {
+        // Neither `String` nor a 16 byte tuple use fulcrum partitioning, so every partition goes
+        // through `partition_in_blocks` and its assertions. Lengths around multiples of the block
+        // size hit all the ways the last blocks can be split.
+        let lens = (0..1_200)
+            .chain((1..40).map(|i| i * 256 + i % 3))
+            .chain([100_000]);
+
+        for len in lens {
+            for v in [
+                patterns::random(len),
+                patterns::random_uniform(len, 0..=4),
+                patterns::saw_mixed(len, 1 + len / 300),
+                patterns::descending(len),
+            ] {
+                let mut expected = v.clone();
+                expected.sort();
+
+                let mut tuples = v.iter().map(|&x| (x as i64, 0u64)).collect::<Vec<_>>();
+                rust_ipnsort::sort(&mut tuples);
+                assert!(tuples
+                    .iter()
+                    .map(|t| t.0 as i32)
+                    .eq(expected.iter().copied()));
+
+                let mut strings = v.iter().map(|x| format!("{x:011}")).collect::<Vec<_>>();
+                let mut expected_strings = strings.clone();
+                expected_strings.sort();
+                rust_ipnsort::sort(&mut strings);
+                assert_eq!(strings, expected_strings);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rust_crumsort_rs")]
+mod crumsort_rs {
+    use sort_comp::unstable::rust_crumsort_rs;
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn sort_by_reverse() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let v = patterns::random(len);
+            let mut expected = v.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            let mut result = v.clone();
+            rust_crumsort_rs::sort_by(&mut result, |a, b| b.cmp(a));
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn unsupported_type_falls_back() {
+        // String is not Copy, the crate can't sort it directly.
+        let v = patterns::random(1_000)
+            .iter()
+            .map(|val| format!("{val:010}"))
+            .collect::<Vec<_>>();
+        let mut expected = v.clone();
+        expected.sort();
+
+        let mut result = v.clone();
+        rust_crumsort_rs::sort(&mut result);
+        assert_eq!(result, expected);
+
+        let mut result_by = v;
+        rust_crumsort_rs::sort_by(&mut result_by, |a, b| b.cmp(a));
+        expected.reverse();
+        assert_eq!(result_by, expected);
+    }
+}
+
+#[cfg(feature = "rust_dmsort")]
+mod dmsort {
+    use sort_comp::unstable::{rust_dmsort, rust_ipnsort};
+    use sort_test_tools::patterns;
+
+    use super::sort_count_comparisons;
+
+    #[test]
+    fn sort_by_reverse() {
+        for len in [0, 1, 2, 20, 1_000, 100_000] {
+            let v = patterns::random(len)
+                .iter()
+                .map(|val| val.to_string())
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            let mut result = v;
+            rust_dmsort::sort_by(&mut result, |a, b| b.cmp(a));
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn nearly_sorted() {
+        // Ascending with 1% of the elements replaced by random values, spread over the whole
+        // slice.
+        let len = 1_000_000;
+        let mut v = patterns::ascending(len);
+        let noise = patterns::random(len / 100);
+        for (i, val) in noise.iter().enumerate() {
+            v[i * 100 + (*val as usize % 100)] = *val;
+        }
+
+        let compare = |a: &i32, b: &i32| a.cmp(b);
+        let (result_dmsort, comparisons_dmsort) =
+            sort_count_comparisons(&v, |v, cmp| rust_dmsort::sort_by(v, cmp), compare);
+        let (result_ipnsort, comparisons_ipnsort) =
+            sort_count_comparisons(&v, |v, cmp| rust_ipnsort::sort_by(v, cmp), compare);
+
+        assert_eq!(result_dmsort, result_ipnsort);
+
+        // Only the dropped 1% need a real sort, the rest is one pass plus a merge.
+        assert!(
+            comparisons_dmsort < comparisons_ipnsort,
+            "dmsort: {comparisons_dmsort} ipnsort: {comparisons_ipnsort}"
+        );
+    }
+}
+
+#[cfg(feature = "rust_tinysort")]
+mod tinysort {
+    use sort_comp::{stable, unstable};
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn primitive() {
+        for len in [0, 1, 2, 20, 1_000, 10_000] {
+            let v = patterns::random(len);
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut result = v.clone();
+            stable::rust_tinysort::sort(&mut result);
+            assert_eq!(result, expected);
+
+            let mut result = v.clone();
+            unstable::rust_tinysort::sort_by(&mut result, |a, b| a.cmp(b));
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn string() {
+        // Non-primitive types are supported directly, without any fallback.
+        for len in [0, 1, 2, 20, 1_000, 10_000] {
+            let v = patterns::random(len)
+                .iter()
+                .map(|val| val.to_string())
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort();
+
+            let mut result = v.clone();
+            stable::rust_tinysort::sort_by(&mut result, |a, b| a.cmp(b));
+            assert_eq!(result, expected);
+
+            let mut result = v.clone();
+            unstable::rust_tinysort::sort(&mut result);
+            assert_eq!(result, expected);
+        }
+    }
+}
+
+#[cfg(feature = "rust_glidesort")]
+mod glidesort {
+    use sort_comp::stable::rust_glidesort::{self, GlideBufferPolicy};
+    use sort_test_tools::patterns;
+
+    #[test]
+    fn buffer_policy_len() {
+        assert_eq!(
+            GlideBufferPolicy::FractionOfLen(0.5).buffer_len::<u64>(1_000),
+            500
+        );
+        assert_eq!(
+            GlideBufferPolicy::FractionOfLen(0.5).buffer_len::<u64>(1_001),
+            501
+        );
+        assert_eq!(
+            GlideBufferPolicy::FractionOfLen(2.0).buffer_len::<u64>(1_000),
+            1_000
+        );
+        assert_eq!(
+            GlideBufferPolicy::FixedElems(64).buffer_len::<u64>(1_000),
+            64
+        );
+        assert_eq!(GlideBufferPolicy::default().buffer_len::<u64>(1_000), 500);
+
+        // Auto is capped for large inputs.
+        let auto_len = GlideBufferPolicy::Auto.buffer_len::<u64>(usize::MAX / 2);
+        assert!(auto_len < usize::MAX / 8 && auto_len > 0);
+    }
+
+    #[test]
+    fn sort_with_policies() {
+        let policies = [
+            GlideBufferPolicy::FixedElems(0),
+            GlideBufferPolicy::FixedElems(1),
+            GlideBufferPolicy::FixedElems(100),
+            GlideBufferPolicy::FractionOfLen(0.1),
+            GlideBufferPolicy::FractionOfLen(0.5),
+            GlideBufferPolicy::FractionOfLen(1.0),
+            GlideBufferPolicy::Auto,
+        ];
+
+        for len in [0, 1, 2, 20, 1_000, 10_000] {
+            let v = patterns::random(len)
+                .iter()
+                .map(|val| val.to_string())
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+            expected.sort();
+
+            for policy in policies {
+                let mut result = v.clone();
+                rust_glidesort::sort_with_policy(&mut result, |a, b| a.cmp(b), policy);
+                assert_eq!(result, expected);
+            }
+        }
+    }
+}